@@ -10,9 +10,12 @@
 //! assert_eq!(localized.get_default(), Some("Default string goes here").as_ref());
 //!
 //! // Insert string with locale
-//! localized.insert(Locale::new("en-US"), "String for en_US");
-//! assert_eq!(localized["en_US"], "String for en_US");
-//! assert_eq!(localized["en-US"], "String for en_US");  // `-` and `_` both works
+//! localized.insert(Locale::new("en-US"), "String for en-US");
+//! assert_eq!(localized["en-US"], "String for en-US");
+//! assert_eq!(localized["en_US"], "String for en-US");  // `-` and `_` both works
+//!
+//! // Locales are always written in the canonical BCP 47 form
+//! assert_eq!(Locale::new("EN_us").to_string(), "en-US");
 //!
 //! // Fallbacks to the default value with unknown locales
 //! assert_eq!(localized["zh_CN"], "Default string goes here");
@@ -21,8 +24,11 @@
 
 mod de;
 pub mod error;
+#[cfg(feature = "schemars")]
+mod schema;
 mod ser;
 
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::hash::Hash;
@@ -31,11 +37,24 @@ use std::str::FromStr;
 
 pub use error::LocaleError;
 
+/// UN M.49 codes of areas used as regions, like `419` for Latin America
+#[cfg(feature = "iso-codes")]
+const M49_AREAS: [&str; 31] = [
+    "001", "002", "003", "005", "009", "011", "013", "014", "015", "017", "018", "019", "021",
+    "029", "030", "034", "035", "039", "053", "054", "057", "061", "142", "143", "145", "150",
+    "151", "154", "155", "202", "419",
+];
+
 /// Simple representation of a locale
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+///
+/// Both `zh_CN` and `zh-CN` are accepted, and the locale is always displayed in the canonical
+/// BCP 47 form, `zh-CN`. The original spelling is kept for linting, but does not take part in
+/// comparisons.
+#[derive(Clone, Debug)]
 pub struct Locale {
     lang: String,
     region: Option<String>,
+    original: Option<String>,
 }
 
 /// Container for localized data
@@ -48,12 +67,20 @@ pub struct Localized<T> {
 impl Locale {
     /// Created a new instance of `Locale`
     pub fn new<S: AsRef<str>>(locale: S) -> Self {
-        let locale_str = locale.as_ref().replace('-', "_");
-        let (lang, region) = match locale_str.split_once('_') {
+        let locale_str = locale.as_ref().replace('_', "-");
+        let (lang, region) = match locale_str.split_once('-') {
             Some((l, r)) => (l.to_lowercase(), Some(r.to_uppercase())),
-            None => (locale_str, None),
+            None => (locale_str.to_lowercase(), None),
         };
-        Self { lang, region }
+        let mut result = Self {
+            lang,
+            region,
+            original: None,
+        };
+        if result.to_string() != locale.as_ref() {
+            result.original = Some(locale.as_ref().to_string());
+        }
+        result
     }
 
     /// Get the language part of the `Locale`
@@ -65,6 +92,53 @@ impl Locale {
     pub fn get_region(&self) -> Option<&str> {
         self.region.as_deref()
     }
+
+    /// Get the original spelling of the `Locale`, `None` if it is already canonical
+    pub fn get_original(&self) -> Option<&str> {
+        self.original.as_deref()
+    }
+
+    /// Is the `Locale` made of a well-formed language and region
+    ///
+    /// The language must be 2 or 3 letters, and the region, if any, 2 letters or 3 digits. With the
+    /// `iso-codes` feature, they must also be known codes: an ISO 639-1 language, or an ISO 639-3
+    /// one like `yue`, and an ISO 3166-1 alpha-2 region, or a UN M.49 area like `419`.
+    pub fn is_valid(&self) -> bool {
+        let lang_ok =
+            matches!(self.lang.len(), 2 | 3) && self.lang.bytes().all(|b| b.is_ascii_lowercase());
+        let region_ok = match &self.region {
+            None => true,
+            Some(r) if r.len() == 3 => r.bytes().all(|b| b.is_ascii_digit()),
+            Some(r) => r.len() == 2 && r.bytes().all(|b| b.is_ascii_uppercase()),
+        };
+        lang_ok && region_ok && self.is_known()
+    }
+
+    /// Are the language and region known codes, see [`Locale::is_valid`]
+    #[cfg(feature = "iso-codes")]
+    fn is_known(&self) -> bool {
+        let lang_ok = match self.lang.len() {
+            2 => isolang::Language::from_639_1(&self.lang).is_some(),
+            _ => isolang::Language::from_639_3(&self.lang).is_some(),
+        };
+        let region_ok = match &self.region {
+            None => true,
+            Some(r) if r.len() == 3 => M49_AREAS.contains(&r.as_str()),
+            Some(r) => isocountry::CountryCode::for_alpha2(r).is_ok(),
+        };
+        lang_ok && region_ok
+    }
+
+    /// Are the language and region known codes, always true without the `iso-codes` feature
+    #[cfg(not(feature = "iso-codes"))]
+    fn is_known(&self) -> bool {
+        true
+    }
+
+    /// Is the `Locale` spelled in the canonical BCP 47 form
+    pub fn is_canonical(&self) -> bool {
+        self.original.is_none()
+    }
 }
 
 impl FromStr for Locale {
@@ -78,12 +152,32 @@ impl FromStr for Locale {
 impl Display for Locale {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.region {
-            Some(r) => write!(f, "{}_{}", self.lang, r),
+            Some(r) => write!(f, "{}-{}", self.lang, r),
             None => write!(f, "{}", self.lang),
         }
     }
 }
 
+impl PartialEq for Locale {
+    fn eq(&self, other: &Self) -> bool {
+        self.lang == other.lang && self.region == other.region
+    }
+}
+
+impl Eq for Locale {}
+
+impl PartialOrd for Locale {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Locale {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.lang, &self.region).cmp(&(&other.lang, &other.region))
+    }
+}
+
 impl Hash for Locale {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.lang.hash(state);
@@ -136,6 +230,38 @@ impl<T> Localized<T> {
     pub fn get_default(&self) -> Option<&T> {
         self.default.as_ref()
     }
+
+    /// Iterate over locales with a translation, the default value excluded
+    pub fn translated_locales(&self) -> impl Iterator<Item = &Locale> {
+        self.content.keys()
+    }
+
+    /// Check if there is any translation besides the default value
+    pub fn is_translated(&self) -> bool {
+        !self.content.is_empty()
+    }
+
+    /// Get the value for the first available locale in `locales`
+    ///
+    /// Each locale is tried as is, then with its language only (`zh-TW`, then `zh`), following
+    /// BCP 47 lookup. Falls back to the default value, then to the first available translation.
+    /// Returns `None` only if the container is empty.
+    pub fn get_with_fallback(&self, locales: &[Locale]) -> Option<&T> {
+        locales
+            .iter()
+            .find_map(|locale| {
+                self.content.get(locale).or_else(|| {
+                    locale.region.as_ref()?;
+                    self.content.get(&Locale {
+                        lang: locale.lang.clone(),
+                        region: None,
+                        original: None,
+                    })
+                })
+            })
+            .or(self.default.as_ref())
+            .or_else(|| self.content.values().next())
+    }
 }
 
 impl<T> Index<&Locale> for Localized<T> {
@@ -163,6 +289,12 @@ where
     }
 }
 
+impl<T> Default for Localized<T> {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
 impl<T: PartialEq> PartialEq for Localized<T> {
     fn eq(&self, other: &Self) -> bool {
         self.default.eq(&other.default) && self.content.eq(&other.content)
@@ -171,6 +303,13 @@ impl<T: PartialEq> PartialEq for Localized<T> {
 
 impl<T: Eq> Eq for Localized<T> {}
 
+impl<T: Hash> Hash for Localized<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.default.hash(state);
+        self.content.hash(state);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::{Locale, Localized};
@@ -191,6 +330,99 @@ mod test {
         assert_eq!(localized["zh-CN"], "乌龟");
         assert_eq!(localized["en-US"], "Turtle");
         assert_eq!(localized["j-J"], "Default");
+        assert!(localized.is_translated());
+        assert_eq!(
+            localized
+                .translated_locales()
+                .map(|l| l.to_string())
+                .collect::<Vec<_>>(),
+            ["en-US", "zh-CN", "zh-TW"]
+        );
+        assert!(!Localized::new(Some("Default")).is_translated());
+    }
+
+    #[test]
+    fn test_canonical() {
+        let underscore = Locale::new("zh_CN");
+        let hyphen = Locale::new("zh-CN");
+        assert_eq!(underscore, hyphen);
+        assert_eq!(underscore.to_string(), "zh-CN");
+        assert_eq!(underscore.get_original(), Some("zh_CN"));
+        assert!(!underscore.is_canonical());
+        assert!(hyphen.is_canonical());
+        assert_eq!(Locale::new("ZH").to_string(), "zh");
+        assert!(!Locale::new("zh-cn").is_canonical());
+    }
+
+    #[test]
+    fn test_valid() {
+        for locale in ["zh", "zh_CN", "zh-TW", "yue-HK", "es-419", "EN_us"] {
+            assert!(Locale::new(locale).is_valid(), "{}", locale);
+        }
+        for locale in [
+            "",
+            "z",
+            "chinese",
+            "zh-",
+            "zh-C",
+            "zh-CHN",
+            "zh-12",
+            "zh-Hans-CN",
+            "z1-CN",
+            "zh-C1",
+            "中文",
+        ] {
+            assert!(!Locale::new(locale).is_valid(), "{}", locale);
+        }
+        // Well-formed, but unknown codes
+        for locale in ["zh_XN", "xx-CN", "zzz", "es-999"] {
+            assert_eq!(
+                Locale::new(locale).is_valid(),
+                cfg!(not(feature = "iso-codes")),
+                "{}",
+                locale
+            );
+        }
+    }
+
+    #[test]
+    fn test_fallback() {
+        let localized = Localized::<String> {
+            default: Some("Turtle".into()),
+            content: BTreeMap::from([
+                (Locale::new("zh_CN"), "乌龟".into()),
+                (Locale::new("zh_TW"), "烏龜".into()),
+            ]),
+        };
+        let zh_tw = [Locale::new("zh_TW"), Locale::new("zh_CN")];
+        let zh_hk = [Locale::new("zh_HK"), Locale::new("zh_CN")];
+        let en = [Locale::new("en_US")];
+        assert_eq!(localized.get_with_fallback(&zh_tw).unwrap(), "烏龜");
+        assert_eq!(localized.get_with_fallback(&zh_hk).unwrap(), "乌龟");
+        assert_eq!(localized.get_with_fallback(&en).unwrap(), "Turtle");
+        assert_eq!(localized.get_with_fallback(&[]).unwrap(), "Turtle");
+
+        let no_default = Localized::<String> {
+            default: None,
+            ..localized
+        };
+        assert_eq!(no_default.get_with_fallback(&en).unwrap(), "乌龟");
+        assert_eq!(Localized::<String>::new(None).get_with_fallback(&en), None);
+
+        let language_only = Localized::<String> {
+            default: Some("Turtle".into()),
+            content: BTreeMap::from([
+                (Locale::new("zh"), "乌龟".into()),
+                (Locale::new("zh_TW"), "烏龜".into()),
+            ]),
+        };
+        let zh_cn = [Locale::new("zh_CN")];
+        assert_eq!(language_only.get_with_fallback(&zh_cn).unwrap(), "乌龟");
+        assert_eq!(language_only.get_with_fallback(&zh_tw).unwrap(), "烏龜");
+        // The language of a preferred locale wins over the next preferred locale
+        let zh_hk_tw = [Locale::new("zh_HK"), Locale::new("zh_TW")];
+        assert_eq!(language_only.get_with_fallback(&zh_hk_tw).unwrap(), "乌龟");
+        assert_eq!(language_only.get_with_fallback(&en).unwrap(), "Turtle");
     }
 
     #[test]
@@ -208,9 +440,9 @@ mod test {
                 Token::Map { len: Some(3) },
                 Token::Str("default"),
                 Token::Str("Grass"),
-                Token::Str("ja_CN"),
+                Token::Str("ja-CN"),
                 Token::Str("Kusa"),
-                Token::Str("zh_CN"),
+                Token::Str("zh-CN"),
                 Token::Str("草"),
                 Token::MapEnd,
             ],
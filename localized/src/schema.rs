@@ -0,0 +1,38 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+
+use std::borrow::Cow;
+
+use crate::{Locale, Localized};
+
+impl JsonSchema for Locale {
+    fn schema_name() -> Cow<'static, str> {
+        "Locale".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+        })
+    }
+}
+
+impl<T> JsonSchema for Localized<T>
+where
+    T: JsonSchema,
+{
+    fn schema_name() -> Cow<'static, str> {
+        format!("Localized_{}", T::schema_name()).into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let value = generator.subschema_for::<T>();
+        json_schema!({
+            "description": "Localized data, keyed by `default` or locale names",
+            "type": "object",
+            "properties": {
+                "default": value,
+            },
+            "additionalProperties": value,
+        })
+    }
+}
@@ -0,0 +1,141 @@
+//! Benchmarks of operations on synthetic manifest collections
+//!
+//! Run with `cargo bench -p topic_manifest`, and again with `--features parallel` to compare the
+//! rayon implementations against the sequential ones.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tempfile::TempDir;
+use topic_manifest::{DependencyGraph, LoadOptions, Manifest, ManifestCollection, TopicId};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::hint::black_box;
+
+/// Numbers of topics in the benchmarked collections
+const SIZES: [usize; 3] = [10, 100, 1000];
+
+/// Packages in each conventional topic
+const PACKAGES_PER_TOPIC: usize = 20;
+
+/// Name of the `i`-th synthetic topic
+fn topic_name(i: usize) -> String {
+    format!("topic-{:04}", i)
+}
+
+/// TOML source of the `i`-th synthetic topic, every tenth one cumulative
+///
+/// Each cumulative topic references the nine conventional topics before it, plus a missing one.
+fn synthetic_manifest(i: usize) -> String {
+    if i % 10 == 9 {
+        let refs: Vec<String> = (i - 9..i)
+            .map(|j| format!("\"{}\"", topic_name(j)))
+            .chain([format!("\"missing-{:04}\"", i)])
+            .collect();
+        format!(
+            "name.default = \"Cumulative {}\"\ntopics = [{}]",
+            i,
+            refs.join(", ")
+        )
+    } else {
+        let packages: Vec<String> = (0..PACKAGES_PER_TOPIC)
+            .map(|p| format!("pkg-{}-{} = \"1.{}.{}\"", i % 50, p, i, p))
+            .collect();
+        format!(
+            "name.default = \"Topic {}\"\nsecurity = {}\ncaution.default = \"\"\n[packages]\n{}",
+            i,
+            i.is_multiple_of(3),
+            packages.join("\n")
+        )
+    }
+}
+
+/// Build a collection of `size` synthetic topics
+fn synthetic_collection(size: usize) -> ManifestCollection {
+    let topics: BTreeMap<TopicId, Manifest> = (0..size)
+        .map(|i| {
+            let manifest = toml::from_str(&synthetic_manifest(i)).unwrap();
+            (TopicId::new(&topic_name(i)).unwrap(), manifest)
+        })
+        .collect();
+    ManifestCollection::from(topics)
+}
+
+fn bench_collection(c: &mut Criterion) {
+    let collections: Vec<(usize, ManifestCollection)> = SIZES
+        .iter()
+        .map(|&size| (size, synthetic_collection(size)))
+        .collect();
+
+    let mut group = c.benchmark_group("find_missing_topics");
+    for (size, collection) in &collections {
+        group.bench_with_input(BenchmarkId::from_parameter(size), collection, |b, c| {
+            b.iter(|| c.find_missing_topics())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("is_consistent");
+    for (size, collection) in &collections {
+        group.bench_with_input(BenchmarkId::from_parameter(size), collection, |b, c| {
+            b.iter(|| c.is_consistent())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("iter");
+    for (size, collection) in &collections {
+        group.bench_with_input(BenchmarkId::from_parameter(size), collection, |b, c| {
+            b.iter(|| c.iter().count())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("all_packages");
+    for (size, collection) in &collections {
+        group.bench_with_input(BenchmarkId::from_parameter(size), collection, |b, c| {
+            b.iter(|| c.all_packages())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("topo_sort");
+    for (size, collection) in &collections {
+        group.bench_with_input(BenchmarkId::from_parameter(size), collection, |b, c| {
+            b.iter(|| {
+                DependencyGraph::from_collection(c)
+                    .topo_sort()
+                    .map(|order| order.len())
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Loading a directory with one job against the default number of jobs
+///
+/// Both take the same path without the `parallel` feature.
+fn bench_load(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_from_dir");
+    for size in SIZES {
+        let dir = TempDir::new().unwrap();
+        for i in 0..size {
+            let path = dir.path().join(format!("{}.toml", topic_name(i)));
+            fs::write(path, synthetic_manifest(i)).unwrap();
+        }
+        for (name, jobs) in [("sequential", Some(1)), ("parallel", None)] {
+            let options = LoadOptions {
+                jobs,
+                ..Default::default()
+            };
+            group.bench_with_input(BenchmarkId::new(name, size), &options, |b, options| {
+                b.iter(|| {
+                    ManifestCollection::load_from_dir(black_box(dir.path()), options).unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_collection, bench_load);
+criterion_main!(benches);
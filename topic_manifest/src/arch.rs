@@ -0,0 +1,20 @@
+//! Deserialization of the architectures topics apply to
+
+use serde::{Deserialize, Deserializer};
+
+/// Deserialize an optional list of architectures, taking an empty list as no list at all
+///
+/// Topics without architectures apply to all of them, so `arch = []` is the same as leaving it
+/// out, and is not written back, see [`is_empty`].
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let arch = Option::<Vec<String>>::deserialize(deserializer)?;
+    Ok(arch.filter(|list| !list.is_empty()))
+}
+
+/// Is a list of architectures missing or empty, so it is skipped when serializing
+pub(crate) fn is_empty(arch: &Option<Vec<String>>) -> bool {
+    arch.as_ref().is_none_or(Vec::is_empty)
+}
@@ -1,22 +1,232 @@
 //! Conventional topic
 
-use localized::Localized;
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "chrono")]
+use chrono::NaiveDate;
+use localized::{Locale, Localized};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
 
 use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
 
-use super::packages::Packages;
+use super::cumulative::Cumulative;
+use super::packages::{PackageMergePolicy, Packages};
+use super::topic_id::TopicId;
+use super::translation::LocalizedField;
+use super::{display_name, localized_for, plural};
 
 /// A conventional topic
-#[derive(Clone, Debug, Serialize, Deserialize)]
+///
+/// The `topic` key is left out of `PartialEq` and `Hash`, since it only names the topic.
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(from = "ConventionalManifest")]
 pub struct Conventional {
+    /// Topic name overriding the file stem, read by [`ConventionalManifest`]
+    #[serde(skip_serializing)]
+    topic: Option<TopicId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extends: Option<String>,
     name: Localized<String>,
     security: bool,
     caution: Localized<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    per_package_caution: Option<BTreeMap<String, Localized<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    severity: Option<Severity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "crate::arch::is_empty")]
+    arch: Option<Vec<String>>,
     packages: Packages,
+    #[cfg(feature = "chrono")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<NaiveDate>,
+}
+
+/// Internal type for deserializing conventional topics
+///
+/// Packages overridden per architecture in `[arch_packages.<arch>]` tables are folded into
+/// versions per architecture of `packages`, see [`Packages::insert_for_arch`].
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+struct ConventionalManifest {
+    #[serde(default, alias = "id")]
+    #[cfg_attr(feature = "schemars", schemars(skip))]
+    topic: Option<TopicId>,
+    #[serde(default)]
+    extends: Option<String>,
+    name: Localized<String>,
+    security: bool,
+    caution: Localized<String>,
+    #[serde(default)]
+    per_package_caution: Option<BTreeMap<String, Localized<String>>>,
+    #[serde(default)]
+    severity: Option<Severity>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default, deserialize_with = "crate::arch::deserialize")]
+    arch: Option<Vec<String>>,
+    packages: Packages,
+    /// Folded into `packages`, so never found in the JSON output
+    #[serde(default)]
+    #[cfg_attr(feature = "schemars", schemars(skip))]
+    arch_packages: BTreeMap<String, Packages>,
+    #[cfg(feature = "chrono")]
+    #[serde(default, deserialize_with = "crate::date::deserialize")]
+    date: Option<NaiveDate>,
+}
+
+impl From<ConventionalManifest> for Conventional {
+    fn from(value: ConventionalManifest) -> Self {
+        let mut packages = value.packages;
+        for (arch, overrides) in value.arch_packages {
+            for (name, version) in overrides.for_arch(&arch).iter() {
+                let version = version.map(str::to_string);
+                packages.insert_for_arch(name.to_string(), arch.clone(), version);
+            }
+        }
+        Self {
+            topic: value.topic,
+            extends: value.extends,
+            name: value.name,
+            security: value.security,
+            caution: value.caution,
+            per_package_caution: value.per_package_caution,
+            severity: value.severity,
+            url: value.url,
+            arch: value.arch,
+            packages,
+            #[cfg(feature = "chrono")]
+            date: value.date,
+        }
+    }
+}
+
+impl PartialEq for Conventional {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            topic: _,
+            extends,
+            name,
+            security,
+            caution,
+            per_package_caution,
+            severity,
+            url,
+            arch,
+            packages,
+            #[cfg(feature = "chrono")]
+            date,
+        } = self;
+        #[cfg(feature = "chrono")]
+        if *date != other.date {
+            return false;
+        }
+        *extends == other.extends
+            && *name == other.name
+            && *security == other.security
+            && *caution == other.caution
+            && *per_package_caution == other.per_package_caution
+            && *severity == other.severity
+            && *url == other.url
+            && *arch == other.arch
+            && *packages == other.packages
+    }
+}
+
+impl Hash for Conventional {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let Self {
+            topic: _,
+            extends,
+            name,
+            security,
+            caution,
+            per_package_caution,
+            severity,
+            url,
+            arch,
+            packages,
+            #[cfg(feature = "chrono")]
+            date,
+        } = self;
+        extends.hash(state);
+        name.hash(state);
+        security.hash(state);
+        caution.hash(state);
+        per_package_caution.hash(state);
+        severity.hash(state);
+        url.hash(state);
+        arch.hash(state);
+        packages.hash(state);
+        #[cfg(feature = "chrono")]
+        date.hash(state);
+    }
+}
+
+/// How important it is for users to read the caution of a topic
+///
+/// Deserialized case-insensitively, serialized in lowercase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Nice to know
+    Info,
+    /// Worth reading before updating
+    Warning,
+    /// Must be read before updating or rebooting
+    Critical,
+}
+
+impl Severity {
+    const ALL: [Severity; 3] = [Self::Info, Self::Warning, Self::Critical];
+
+    /// Name of the severity level as written in manifests
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Severity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::ALL
+            .into_iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(&s))
+            .ok_or_else(|| D::Error::unknown_variant(&s, &["info", "warning", "critical"]))
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl Conventional {
+    /// Get the topic name set by the `topic` (or `id`) key, see [`Manifest::get_topic_id`]
+    ///
+    /// [`Manifest::get_topic_id`]: crate::Manifest::get_topic_id
+    pub fn get_topic_id(&self) -> Option<&TopicId> {
+        self.topic.as_ref()
+    }
+
+    /// Get the topic whose packages this topic extends, see
+    /// [`ManifestCollection::resolve_extends`](crate::ManifestCollection::resolve_extends)
+    pub fn get_extends(&self) -> Option<&str> {
+        self.extends.as_deref()
+    }
+
     /// Get name of the topic
     pub fn get_name(&self) -> &Localized<String> {
         &self.name
@@ -32,9 +242,195 @@ impl Conventional {
         &self.caution
     }
 
+    /// Get localized caution strings of single packages, keyed by package names
+    pub fn get_per_package_caution(&self) -> Option<&BTreeMap<String, Localized<String>>> {
+        self.per_package_caution.as_ref()
+    }
+
+    /// Get localized caution strings of `pkg`, `None` if it has no caution of its own
+    ///
+    /// The caution of the whole topic is not included, see [`Conventional::get_caution`].
+    pub fn get_caution_for_package(&self, pkg: &str) -> Option<&Localized<String>> {
+        self.per_package_caution.as_ref()?.get(pkg)
+    }
+
+    /// Get severity of the caution, `None` if not specified
+    pub fn get_severity(&self) -> Option<Severity> {
+        self.severity
+    }
+
+    /// Get link to upstream release notes or announcements of the topic
+    ///
+    /// The URL is not checked while deserializing, see
+    /// [`ManifestCollection::find_invalid_urls`](crate::ManifestCollection::find_invalid_urls).
+    pub fn get_url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Architectures this topic applies to, `None` for all architectures, also written as an empty
+    /// list
+    pub fn get_arch(&self) -> Option<&[String]> {
+        self.arch.as_deref()
+    }
+
+    /// Does this topic apply to `arch`
+    pub fn is_applicable_for_arch(&self, arch: &str) -> bool {
+        self.arch
+            .as_ref()
+            .is_none_or(|list| list.iter().any(|a| a == arch))
+    }
+
+    /// Get name of the topic in the first available locale in `locales`
+    ///
+    /// Falls back to the default name, then to the first available translation.
+    pub fn name_for(&self, locales: &[Locale]) -> &str {
+        localized_for(&self.name, locales)
+    }
+
+    /// Does the topic or any of its packages carry a caution message, empty messages are ignored
+    pub fn has_caution(&self) -> bool {
+        std::iter::once(&self.caution)
+            .chain(self.per_package_caution.iter().flat_map(|m| m.values()))
+            .flat_map(|caution| caution.default.iter().chain(caution.content.values()))
+            .any(|s| !s.trim().is_empty())
+    }
+
+    /// Get caution of the topic in the first available locale in `locales`
+    ///
+    /// Falls back to the default caution, then to the first available translation.
+    pub fn caution_for(&self, locales: &[Locale]) -> &str {
+        localized_for(&self.caution, locales)
+    }
+
+    /// Get mutable localized fields of the topic, including cautions of single packages
+    pub(crate) fn localized_fields_mut(&mut self) -> Vec<(LocalizedField, &mut Localized<String>)> {
+        let mut fields = vec![
+            (LocalizedField::Name, &mut self.name),
+            (LocalizedField::Caution, &mut self.caution),
+        ];
+        for caution in self
+            .per_package_caution
+            .iter_mut()
+            .flat_map(|m| m.values_mut())
+        {
+            fields.push((LocalizedField::PackageCaution, caution));
+        }
+        fields
+    }
+
     /// Get package updates in this topic
-    pub fn get_packages(&self) -> &BTreeMap<String, Option<String>> {
-        self.packages.as_ref()
+    pub fn get_packages(&self) -> &Packages {
+        &self.packages
+    }
+
+    /// Get package updates in this topic as [`Packages`]
+    #[deprecated(note = "use `Conventional::get_packages` instead")]
+    pub fn packages(&self) -> &Packages {
+        &self.packages
+    }
+
+    /// Apply `other` on top of `packages`, entries in `other` replace those with the same name
+    ///
+    /// Packages set to `None` in `other` are marked for removal.
+    pub fn merge_packages_from(&mut self, other: &Packages) {
+        let _ = self.packages.merge(other, PackageMergePolicy::OtherWins);
+    }
+
+    /// Names of packages updated or removed on any architecture
+    pub(crate) fn package_names(&self) -> impl Iterator<Item = &str> {
+        self.packages.names()
+    }
+
+    /// Build a conventional topic with the packages of all topics referenced by `cumulative`
+    ///
+    /// Packages of later topics in `topics` take priority. The new topic is a security update if
+    /// any of `topics` is, with the highest severity among them, and no caution, not even for
+    /// single packages.
+    pub(crate) fn flattened(cumulative: &Cumulative, topics: &[&Conventional]) -> Self {
+        let mut flattened = Conventional {
+            name: cumulative.get_name().clone(),
+            arch: cumulative.get_arch().map(<[String]>::to_vec),
+            ..Default::default()
+        };
+        #[cfg(feature = "chrono")]
+        {
+            flattened.date = cumulative.get_date();
+        }
+        for topic in topics {
+            flattened.security |= topic.security;
+            flattened.severity = flattened.severity.max(topic.severity);
+            let _ = flattened
+                .packages
+                .merge(&topic.packages, PackageMergePolicy::OtherWins);
+        }
+        flattened
+    }
+
+    /// Build this topic with the packages of `parent`, entries in this topic replace those with
+    /// the same name
+    ///
+    /// A package with versions per architecture in this topic replaces all versions of it in
+    /// `parent`. `parent` must not extend any topic itself, and neither does the new topic.
+    pub(crate) fn extending(&self, parent: &Conventional) -> Self {
+        let mut packages = parent.packages.clone();
+        let _ = packages.merge(&self.packages, PackageMergePolicy::OtherWins);
+        Self {
+            extends: None,
+            packages,
+            ..self.clone()
+        }
+    }
+
+    /// Get package updates in this topic applicable to `arch`, see [`Packages::for_arch`]
+    pub fn get_packages_for_arch(&self, arch: &str) -> Packages {
+        self.packages.for_arch(arch)
+    }
+
+    /// Replace package updates with those applicable to `arch`, dropping versions per architecture
+    pub fn resolve_arch(&mut self, arch: &str) {
+        self.packages = self.get_packages_for_arch(arch);
+    }
+
+    /// Get date of the topic, used to determine whether the topic is expired
+    #[cfg(feature = "chrono")]
+    pub fn get_date(&self) -> Option<NaiveDate> {
+        self.date
+    }
+
+    /// Multi-line human-readable summary of the topic, listing its packages
+    pub fn summary(&self) -> String {
+        let mut summary = self.to_string();
+        for (name, ver) in self.packages.iter() {
+            if self.packages.get_arch_specific(name).is_none() {
+                let _ = write!(summary, "\n  {} {}", name, ver.unwrap_or("(removed)"));
+            }
+        }
+        for (name, versions) in self.packages.arch_specific() {
+            let default = self.packages.get(name).map(|ver| ("default", ver));
+            let versions: Vec<String> = default
+                .into_iter()
+                .chain(versions.iter().map(|(arch, ver)| (arch.as_str(), ver)))
+                .map(|(arch, ver)| format!("{}={}", arch, ver.as_deref().unwrap_or("(removed)")))
+                .collect();
+            let _ = write!(summary, "\n  {} {}", name, versions.join(", "));
+        }
+        summary
+    }
+}
+
+impl fmt::Display for Conventional {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "conventional {:?}", display_name(&self.name))?;
+        if self.security {
+            write!(f, " [security]")?;
+        }
+        let count = self.packages.len();
+        write!(f, " — {} package{}", count, plural(count))?;
+        let removals = self.packages.removed().count();
+        if removals > 0 {
+            write!(f, " ({} removal{})", removals, plural(removals))?;
+        }
+        Ok(())
     }
 }
 
@@ -45,7 +441,8 @@ mod test {
 
     use std::collections::BTreeMap;
 
-    use super::Conventional;
+    use super::{Conventional, Packages, Severity};
+    use crate::packages::ArchVersions;
 
     #[test]
     fn test_de() -> Result<()> {
@@ -75,13 +472,17 @@ mod test {
                 ),
             }
         );
-        assert_eq!(converted.security, true);
+        assert!(converted.security);
         assert_eq!(converted.caution, Localized::<String> {
             default: Some(r#"This topic may use significantly more memory after reboot. Our testing finds that the new KDE version may use up to 16GiB of RAM."#.into()),
             content: BTreeMap::from([
                 (Locale::new("zh-CN"), "本次更新重启后可能会需要更多内存。据我社维护者测试，新版 KDE 可能需要接近 16GiB 内存。".into()),
             ]),
         });
+        assert_eq!(converted.get_severity(), None);
+        assert_eq!(converted.get_url(), None);
+        assert_eq!(converted.get_arch(), None);
+        assert!(converted.is_applicable_for_arch("loongarch64"));
         assert_eq!(converted.packages.as_ref().len(), 3);
         assert_eq!(
             converted.packages.as_ref()["konsole"],
@@ -92,6 +493,271 @@ mod test {
             Some("23.04.1".to_string())
         );
         assert_eq!(converted.packages.as_ref()["pykde"], None);
+        assert_eq!(
+            converted.get_packages().removed().collect::<Vec<_>>(),
+            ["pykde"]
+        );
+        assert_eq!(
+            converted.to_string(),
+            r#"conventional "KDE Updates (Winter 2023)" [security] — 3 packages (1 removal)"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_locale_fallback() -> Result<()> {
+        let example = r#"
+        name.default = "KDE Updates (Winter 2023)"
+        name.zh_CN = "KDE 更新（2023 年冬季）"
+        name.zh_TW = "KDE 更新（2023 年冬季）（正體）"
+        security = false
+        caution.zh_CN = "请注意内存占用"
+
+        [packages]
+        konsole = "23.04.1-1"
+        "#;
+
+        let converted = toml::from_str::<Conventional>(example)?;
+        let zh_tw = [Locale::new("zh_TW"), Locale::new("zh_CN")];
+        let zh_hk = [Locale::new("zh_HK"), Locale::new("zh_CN")];
+        let en = [Locale::new("en_US")];
+        assert_eq!(
+            converted.name_for(&zh_tw),
+            "KDE 更新（2023 年冬季）（正體）"
+        );
+        assert_eq!(converted.name_for(&zh_hk), "KDE 更新（2023 年冬季）");
+        assert_eq!(converted.name_for(&en), "KDE Updates (Winter 2023)");
+        // No default caution, use the first translation
+        assert_eq!(converted.caution_for(&en), "请注意内存占用");
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_caution() -> Result<()> {
+        let with_caution = |caution: &str| -> Result<bool> {
+            let example = format!(
+                "name.default = \"KDE\"\nsecurity = false\n{}\n[packages]",
+                caution
+            );
+            Ok(toml::from_str::<Conventional>(&example)?.has_caution())
+        };
+        assert!(with_caution(r#"caution.default = "Reboot required""#)?);
+        assert!(with_caution(r#"caution.zh_CN = "需要重启""#)?);
+        assert!(with_caution(
+            "caution.default = \"\"\ncaution.zh_CN = \"需要重启\""
+        )?);
+        assert!(!with_caution(r#"caution.default = """#)?);
+        assert!(!with_caution("caution.default = \"\"\"\n\"\"\"")?);
+        assert!(!with_caution("caution = {}")?);
+        assert!(with_caution(
+            "caution.default = \"\"\n[per_package_caution.linux-kernel]\ndefault = \"Reboot\""
+        )?);
+        assert!(!with_caution(
+            "caution.default = \"\"\n[per_package_caution.linux-kernel]\ndefault = \" \""
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_package_caution() -> Result<()> {
+        let example = r#"
+        name.default = "Core Updates"
+        security = false
+        caution.default = "Read the release notes"
+
+        [packages]
+        "linux+kernel" = "6.6.3"
+        firefox = "120.0"
+
+        [per_package_caution."linux+kernel"]
+        default = "Reboot to use the new kernel."
+        zh_CN = "重启以使用新内核。"
+        "#;
+
+        let converted = toml::from_str::<Conventional>(example)?;
+        assert_eq!(converted.get_per_package_caution().unwrap().len(), 1);
+        let kernel = converted.get_caution_for_package("linux+kernel").unwrap();
+        assert_eq!(
+            kernel.get_default(),
+            Some(&"Reboot to use the new kernel.".to_string())
+        );
+        assert_eq!(kernel.content[&Locale::new("zh_CN")], "重启以使用新内核。");
+        assert_eq!(converted.get_caution_for_package("firefox"), None);
+        assert_eq!(converted.caution_for(&[]), "Read the release notes");
+
+        let json = serde_json::to_string(&converted)?;
+        assert!(json.contains(r#""per_package_caution":{"linux+kernel":{"#));
+        assert_eq!(serde_json::from_str::<Conventional>(&json)?, converted);
+
+        let without = toml::from_str::<Conventional>(
+            "name.default = \"KDE\"\nsecurity = false\ncaution.default = \"\"\n[packages]",
+        )?;
+        assert_eq!(without.get_per_package_caution(), None);
+        assert_eq!(without.get_caution_for_package("linux+kernel"), None);
+        assert!(!serde_json::to_string(&without)?.contains("per_package_caution"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary() -> Result<()> {
+        let example = r#"
+        name.zh_CN = "KDE 更新（2023 年冬季）"
+        security = false
+        caution.default = ""
+
+        [packages]
+        konsole = "23.04.1-1"
+        "#;
+
+        let converted = toml::from_str::<Conventional>(example)?;
+        assert_eq!(
+            converted.summary(),
+            "conventional \"KDE 更新（2023 年冬季）\" — 1 package\n  konsole 23.04.1-1"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_severity() -> Result<()> {
+        let with_severity = |severity: &str| {
+            let example = format!(
+                "name.default = \"KDE\"\nsecurity = false\ncaution.default = \"Reboot\"\n{}\n[packages]",
+                severity
+            );
+            toml::from_str::<Conventional>(&example)
+        };
+        assert_eq!(with_severity("")?.get_severity(), None);
+        assert_eq!(
+            with_severity(r#"severity = "info""#)?.get_severity(),
+            Some(Severity::Info)
+        );
+        assert_eq!(
+            with_severity(r#"severity = "Warning""#)?.get_severity(),
+            Some(Severity::Warning)
+        );
+        assert_eq!(
+            with_severity(r#"severity = "CRITICAL""#)?.get_severity(),
+            Some(Severity::Critical)
+        );
+        let err = with_severity(r#"severity = "fatal""#).unwrap_err();
+        assert!(err.to_string().contains("unknown variant `fatal`"));
+        assert!(Severity::Info < Severity::Critical);
+
+        let critical = with_severity(r#"severity = "Critical""#)?;
+        let json = serde_json::to_string(&critical)?;
+        assert!(json.contains(r#""severity":"critical""#));
+        assert_eq!(serde_json::from_str::<Conventional>(&json)?, critical);
+        assert!(!serde_json::to_string(&with_severity("")?)?.contains("severity"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_arch() -> Result<()> {
+        let converted = toml::from_str::<Conventional>(
+            "name.default = \"KDE\"\nsecurity = false\ncaution.default = \"\"\narch = []\n[packages]",
+        )?;
+        assert_eq!(converted.get_arch(), None);
+        assert!(converted.is_applicable_for_arch("amd64"));
+        let json = serde_json::to_string(&converted)?;
+        assert!(!json.contains("arch"));
+        assert_eq!(serde_json::from_str::<Conventional>(&json)?, converted);
+        Ok(())
+    }
+
+    #[test]
+    fn test_arch_packages() -> Result<()> {
+        let example = r#"
+        name.default = "Mesa"
+        security = false
+        caution.default = ""
+
+        [packages]
+        mesa = "23.3.0"
+        libdrm = "2.4.118"
+        firmware = { amd64 = "20231111" }
+
+        [arch_packages.arm64]
+        mesa = "23.2.1"
+        libdrm = false
+        firmware = { arm64 = "20231030" }
+        "#;
+        let mut converted = toml::from_str::<Conventional>(example)?;
+        let packages = converted.get_packages();
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages.get("mesa"), Some(&Some("23.3.0".to_string())));
+        assert_eq!(
+            packages.get_arch_specific("mesa"),
+            Some(&ArchVersions::from([(
+                "arm64".to_string(),
+                Some("23.2.1".to_string())
+            )]))
+        );
+
+        let amd64 = converted.get_packages_for_arch("amd64");
+        assert_eq!(amd64.get("mesa"), Some(&Some("23.3.0".to_string())));
+        assert_eq!(amd64.get("firmware"), Some(&Some("20231111".to_string())));
+        let arm64 = converted.get_packages_for_arch("arm64");
+        assert_eq!(arm64.get("mesa"), Some(&Some("23.2.1".to_string())));
+        assert_eq!(arm64.get("libdrm"), Some(&None));
+        assert_eq!(arm64.get("firmware"), Some(&Some("20231030".to_string())));
+        let riscv64 = converted.get_packages_for_arch("riscv64");
+        assert_eq!(riscv64.len(), 2);
+        assert!(!riscv64.contains("firmware"));
+        assert!(converted
+            .summary()
+            .contains("\n  mesa default=23.3.0, arm64=23.2.1"));
+
+        let json = serde_json::to_string(&converted)?;
+        assert!(!json.contains("arch_packages"));
+        assert!(json.contains(r#""libdrm":{"default":"2.4.118","arm64":null}"#));
+        assert!(json.contains(r#""mesa":{"default":"23.3.0","arm64":"23.2.1"}"#));
+        assert_eq!(serde_json::from_str::<Conventional>(&json)?, converted);
+
+        converted.resolve_arch("arm64");
+        assert_eq!(converted.get_packages().arch_specific().count(), 0);
+        assert_eq!(converted.get_packages(), &arm64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_packages_from() -> Result<()> {
+        let mut topic = toml::from_str::<Conventional>(
+            r#"
+            name.default = "Mesa"
+            security = false
+            caution.default = ""
+
+            [packages]
+            mesa = "23.3.0"
+            libdrm = "2.4.118"
+            firmware = { amd64 = "20231111" }
+            "#,
+        )?;
+        let patch = toml::from_str::<Packages>(
+            r#"
+            mesa = "23.3.1"
+            libdrm = false
+            firmware = "20231201"
+            vulkan-loader = "1.3.270"
+            "#,
+        )?;
+        topic.merge_packages_from(&patch);
+        let packages = topic.get_packages();
+        assert_eq!(packages.len(), 4);
+        assert_eq!(packages.get("mesa"), Some(&Some("23.3.1".to_string())));
+        assert_eq!(packages.get("libdrm"), Some(&None));
+        assert_eq!(
+            packages.get("firmware"),
+            Some(&Some("20231201".to_string()))
+        );
+        assert_eq!(packages.get_arch_specific("firmware"), None);
+        assert_eq!(
+            packages.get("vulkan-loader"),
+            Some(&Some("1.3.270".to_string()))
+        );
+
+        topic.merge_packages_from(&Packages::new());
+        assert_eq!(topic.get_packages(), &patch);
         Ok(())
     }
 }
@@ -1,25 +1,177 @@
 //! Cumulative topic
 
-use localized::Localized;
+#[cfg(feature = "chrono")]
+use chrono::NaiveDate;
+use localized::{Locale, Localized};
 use serde::{Deserialize, Serialize};
 
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
+
+use super::topic_id::TopicId;
+use super::translation::LocalizedField;
+use super::{display_name, localized_for, plural};
+
 /// Cumulative topic
-#[derive(Clone, Debug, Serialize, Deserialize)]
+///
+/// The `topic` key is left out of `PartialEq` and `Hash`, since it only names the topic.
+#[derive(Clone, Debug, Default, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Cumulative {
+    /// Topic name overriding the file stem, only read from TOML manifests
+    #[serde(default, alias = "id", skip_serializing)]
+    #[cfg_attr(feature = "schemars", schemars(skip))]
+    topic: Option<TopicId>,
     name: Localized<String>,
     topics: Vec<String>,
+    #[serde(default, skip_serializing_if = "crate::arch::is_empty")]
+    #[serde(deserialize_with = "crate::arch::deserialize")]
+    arch: Option<Vec<String>>,
+    #[cfg(feature = "chrono")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "crate::date::deserialize")]
+    date: Option<NaiveDate>,
+}
+
+impl PartialEq for Cumulative {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            topic: _,
+            name,
+            topics,
+            arch,
+            #[cfg(feature = "chrono")]
+            date,
+        } = self;
+        #[cfg(feature = "chrono")]
+        if *date != other.date {
+            return false;
+        }
+        *name == other.name && *topics == other.topics && *arch == other.arch
+    }
+}
+
+impl Hash for Cumulative {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let Self {
+            topic: _,
+            name,
+            topics,
+            arch,
+            #[cfg(feature = "chrono")]
+            date,
+        } = self;
+        name.hash(state);
+        topics.hash(state);
+        arch.hash(state);
+        #[cfg(feature = "chrono")]
+        date.hash(state);
+    }
 }
 
 impl Cumulative {
+    /// Topic name set by the `topic` (or `id`) key, see [`Manifest::get_topic_id`]
+    ///
+    /// [`Manifest::get_topic_id`]: crate::Manifest::get_topic_id
+    pub fn get_topic_id(&self) -> Option<&TopicId> {
+        self.topic.as_ref()
+    }
+
     /// Name of the topic
     pub fn get_name(&self) -> &Localized<String> {
         &self.name
     }
 
+    /// Name of the topic in the first available locale in `locales`
+    ///
+    /// Falls back to the default name, then to the first available translation.
+    pub fn name_for(&self, locales: &[Locale]) -> &str {
+        localized_for(&self.name, locales)
+    }
+
+    /// Get mutable localized fields of the topic
+    pub(crate) fn localized_fields_mut(&mut self) -> [(LocalizedField, &mut Localized<String>); 1] {
+        [(LocalizedField::Name, &mut self.name)]
+    }
+
     /// Conventional topics used in this topic
     pub fn get_topics(&self) -> &[String] {
         &self.topics
     }
+
+    /// Is `topic` used in this topic
+    pub fn contains_topic(&self, topic: &str) -> bool {
+        self.topics.iter().any(|t| t == topic)
+    }
+
+    /// Position of the first occurrence of `topic` in this topic
+    pub fn topic_index(&self, topic: &str) -> Option<usize> {
+        self.topics.iter().position(|t| t == topic)
+    }
+
+    /// Architectures this topic targets, `None` if not specified or empty
+    pub fn get_arch(&self) -> Option<&[String]> {
+        self.arch.as_deref()
+    }
+
+    /// Does this topic apply to `arch`, topics without architectures apply to all of them
+    pub fn is_applicable_for_arch(&self, arch: &str) -> bool {
+        self.arch
+            .as_ref()
+            .is_none_or(|list| list.iter().any(|a| a == arch))
+    }
+
+    /// Get date of the topic, used to determine whether the topic is expired
+    #[cfg(feature = "chrono")]
+    pub fn get_date(&self) -> Option<NaiveDate> {
+        self.date
+    }
+
+    /// Topics listed more than once in this topic
+    pub fn find_duplicate_topics(&self) -> Vec<String> {
+        let mut seen = BTreeSet::new();
+        let mut duplicates = BTreeSet::new();
+        for topic in &self.topics {
+            if !seen.insert(topic) {
+                duplicates.insert(topic);
+            }
+        }
+        duplicates.into_iter().cloned().collect()
+    }
+
+    /// Remove duplicated topics, keeping the first occurrence of each
+    pub fn dedup_topics(&mut self) {
+        let mut seen = BTreeSet::new();
+        self.topics.retain(|t| seen.insert(t.to_string()));
+    }
+
+    /// Keep only topics for which `f` returns `true`
+    pub(crate) fn retain_topics<F: FnMut(&str) -> bool>(&mut self, mut f: F) {
+        self.topics.retain(|t| f(t));
+    }
+
+    /// Multi-line human-readable summary of the topic, listing its topics
+    pub fn summary(&self) -> String {
+        let mut summary = self.to_string();
+        for topic in &self.topics {
+            let _ = write!(summary, "\n  {}", topic);
+        }
+        summary
+    }
+}
+
+impl fmt::Display for Cumulative {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.topics.len();
+        write!(
+            f,
+            "cumulative {:?} — {} topic{}",
+            display_name(&self.name),
+            count,
+            plural(count)
+        )
+    }
 }
 
 #[cfg(test)]
@@ -59,6 +211,87 @@ mod test {
             converted.topics,
             ["kde-survey-20231201".to_string(), "core-12.1.0".to_string(),]
         );
+        assert_eq!(
+            converted.name_for(&[Locale::new("zh_CN"), Locale::new("zh_MS")]),
+            "适用于 amd64 AOSC OS 版本的 23 冬季累计更新"
+        );
+        assert_eq!(
+            converted.name_for(&[Locale::new("en_US")]),
+            "Winter 2023 Cumulative Update for amd64 AOSC OS systems"
+        );
+        assert!(converted.contains_topic("core-12.1.0"));
+        assert!(!converted.contains_topic("core"));
+        assert_eq!(converted.topic_index("core-12.1.0"), Some(1));
+        assert_eq!(converted.topic_index("core"), None);
+        assert_eq!(converted.get_arch(), None);
+        assert!(!serde_json::to_string(&converted)?.contains("arch"));
+        assert_eq!(
+            converted.summary(),
+            r#"cumulative "Winter 2023 Cumulative Update for amd64 AOSC OS systems" — 2 topics
+  kde-survey-20231201
+  core-12.1.0"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_arch() -> Result<()> {
+        let single = toml::from_str::<Cumulative>(
+            r#"
+            name.default = "Winter 2023 Cumulative Update for amd64 AOSC OS systems"
+            topics = ["kde-survey-20231201"]
+            arch = ["amd64"]
+            "#,
+        )?;
+        assert_eq!(single.get_arch(), Some(["amd64".to_string()].as_slice()));
+        assert!(serde_json::to_string(&single)?.contains(r#""arch":["amd64"]"#));
+
+        let multiple = toml::from_str::<Cumulative>(
+            r#"
+            name.default = "Winter 2023 Cumulative Update"
+            topics = ["kde-survey-20231201"]
+            arch = ["amd64", "arm64", "loongarch64"]
+            "#,
+        )?;
+        assert_eq!(
+            multiple.get_arch(),
+            Some(
+                [
+                    "amd64".to_string(),
+                    "arm64".to_string(),
+                    "loongarch64".to_string()
+                ]
+                .as_slice()
+            )
+        );
+
+        let empty = toml::from_str::<Cumulative>(
+            r#"
+            name.default = "Winter 2023 Cumulative Update"
+            topics = ["kde-survey-20231201"]
+            arch = []
+            "#,
+        )?;
+        assert_eq!(empty.get_arch(), None);
+        assert!(empty.is_applicable_for_arch("amd64"));
+        let json = serde_json::to_string(&empty)?;
+        assert!(!json.contains("arch"));
+        assert_eq!(serde_json::from_str::<Cumulative>(&json)?, empty);
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_topics() -> Result<()> {
+        let mut converted = toml::from_str::<Cumulative>(
+            r#"
+            name.default = "Cumulative"
+            topics = ["b", "a", "b", "c", "a", "b"]
+            "#,
+        )?;
+        assert_eq!(converted.find_duplicate_topics(), ["a", "b"]);
+        converted.dedup_topics();
+        assert_eq!(converted.topics, ["b", "a", "c"]);
+        assert!(converted.find_duplicate_topics().is_empty());
         Ok(())
     }
 }
@@ -0,0 +1,32 @@
+//! Deserialization of dates from both ISO-8601 strings and TOML local dates
+
+use chrono::NaiveDate;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DateRepr {
+    Iso(NaiveDate),
+    Toml(toml::value::Datetime),
+}
+
+/// Deserialize an optional date, accepting `date = 2023-12-01` as well as `date = "2023-12-01"`
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<DateRepr>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(DateRepr::Iso(date)) => Ok(Some(date)),
+        Some(DateRepr::Toml(datetime)) => match (datetime.date, datetime.time) {
+            (Some(d), None) => NaiveDate::from_ymd_opt(d.year.into(), d.month.into(), d.day.into())
+                .map(Some)
+                .ok_or_else(|| D::Error::custom(format!("invalid date `{}`", datetime))),
+            _ => Err(D::Error::custom(format!(
+                "expected a date without time, found `{}`",
+                datetime
+            ))),
+        },
+    }
+}
@@ -0,0 +1,125 @@
+//! Differences between manifest collections
+
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+
+use super::packages::PackagesDiff;
+use super::{Manifest, ManifestCollection};
+
+/// Differences between two versions of a manifest collection, each list sorted by topic name
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CollectionDiff {
+    /// Topics absent from the old collection
+    pub added: Vec<String>,
+    /// Topics in the old collection no longer present
+    pub removed: Vec<String>,
+    /// Topics in both collections with different content
+    pub changed: Vec<String>,
+    /// Package changes of topics in `changed` which are conventional in both collections
+    ///
+    /// Topics whose packages are the same are left out.
+    pub packages: BTreeMap<String, PackagesDiff>,
+}
+
+impl CollectionDiff {
+    /// Are the collections identical
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Record `topic` as changed from `old` to `new`
+    pub(crate) fn push_changed(&mut self, topic: &str, new: &Manifest, old: &Manifest) {
+        self.changed.push(topic.to_string());
+        if let (Manifest::Conventional(new), Manifest::Conventional(old)) = (new, old) {
+            let packages = new.get_packages().diff_against(old.get_packages());
+            if !packages.is_empty() {
+                self.packages.insert(topic.to_string(), packages);
+            }
+        }
+    }
+}
+
+impl ManifestCollection {
+    /// Compare this collection against an older version of it, such as a previous output
+    pub fn diff(&self, old: &ManifestCollection) -> CollectionDiff {
+        let mut diff = CollectionDiff::default();
+        for (topic, manifest) in self.iter() {
+            match old.get(topic) {
+                None => diff.added.push(topic.to_string()),
+                Some(old) if manifest != old => diff.push_changed(topic, manifest, old),
+                Some(_) => (),
+            }
+        }
+        diff.removed = old
+            .iter()
+            .filter(|(topic, _)| self.get(topic).is_none())
+            .map(|(topic, _)| topic.to_string())
+            .collect();
+        diff
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+
+    use std::collections::BTreeMap;
+
+    use super::CollectionDiff;
+    use crate::packages::PackagesDiff;
+    use crate::test_util::{conventional, topics};
+    use crate::{Manifest, ManifestCollection};
+
+    fn kde(konsole: &str) -> Manifest {
+        conventional(&format!("[packages]\nkonsole = {:?}", konsole))
+    }
+
+    #[test]
+    fn test_diff() -> Result<()> {
+        let old = ManifestCollection::from(topics([
+            ("kde".to_string(), kde("23.04.1")),
+            ("kde-fix".to_string(), kde("23.04.2")),
+            ("qt".to_string(), kde("5.15.11")),
+        ]));
+        let new = ManifestCollection::from(topics([
+            ("kde".to_string(), kde("23.04.1")),
+            ("kde-fix".to_string(), kde("23.04.3")),
+            ("mesa".to_string(), kde("23.3.0")),
+        ]));
+        assert_eq!(
+            new.diff(&old),
+            CollectionDiff {
+                added: vec!["mesa".to_string()],
+                removed: vec!["qt".to_string()],
+                changed: vec!["kde-fix".to_string()],
+                packages: BTreeMap::from([(
+                    "kde-fix".to_string(),
+                    PackagesDiff {
+                        updated: BTreeMap::from([(
+                            "konsole".to_string(),
+                            (Some("23.04.2".to_string()), "23.04.3".to_string())
+                        )]),
+                        ..Default::default()
+                    }
+                )]),
+            }
+        );
+        assert!(new.diff(&new).is_empty());
+        assert!(ManifestCollection::default()
+            .diff(&ManifestCollection::default())
+            .is_empty());
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_diff_against_json() -> Result<()> {
+        // Topic keys are lost in JSON, but do not count as differences
+        let new = ManifestCollection::from(topics([("kde", conventional("topic = \"kde\""))]));
+        let old = ManifestCollection::from_json_str(&new.to_json_string()?)?;
+        assert_eq!(new, old);
+        assert!(new.diff(&old).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+//! Resolving conventional topics extending the packages of others
+
+use std::collections::BTreeMap;
+
+use super::{Conventional, Manifest, ManifestCollection};
+
+impl ManifestCollection {
+    /// Get a new collection where every conventional topic with `extends` carries the packages of
+    /// the topic it extends
+    ///
+    /// Parents are resolved first, so packages are inherited along the whole chain, and packages
+    /// of the extending topic take priority. Other fields are kept as is. Topics without `extends`
+    /// are unchanged, and cumulative topics reference the topics as they are before resolving.
+    /// Fails if a parent is missing or cumulative, or if topics extend each other in a cycle.
+    pub fn resolve_extends(&self) -> Result<ManifestCollection, String> {
+        let mut resolved = BTreeMap::new();
+        for (id, manifest) in &self.topics {
+            let manifest = match manifest {
+                Manifest::Conventional(c) if c.get_extends().is_some() => {
+                    Manifest::Conventional(self.resolve_conventional(id.as_str(), c)?)
+                }
+                manifest => manifest.clone(),
+            };
+            resolved.insert(id.clone(), manifest);
+        }
+        Ok(resolved.into())
+    }
+
+    /// Get a list of conventional topics whose `extends` can not be resolved, along with the
+    /// reasons, see [`ManifestCollection::resolve_extends`]
+    pub fn find_invalid_extends(&self) -> Vec<(String, String)> {
+        self.iter()
+            .filter_map(|(topic, manifest)| match manifest {
+                Manifest::Conventional(c) if c.get_extends().is_some() => {
+                    let reason = self.extends_chain(topic, c).err()?;
+                    Some((topic.to_string(), reason))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Get `conventional`, named `topic`, carrying the packages of the topics it extends, see
+    /// [`ManifestCollection::resolve_extends`]
+    pub(crate) fn resolve_conventional(
+        &self,
+        topic: &str,
+        conventional: &Conventional,
+    ) -> Result<Conventional, String> {
+        let chain = self.extends_chain(topic, conventional)?;
+        let (root, parents) = chain.split_last().expect("Chain includes the topic");
+        Ok(parents
+            .iter()
+            .rev()
+            .fold((*root).clone(), |parent, child| child.extending(&parent)))
+    }
+
+    /// Get `topic` followed by the topics it extends, directly or indirectly
+    pub(crate) fn extends_chain<'a>(
+        &'a self,
+        topic: &'a str,
+        conventional: &'a Conventional,
+    ) -> Result<Vec<&'a Conventional>, String> {
+        let mut names = vec![topic];
+        let mut chain = vec![conventional];
+        while let Some(parent) = chain.last().and_then(|c| c.get_extends()) {
+            let child = names.last().expect("Chain includes the topic");
+            if let Some(start) = names.iter().position(|name| *name == parent) {
+                let mut cycle = names[start..].to_vec();
+                cycle.push(parent);
+                return Err(format!(
+                    "Topics extend each other in a cycle: {}",
+                    cycle.join(" -> ")
+                ));
+            }
+            match self.get(parent) {
+                Some(Manifest::Conventional(c)) => chain.push(c),
+                Some(Manifest::Cumulative(_)) => {
+                    return Err(format!(
+                        "Topic {} extends cumulative topic {}",
+                        child, parent
+                    ))
+                }
+                None => return Err(format!("Topic {} extends missing topic {}", child, parent)),
+            }
+            names.push(parent);
+        }
+        Ok(chain)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+
+    use std::collections::BTreeMap;
+
+    use crate::test_util::{conventional, cumulative, topics};
+    use crate::{Manifest, ManifestCollection};
+
+    fn extending(extends: &str, packages: &str) -> Manifest {
+        conventional(&format!(
+            "extends = {:?}\n[packages]\n{}",
+            extends, packages
+        ))
+    }
+
+    #[test]
+    fn test_resolve_extends() -> Result<()> {
+        let collection = ManifestCollection::from(topics([
+            (
+                "base",
+                conventional("[packages]\ngcc = \"13.2.0\"\nglibc = \"2.38\""),
+            ),
+            (
+                "kde",
+                conventional(
+                    r#"
+                    extends = "base"
+                    name.default = "KDE"
+                    security = true
+                    caution.default = "Reboot"
+                    [packages]
+                    glibc = "2.39"
+                    konsole = "23.04.1"
+                    [arch_packages.arm64]
+                    dolphin = "23.04.1"
+                    "#,
+                ),
+            ),
+            ("kde-fix", extending("kde", "gcc = false")),
+            ("qt", conventional("[packages]\nqt5-base = \"5.15.11\"")),
+        ]));
+        assert!(collection
+            .get("kde")
+            .unwrap()
+            .to_toml_string()
+            .starts_with("extends = \"base\"\n"));
+
+        let resolved = collection.resolve_extends().unwrap();
+        assert_eq!(resolved.len(), 4);
+        assert_eq!(resolved.get("base"), collection.get("base"));
+        assert_eq!(resolved.get("qt"), collection.get("qt"));
+
+        let Some(Manifest::Conventional(kde)) = resolved.get("kde") else {
+            unreachable!()
+        };
+        assert_eq!(kde.get_extends(), None);
+        assert!(kde.is_security_update());
+        assert!(kde.has_caution());
+        assert_eq!(
+            kde.get_packages().iter().collect::<BTreeMap<_, _>>(),
+            BTreeMap::from([
+                ("gcc", Some("13.2.0")),
+                ("glibc", Some("2.39")),
+                ("konsole", Some("23.04.1")),
+            ])
+        );
+
+        let Some(Manifest::Conventional(fix)) = resolved.get("kde-fix") else {
+            unreachable!()
+        };
+        assert!(!fix.is_security_update());
+        assert_eq!(
+            fix.get_packages().iter().collect::<BTreeMap<_, _>>(),
+            BTreeMap::from([
+                ("gcc", None),
+                ("glibc", Some("2.39")),
+                ("konsole", Some("23.04.1"))
+            ])
+        );
+        assert_eq!(
+            fix.get_packages_for_arch("arm64").get("dolphin"),
+            Some(&Some("23.04.1".to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_extends_errors() -> Result<()> {
+        let missing = ManifestCollection::from(topics([("kde", extending("base", ""))]));
+        assert_eq!(
+            missing.resolve_extends().unwrap_err(),
+            "Topic kde extends missing topic base"
+        );
+
+        let cumulative = ManifestCollection::from(topics([
+            ("kde", extending("all", "")),
+            ("all", cumulative(&["kde"])),
+        ]));
+        assert_eq!(
+            cumulative.resolve_extends().unwrap_err(),
+            "Topic kde extends cumulative topic all"
+        );
+
+        let cycle = ManifestCollection::from(topics([
+            ("a", extending("b", "")),
+            ("b", extending("a", "")),
+            ("c", extending("c", "")),
+        ]));
+        assert_eq!(
+            cycle.resolve_extends().unwrap_err(),
+            "Topics extend each other in a cycle: a -> b -> a"
+        );
+        let itself = ManifestCollection::from(topics([("c", extending("c", ""))]));
+        assert_eq!(
+            itself.resolve_extends().unwrap_err(),
+            "Topics extend each other in a cycle: c -> c"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+//! Resolving cumulative topics into conventional topics
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use super::graph::{CycleError, DependencyGraph};
+use super::{Conventional, Manifest, ManifestCollection};
+
+/// Error returned when cumulative topics can not be flattened
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FlattenError {
+    /// Cumulative topics reference topics missing from the collection, see
+    /// [`ManifestCollection::find_missing_topics`]
+    MissingTopics(Vec<(String, Vec<String>)>),
+    /// Cumulative topics reference each other in a cycle
+    Cycle(CycleError),
+    /// `extends` of a conventional topic can not be resolved, see
+    /// [`ManifestCollection::resolve_extends`]
+    Extends(String),
+}
+
+impl fmt::Display for FlattenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTopics(missing) => {
+                let topics: Vec<&str> = missing.iter().map(|(t, _)| t.as_str()).collect();
+                write!(
+                    f,
+                    "Missing dependencies for cumulative topics: {}",
+                    topics.join(", ")
+                )
+            }
+            Self::Cycle(error) => error.fmt(f),
+            Self::Extends(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for FlattenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::MissingTopics(_) | Self::Extends(_) => None,
+            Self::Cycle(error) => Some(error),
+        }
+    }
+}
+
+impl ManifestCollection {
+    /// Get a new collection where every cumulative topic is replaced by a conventional topic
+    ///
+    /// The new topic carries the packages of all topics the cumulative topic references,
+    /// directly or indirectly. Versions from topics listed later take priority. Conventional
+    /// topics have their `extends` resolved first, see [`ManifestCollection::resolve_extends`].
+    /// Fails if any referenced topic is missing, if topics reference each other in a cycle, or if
+    /// `extends` can not be resolved.
+    pub fn flatten_cumulative(&self) -> Result<ManifestCollection, FlattenError> {
+        let missing = self.find_missing_topics();
+        if !missing.is_empty() {
+            return Err(FlattenError::MissingTopics(missing));
+        }
+        let resolved_extends = self.resolve_extends().map_err(FlattenError::Extends)?;
+        let graph = DependencyGraph::from_collection(self);
+        let order = graph.topo_sort().map_err(FlattenError::Cycle)?;
+
+        // Topics come after everything they reference, so dependencies are always resolved
+        let mut resolved: BTreeMap<&str, Conventional> = BTreeMap::new();
+        for topic in order {
+            let conventional = match &resolved_extends.topics[topic] {
+                Manifest::Conventional(c) => c.clone(),
+                Manifest::Cumulative(c) => {
+                    let topics: Vec<&Conventional> = c
+                        .get_topics()
+                        .iter()
+                        .map(|t| &resolved[t.as_str()])
+                        .collect();
+                    Conventional::flattened(c, &topics)
+                }
+            };
+            resolved.insert(topic, conventional);
+        }
+        Ok(self
+            .topics
+            .keys()
+            .map(|id| {
+                let conventional = resolved.remove(id.as_str()).expect("Every topic is sorted");
+                (id.clone(), Manifest::Conventional(conventional))
+            })
+            .collect::<BTreeMap<_, _>>()
+            .into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+
+    use std::collections::BTreeMap;
+
+    use super::FlattenError;
+    use crate::test_util::{conventional, cumulative, topics};
+    use crate::{Manifest, ManifestCollection, Severity};
+
+    #[test]
+    fn test_flatten() -> Result<()> {
+        let collection = ManifestCollection::from(topics([
+            (
+                "kde".to_string(),
+                conventional("[packages]\nkonsole = \"23.04.1\"\npykde = false"),
+            ),
+            (
+                "kde-fix".to_string(),
+                conventional(
+                    r#"
+                    name.default = "KDE fix"
+                    security = true
+                    caution.default = "Reboot"
+                    severity = "critical"
+                    [packages]
+                    konsole = "23.04.2"
+                    [arch_packages.arm64]
+                    dolphin = "23.04.1"
+                    "#,
+                ),
+            ),
+            (
+                "qt".to_string(),
+                conventional("[packages]\nqt5-base = \"5.15.11\""),
+            ),
+            ("2023H3".to_string(), cumulative(&["kde", "kde-fix"])),
+            ("all".to_string(), cumulative(&["2023H3", "qt"])),
+        ]));
+
+        let flattened = collection.flatten_cumulative()?;
+        assert_eq!(flattened.len(), 5);
+        assert!(flattened.iter().all(|(_, m)| m.is_conventional()));
+        assert_eq!(flattened.get("kde"), collection.get("kde"));
+
+        let Some(Manifest::Conventional(all)) = flattened.get("all") else {
+            unreachable!()
+        };
+        assert_eq!(all.get_name().default.as_deref(), Some("Cumulative"));
+        assert!(all.is_security_update());
+        assert_eq!(all.get_severity(), Some(Severity::Critical));
+        assert!(!all.has_caution());
+        assert_eq!(
+            all.get_packages().iter().collect::<BTreeMap<_, _>>(),
+            BTreeMap::from([
+                ("konsole", Some("23.04.2")),
+                ("pykde", None),
+                ("qt5-base", Some("5.15.11")),
+            ])
+        );
+        assert_eq!(
+            all.get_packages_for_arch("arm64").get("dolphin"),
+            Some(&Some("23.04.1".to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_flatten_extends() -> Result<()> {
+        let collection = ManifestCollection::from(topics([
+            (
+                "base",
+                conventional("[packages]\ngcc = \"13.2.0\"\nglibc = \"2.38\""),
+            ),
+            (
+                "kde",
+                conventional("extends = \"base\"\n[packages]\nglibc = \"2.39\""),
+            ),
+            ("all", cumulative(&["kde"])),
+        ]));
+
+        let flattened = collection.flatten_cumulative()?;
+        let Some(Manifest::Conventional(kde)) = flattened.get("kde") else {
+            unreachable!()
+        };
+        assert_eq!(kde.get_extends(), None);
+        let Some(Manifest::Conventional(all)) = flattened.get("all") else {
+            unreachable!()
+        };
+        assert_eq!(
+            all.get_packages().iter().collect::<BTreeMap<_, _>>(),
+            BTreeMap::from([("gcc", Some("13.2.0")), ("glibc", Some("2.39"))])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_flatten_errors() -> Result<()> {
+        let missing = ManifestCollection::from(topics([("all".to_string(), cumulative(&["kde"]))]));
+        assert_eq!(
+            missing.flatten_cumulative().unwrap_err(),
+            FlattenError::MissingTopics(vec![("all".to_string(), vec!["kde".to_string()])])
+        );
+
+        let cycle = ManifestCollection::from(topics([
+            ("a".to_string(), cumulative(&["b"])),
+            ("b".to_string(), cumulative(&["a"])),
+        ]));
+        let err = cycle.flatten_cumulative().unwrap_err();
+        assert!(matches!(&err, FlattenError::Cycle(e) if e.get_topics() == ["a", "b"]));
+        assert_eq!(err.to_string(), "Dependency cycle among topics: a, b");
+
+        let extends = ManifestCollection::from(topics([
+            ("kde", conventional("extends = \"base\"")),
+            ("all", cumulative(&["kde"])),
+        ]));
+        assert_eq!(
+            extends.flatten_cumulative().unwrap_err(),
+            FlattenError::Extends("Topic kde extends missing topic base".to_string())
+        );
+        Ok(())
+    }
+}
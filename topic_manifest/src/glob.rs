@@ -0,0 +1,127 @@
+//! Matching paths against glob patterns
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use std::path::Path;
+
+/// Compiled [`LoadOptions::exclude`] and [`LoadOptions::include`] patterns
+///
+/// [`LoadOptions::exclude`]: crate::LoadOptions::exclude
+/// [`LoadOptions::include`]: crate::LoadOptions::include
+pub(crate) struct PathFilter {
+    exclude: GlobSet,
+    /// Exclude patterns like `templates/**`, with and without the trailing `/**`, matching
+    /// directories whose contents are all excluded
+    exclude_dirs: GlobSet,
+    include: GlobSet,
+    /// Parts of include patterns before their first special character
+    include_prefixes: Vec<String>,
+}
+
+/// Build a set of patterns where `*` and `?` do not match `/`
+fn build_set<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+    }
+    builder.build()
+}
+
+impl PathFilter {
+    pub(crate) fn new(exclude: &[String], include: &[String]) -> Result<Self, globset::Error> {
+        let include_prefixes = include
+            .iter()
+            .map(|p| {
+                let end = p.find(['*', '?', '[', '{', '\\']).unwrap_or(p.len());
+                p[..end].to_string()
+            })
+            .collect();
+        let exclude_dirs = exclude
+            .iter()
+            .filter_map(|p| Some([p.strip_suffix("/**")?, p.as_str()]))
+            .flatten();
+        Ok(Self {
+            exclude: build_set(exclude.iter().map(String::as_str))?,
+            exclude_dirs: build_set(exclude_dirs)?,
+            include: build_set(include.iter().map(String::as_str))?,
+            include_prefixes,
+        })
+    }
+
+    /// Should the file at `relative`, a path relative to the loaded directory, be loaded
+    pub(crate) fn is_included(&self, relative: &Path) -> bool {
+        !self.exclude.is_match(relative) || self.include.is_match(relative)
+    }
+
+    /// Can the directory at `relative` be skipped, since no file in it would be loaded
+    pub(crate) fn skips_dir(&self, relative: &Path) -> bool {
+        if !self.exclude_dirs.is_match(relative) {
+            return false;
+        }
+        let dir = format!("{}/", to_slash(relative));
+        !self
+            .include_prefixes
+            .iter()
+            .any(|prefix| dir.starts_with(prefix.as_str()) || prefix.starts_with(&dir))
+    }
+}
+
+/// `path` with components separated by `/`
+fn to_slash(path: &Path) -> String {
+    let components: Vec<_> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    components.join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::PathFilter;
+
+    #[test]
+    fn test_is_included() {
+        let cases = [
+            ("kde.toml", "kde.toml", true),
+            ("*.toml", "kde.toml", true),
+            ("*.toml", "nested/kde.toml", false),
+            ("kde-?.toml", "kde-1.toml", true),
+            ("kde-?.toml", "kde-10.toml", false),
+            ("templates/**", "templates/kde.toml", true),
+            ("templates/**", "templates/nested/kde.toml", true),
+            ("templates/**", "topics/templates/kde.toml", false),
+            ("**/templates/**", "topics/templates/kde.toml", true),
+            ("**/templates/**", "templates/kde.toml", true),
+            ("**/*.toml", "kde.toml", true),
+            ("**/*.toml", "a/b/c/kde.toml", true),
+            ("**/_*", "a/_config.toml", true),
+            ("**/_*", "a/b_config.toml", false),
+            ("a/*/c.toml", "a/b/c.toml", true),
+            ("a/*/c.toml", "a/b/b/c.toml", false),
+            ("{kde,qt}.toml", "qt.toml", true),
+            ("核心/*", "核心/更新.toml", true),
+        ];
+        for (pattern, path, excluded) in cases {
+            let filter = PathFilter::new(&[pattern.to_string()], &[]).unwrap();
+            let included = filter.is_included(Path::new(path));
+            assert_eq!(included, !excluded, "{} {}", pattern, path);
+        }
+        assert!(PathFilter::new(&["[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_skips_dir() {
+        let exclude = ["templates/**".to_string(), "**/drafts/**".to_string()];
+        let filter = PathFilter::new(&exclude, &["templates/nested/*".to_string()]).unwrap();
+        assert!(filter.skips_dir(Path::new("templates/other")));
+        assert!(filter.skips_dir(Path::new("a/drafts")));
+        assert!(!filter.skips_dir(Path::new("templates")));
+        assert!(!filter.skips_dir(Path::new("templates/nested")));
+        assert!(!filter.skips_dir(Path::new("drafts-2023")));
+
+        let filter = PathFilter::new(&exclude, &["**/keep.toml".to_string()]).unwrap();
+        assert!(!filter.skips_dir(Path::new("a/drafts")));
+    }
+}
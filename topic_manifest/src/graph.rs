@@ -0,0 +1,291 @@
+//! Dependency graph of topics
+//!
+//! Each topic in the collection is a node, and each cumulative topic has an edge pointing to every
+//! topic it references. Topics referenced by a cumulative topic but missing from the collection are
+//! also included as nodes, so they show up as leaves of the graph.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+
+use super::{Manifest, ManifestCollection};
+
+/// Dependency graph built from a [`ManifestCollection`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    successors: BTreeMap<String, BTreeSet<String>>,
+    predecessors: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Error returned when the graph contains cycles
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CycleError {
+    topics: Vec<String>,
+}
+
+impl DependencyGraph {
+    /// Build the dependency graph of a manifest collection
+    pub fn from_collection(collection: &ManifestCollection) -> Self {
+        let mut graph = Self::default();
+        for (name, manifest) in collection.iter() {
+            graph.add_node(name);
+            if let Manifest::Cumulative(c) = manifest {
+                for topic in c.get_topics() {
+                    graph.add_edge(name, topic);
+                }
+            }
+        }
+        graph
+    }
+
+    /// Remove edges from topics to themselves, so only cycles of multiple topics remain
+    pub(crate) fn remove_self_loops(&mut self) {
+        for (topic, successors) in self.successors.iter_mut() {
+            successors.remove(topic);
+        }
+        for (topic, predecessors) in self.predecessors.iter_mut() {
+            predecessors.remove(topic);
+        }
+    }
+
+    fn add_node(&mut self, topic: &str) {
+        self.successors.entry(topic.to_string()).or_default();
+        self.predecessors.entry(topic.to_string()).or_default();
+    }
+
+    fn add_edge(&mut self, from: &str, to: &str) {
+        self.add_node(from);
+        self.add_node(to);
+        self.successors
+            .get_mut(from)
+            .expect("Node inserted above")
+            .insert(to.to_string());
+        self.predecessors
+            .get_mut(to)
+            .expect("Node inserted above")
+            .insert(from.to_string());
+    }
+
+    /// Number of nodes in the graph
+    pub fn len(&self) -> usize {
+        self.successors.len()
+    }
+
+    /// Is the graph empty
+    pub fn is_empty(&self) -> bool {
+        self.successors.is_empty()
+    }
+
+    /// Does the graph contain this topic
+    pub fn contains(&self, topic: &str) -> bool {
+        self.successors.contains_key(topic)
+    }
+
+    /// Iterate over all topics in the graph
+    pub fn nodes(&self) -> impl Iterator<Item = &str> {
+        self.successors.keys().map(|k| k.as_str())
+    }
+
+    /// Iterate over all edges in the graph, in the form of `(cumulative topic, referenced topic)`
+    pub fn edges(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.successors
+            .iter()
+            .flat_map(|(k, v)| v.iter().map(move |t| (k.as_str(), t.as_str())))
+    }
+
+    /// Topics not referenced by any other topic
+    pub fn roots(&self) -> Vec<&str> {
+        self.predecessors
+            .iter()
+            .filter(|(_, v)| v.is_empty())
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+
+    /// Topics not referencing any other topic
+    pub fn leaves(&self) -> Vec<&str> {
+        self.successors
+            .iter()
+            .filter(|(_, v)| v.is_empty())
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+
+    /// Topics referencing this topic, `None` if the topic is not in the graph
+    pub fn predecessors(&self, topic: &str) -> Option<Vec<&str>> {
+        self.predecessors
+            .get(topic)
+            .map(|v| v.iter().map(|t| t.as_str()).collect())
+    }
+
+    /// Topics referenced by this topic, `None` if the topic is not in the graph
+    pub fn successors(&self, topic: &str) -> Option<Vec<&str>> {
+        self.successors
+            .get(topic)
+            .map(|v| v.iter().map(|t| t.as_str()).collect())
+    }
+
+    /// Is the graph free of cycles
+    pub fn is_dag(&self) -> bool {
+        self.topo_sort().is_ok()
+    }
+
+    /// Sort topics so that every topic comes after all topics it references
+    ///
+    /// Topics with no ordering constraint between them are sorted by name.
+    pub fn topo_sort(&self) -> Result<Vec<&str>, CycleError> {
+        let mut remaining: BTreeMap<&str, usize> = self
+            .successors
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.len()))
+            .collect();
+        let mut ready: BTreeSet<&str> = remaining
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .map(|(k, _)| *k)
+            .collect();
+        let mut sorted = Vec::with_capacity(self.len());
+
+        while let Some(topic) = ready.pop_first() {
+            remaining.remove(topic);
+            sorted.push(topic);
+            for pred in &self.predecessors[topic] {
+                let count = remaining
+                    .get_mut(pred.as_str())
+                    .expect("Predecessor not sorted yet");
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(pred.as_str());
+                }
+            }
+        }
+
+        if remaining.is_empty() {
+            Ok(sorted)
+        } else {
+            Err(CycleError {
+                topics: remaining.into_keys().map(|k| k.to_string()).collect(),
+            })
+        }
+    }
+}
+
+impl CycleError {
+    /// Topics involved in or depending on a cycle
+    pub fn get_topics(&self) -> &[String] {
+        &self.topics
+    }
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Dependency cycle among topics: {}",
+            self.topics.join(", ")
+        )
+    }
+}
+
+impl Error for CycleError {}
+
+impl ManifestCollection {
+    /// Get a new collection of `root` and all topics it references or extends, directly or
+    /// indirectly
+    ///
+    /// Referenced topics missing from this collection are skipped, so the new collection is
+    /// consistent as long as this one is. Fails if `root` is not in the collection.
+    pub fn subgraph(&self, root: &str) -> Result<ManifestCollection, String> {
+        if self.get(root).is_none() {
+            return Err(format!("Topic {} not found", root));
+        }
+        let mut topics = BTreeMap::new();
+        let mut stack = vec![root];
+        while let Some(topic) = stack.pop() {
+            if topics.contains_key(topic) {
+                continue;
+            }
+            let Some((id, manifest)) = self.topics.get_key_value(topic) else {
+                continue;
+            };
+            match manifest {
+                Manifest::Conventional(c) => stack.extend(c.get_extends()),
+                Manifest::Cumulative(c) => stack.extend(c.get_topics().iter().map(String::as_str)),
+            }
+            topics.insert(id.clone(), manifest.clone());
+        }
+        Ok(topics.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DependencyGraph;
+    use crate::test_util::{conventional, cumulative, topics};
+    use crate::ManifestCollection;
+
+    #[test]
+    fn test_graph() {
+        let collection = ManifestCollection::from(topics([
+            ("a".to_string(), cumulative(&["b", "c"])),
+            ("b".to_string(), cumulative(&["c"])),
+            ("c".to_string(), cumulative(&[])),
+            ("d".to_string(), cumulative(&["missing"])),
+        ]));
+        let graph = DependencyGraph::from_collection(&collection);
+        assert_eq!(graph.len(), 5);
+        assert!(graph.contains("missing"));
+        assert_eq!(graph.roots(), ["a", "d"]);
+        assert_eq!(graph.leaves(), ["c", "missing"]);
+        assert_eq!(graph.predecessors("c"), Some(vec!["a", "b"]));
+        assert_eq!(graph.successors("a"), Some(vec!["b", "c"]));
+        assert_eq!(graph.successors("nonexistent"), None);
+        assert!(graph.is_dag());
+        assert_eq!(graph.topo_sort().unwrap(), ["c", "b", "a", "missing", "d"]);
+    }
+
+    #[test]
+    fn test_subgraph() {
+        let collection = ManifestCollection::from(topics([
+            ("a".to_string(), cumulative(&["b", "c"])),
+            ("b".to_string(), cumulative(&["c", "a"])),
+            ("c".to_string(), cumulative(&[])),
+            ("d".to_string(), cumulative(&["c"])),
+        ]));
+        let subgraph = collection.subgraph("b").unwrap();
+        assert_eq!(
+            subgraph.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["a", "b", "c"]
+        );
+        assert!(subgraph.is_consistent());
+        assert_eq!(collection.subgraph("c").unwrap().len(), 1);
+        assert_eq!(collection.subgraph("e").unwrap_err(), "Topic e not found");
+
+        // Extended topics are followed too
+        let collection = ManifestCollection::from(topics([
+            ("all", cumulative(&["kde"])),
+            ("kde", conventional("extends = \"qt\"")),
+            ("qt", conventional("extends = \"base\"")),
+            ("base", conventional("")),
+            ("other", conventional("")),
+        ]));
+        let subgraph = collection.subgraph("all").unwrap();
+        assert_eq!(
+            subgraph.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["all", "base", "kde", "qt"]
+        );
+        assert!(subgraph.resolve_extends().is_ok());
+    }
+
+    #[test]
+    fn test_cycle() {
+        let collection = ManifestCollection::from(topics([
+            ("a".to_string(), cumulative(&["b"])),
+            ("b".to_string(), cumulative(&["a"])),
+            ("c".to_string(), cumulative(&[])),
+        ]));
+        let graph = DependencyGraph::from_collection(&collection);
+        assert!(!graph.is_dag());
+        assert_eq!(graph.topo_sort().unwrap_err().get_topics(), ["a", "b"]);
+    }
+}
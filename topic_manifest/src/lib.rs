@@ -2,36 +2,80 @@
 //!
 //! Collection of types for serializing, deserializing, and processing topic manifests for AOSC OS.
 
+mod arch;
 pub mod conventional;
 pub mod cumulative;
+#[cfg(feature = "chrono")]
+mod date;
+pub mod diff;
+mod extends;
+pub mod flatten;
+mod glob;
+pub mod graph;
+pub mod lint;
+pub mod loader;
+pub mod normalize;
 pub mod packages;
+pub mod parse_error;
+#[cfg(feature = "json")]
+pub mod stats;
+#[cfg(test)]
+mod test_util;
+mod toml_ser;
+pub mod topic_id;
+pub mod translation;
+pub mod validation;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 use serde::{Deserialize, Serialize};
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+#[cfg(feature = "json")]
+use std::io::Write;
 
 pub use localized::{Locale, Localized};
 
-pub use conventional::Conventional;
+pub use conventional::{Conventional, Severity};
 pub use cumulative::Cumulative;
+pub use diff::CollectionDiff;
+pub use flatten::FlattenError;
+pub use graph::DependencyGraph;
+pub use lint::{LintFinding, LintRules};
+pub use loader::{LoadError, LoadOptions, LoadWarning, TopicSources};
+pub use normalize::NormalizeOptions;
 pub use packages::Packages;
+#[cfg(feature = "json")]
+pub use stats::{CollectionStats, TopicStats};
+pub use topic_id::{InvalidTopicIdError, TopicId};
+pub use translation::{LocalizedField, MissingTranslation};
+pub use validation::{
+    is_valid_http_url, topic_matches_name, ValidateOptions, ValidationIssue, ValidationReport,
+};
 
 /// Internal type for deserializing untagged manifest data
 #[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 enum ManifestUntagged {
     Conventional(Conventional),
     Cumulative(Cumulative),
 }
 
 /// Topic update manifest
-#[derive(Clone, Debug, Serialize, Deserialize)]
+///
+/// The `Hash` implementation hashes the in-memory structure, so a manifest hashes the same no matter
+/// which format it was deserialized from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
 #[serde(from = "ManifestUntagged")]
 #[serde(rename_all = "lowercase")]
+// Most topics are conventional, boxing them would only add an allocation
+#[allow(clippy::large_enum_variant)]
 pub enum Manifest {
     /// A conventional topic
     Conventional(Conventional),
@@ -39,11 +83,69 @@ pub enum Manifest {
     Cumulative(Cumulative),
 }
 
+/// Serialization format of manifest collections
+#[cfg(feature = "json")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Pretty-printed JSON
+    #[default]
+    Json,
+    /// JSON without any whitespace
+    JsonCompact,
+    /// YAML, with the `yaml` feature
+    #[cfg(feature = "yaml")]
+    Yaml,
+    /// MessagePack with named fields, with the `msgpack` feature
+    #[cfg(feature = "msgpack")]
+    MsgPack,
+}
+
+/// Error serializing a manifest collection, see [`ManifestCollection::to_writer`]
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub enum OutputError {
+    /// Failed to write JSON
+    Json(serde_json::Error),
+    /// Failed to write YAML
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
+    /// Failed to write MessagePack
+    #[cfg(feature = "msgpack")]
+    MsgPack(rmp_serde::encode::Error),
+}
+
+#[cfg(feature = "json")]
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(error) => error.fmt(f),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(error) => error.fmt(f),
+            #[cfg(feature = "msgpack")]
+            Self::MsgPack(error) => error.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl std::error::Error for OutputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(error) => Some(error),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(error) => Some(error),
+            #[cfg(feature = "msgpack")]
+            Self::MsgPack(error) => Some(error),
+        }
+    }
+}
+
 /// Collection of multiple topic manifests
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ManifestCollection {
     #[serde(flatten)]
-    topics: BTreeMap<String, Manifest>,
+    topics: BTreeMap<TopicId, Manifest>,
 }
 
 impl From<ManifestUntagged> for Manifest {
@@ -55,26 +157,181 @@ impl From<ManifestUntagged> for Manifest {
     }
 }
 
-impl From<BTreeMap<String, Manifest>> for ManifestCollection {
-    fn from(value: BTreeMap<String, Manifest>) -> Self {
+impl From<BTreeMap<TopicId, Manifest>> for ManifestCollection {
+    fn from(value: BTreeMap<TopicId, Manifest>) -> Self {
         Self { topics: value }
     }
 }
 
+impl TryFrom<BTreeMap<String, Manifest>> for ManifestCollection {
+    type Error = InvalidTopicIdError;
+
+    /// Build a collection from plain topic names, failing on the first invalid one
+    fn try_from(value: BTreeMap<String, Manifest>) -> Result<Self, Self::Error> {
+        let topics = value
+            .into_iter()
+            .map(|(k, v)| Ok((TopicId::try_from(k)?, v)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { topics })
+    }
+}
+
+impl From<ManifestCollection> for BTreeMap<TopicId, Manifest> {
+    fn from(value: ManifestCollection) -> Self {
+        value.topics
+    }
+}
+
+/// Append `value` to `out` in the canonical form of [`ManifestCollection::to_canonical_json`]
+#[cfg(feature = "json")]
+fn write_canonical_json(value: &serde_json::Value, out: &mut String) {
+    use serde_json::Value;
+
+    match value {
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(k.clone()).to_string());
+                out.push(':');
+                write_canonical_json(v, out);
+            }
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+/// Localized string for the first available locale in `locales`, see [`Localized::get_with_fallback`]
+pub(crate) fn localized_for<'a>(localized: &'a Localized<String>, locales: &[Locale]) -> &'a str {
+    localized
+        .get_with_fallback(locales)
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+/// Name of a topic for human-readable output, preferring the default value
+pub(crate) fn display_name(name: &Localized<String>) -> &str {
+    localized_for(name, &[])
+}
+
+/// Plural suffix for `n` items
+pub(crate) fn plural(n: usize) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
 impl Manifest {
     /// Is this a conventional topic
     pub fn is_conventional(&self) -> bool {
-        match self {
-            Self::Conventional(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Conventional(_))
     }
 
     /// Is this a cumulative topic
     pub fn is_cumulative(&self) -> bool {
+        matches!(self, Self::Cumulative(_))
+    }
+
+    /// Get the topic name set by the `topic` (or `id`) key of the manifest
+    ///
+    /// When set, it is used as the topic name instead of the file stem. It is only read from TOML
+    /// manifests, and never serialized, since the topic name is the key in a collection.
+    pub fn get_topic_id(&self) -> Option<&TopicId> {
         match self {
-            Self::Cumulative(_) => true,
-            _ => false,
+            Self::Conventional(c) => c.get_topic_id(),
+            Self::Cumulative(c) => c.get_topic_id(),
+        }
+    }
+
+    /// Get name of the topic
+    pub fn get_name(&self) -> &Localized<String> {
+        match self {
+            Self::Conventional(c) => c.get_name(),
+            Self::Cumulative(c) => c.get_name(),
+        }
+    }
+
+    /// Get name of the topic in the first available locale in `locales`
+    pub fn name_for(&self, locales: &[Locale]) -> &str {
+        localized_for(self.get_name(), locales)
+    }
+
+    /// Get name of the topic for `locale`, see [`Localized::get_with_fallback`]
+    ///
+    /// Returns `None` only if the topic has no name at all.
+    pub fn get_name_for_locale(&self, locale: &Locale) -> Option<&str> {
+        self.get_name()
+            .get_with_fallback(std::slice::from_ref(locale))
+            .map(String::as_str)
+    }
+
+    /// Iterate over locales used in localized fields of the topic, including cautions of single
+    /// packages
+    pub fn locales(&self) -> impl Iterator<Item = &Locale> {
+        let (name, conventional) = match self {
+            Self::Conventional(c) => (c.get_name(), Some(c)),
+            Self::Cumulative(c) => (c.get_name(), None),
+        };
+        let cautions = conventional.into_iter().flat_map(|c| {
+            std::iter::once(c.get_caution()).chain(
+                c.get_per_package_caution()
+                    .into_iter()
+                    .flat_map(|m| m.values()),
+            )
+        });
+        name.content
+            .keys()
+            .chain(cautions.flat_map(|c| c.content.keys()))
+    }
+
+    /// Get date of the topic
+    #[cfg(feature = "chrono")]
+    pub fn get_date(&self) -> Option<chrono::NaiveDate> {
+        match self {
+            Self::Conventional(c) => c.get_date(),
+            Self::Cumulative(c) => c.get_date(),
+        }
+    }
+
+    /// Does this topic apply to `arch`
+    pub fn is_applicable_for_arch(&self, arch: &str) -> bool {
+        match self {
+            Self::Conventional(c) => c.is_applicable_for_arch(arch),
+            Self::Cumulative(c) => c.is_applicable_for_arch(arch),
+        }
+    }
+
+    /// Multi-line human-readable summary of the topic, listing its packages or topics
+    pub fn summary(&self) -> String {
+        match self {
+            Self::Conventional(c) => c.summary(),
+            Self::Cumulative(c) => c.summary(),
+        }
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Conventional(c) => c.fmt(f),
+            Self::Cumulative(c) => c.fmt(f),
         }
     }
 }
@@ -90,47 +347,402 @@ impl ManifestCollection {
         self.topics.is_empty()
     }
 
+    /// Iterate over topic names and manifests, sorted by topic name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Manifest)> {
+        self.topics.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Get the underlying map of topic names and manifests
+    pub fn as_map(&self) -> &BTreeMap<TopicId, Manifest> {
+        &self.topics
+    }
+
+    /// Convert the collection into a map of topic names and manifests
+    pub fn into_inner(self) -> BTreeMap<TopicId, Manifest> {
+        self.topics
+    }
+
+    /// Get a topic manifest by name
+    pub fn get(&self, topic: &str) -> Option<&Manifest> {
+        self.topics.get(topic)
+    }
+
+    /// Add topics in `other` to the collection, replacing those with the same names
+    ///
+    /// Returns topics added and those replaced with different content, nothing is removed.
+    pub fn merge(&mut self, other: ManifestCollection) -> CollectionDiff {
+        let mut diff = CollectionDiff::default();
+        for (topic, manifest) in other.topics {
+            match self.topics.get(&topic) {
+                None => diff.added.push(topic.to_string()),
+                Some(old) if manifest != *old => diff.push_changed(&topic, &manifest, old),
+                Some(_) => (),
+            }
+            self.topics.insert(topic, manifest);
+        }
+        diff
+    }
+
+    /// Keep only topics for which `f` returns `true`
+    ///
+    /// Unlike [`ManifestCollection::retain_arch`], references in cumulative topics are kept.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &Manifest) -> bool,
+    {
+        self.topics.retain(|k, v| f(k, v));
+    }
+
+    /// Remove topics not applicable to `arch`, along with references to them in cumulative topics
+    ///
+    /// Conventional topics extending removed topics, directly or indirectly, have their `extends`
+    /// resolved first, see [`ManifestCollection::resolve_extends`], so they keep the inherited
+    /// packages. References to topics missing from the collection in the first place are kept,
+    /// and so are `extends` that can not be resolved.
+    pub fn retain_arch(&mut self, arch: &str) {
+        let removed: BTreeSet<String> = self
+            .topics
+            .iter()
+            .filter(|(_, v)| !v.is_applicable_for_arch(arch))
+            .map(|(k, _)| k.to_string())
+            .collect();
+        let inheriting: Vec<(TopicId, Conventional)> = self
+            .topics
+            .iter()
+            .filter_map(|(k, v)| match v {
+                Manifest::Conventional(c) if !removed.contains(k.as_str()) => {
+                    let chain = self.extends_chain(k, c).ok()?;
+                    let mut parents = chain.iter().filter_map(|c| c.get_extends());
+                    if !parents.any(|p| removed.contains(p)) {
+                        return None;
+                    }
+                    Some((k.clone(), self.resolve_conventional(k, c).ok()?))
+                }
+                _ => None,
+            })
+            .collect();
+        for (topic, conventional) in inheriting {
+            self.topics
+                .insert(topic, Manifest::Conventional(conventional));
+        }
+        self.topics.retain(|k, _| !removed.contains(k.as_str()));
+        for manifest in self.topics.values_mut() {
+            if let Manifest::Cumulative(c) = manifest {
+                c.retain_topics(|t| !removed.contains(t));
+            }
+        }
+    }
+
+    /// Resolve package updates of conventional topics for `arch`, see
+    /// [`Conventional::resolve_arch`]
+    pub fn resolve_arch_packages(&mut self, arch: &str) {
+        for manifest in self.topics.values_mut() {
+            if let Manifest::Conventional(c) = manifest {
+                c.resolve_arch(arch);
+            }
+        }
+    }
+
+    /// Get a new collection of topics applicable to `arch`, see [`ManifestCollection::retain_arch`]
+    ///
+    /// Cumulative topics are kept with references to filtered-out topics removed, and topics
+    /// extending filtered-out topics carry their packages instead, so the new collection is
+    /// consistent as long as this one is.
+    pub fn filter_by_arch(&self, arch: &str) -> ManifestCollection {
+        let mut filtered = self.clone();
+        filtered.retain_arch(arch);
+        filtered
+    }
+
+    /// Names of all packages updated or removed by conventional topics, including per architecture
+    pub fn all_packages(&self) -> BTreeSet<&str> {
+        let mut names = BTreeSet::new();
+        for manifest in self.topics.values() {
+            if let Manifest::Conventional(c) = manifest {
+                names.extend(c.package_names());
+            }
+        }
+        names
+    }
+
+    /// Names of conventional topics updating or removing `pkg`, including per architecture
+    pub fn topics_for_package(&self, pkg: &str) -> Vec<&str> {
+        self.topics
+            .iter()
+            .filter_map(|(k, v)| match v {
+                Manifest::Conventional(c) if c.get_packages().contains(pkg) => Some(k.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Map each package to the conventional topics updating or removing it, see
+    /// [`ManifestCollection::topics_for_package`]
+    pub fn package_index(&self) -> BTreeMap<String, Vec<String>> {
+        let mut index: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (topic, manifest) in &self.topics {
+            let Manifest::Conventional(c) = manifest else {
+                continue;
+            };
+            for name in c.package_names() {
+                let topics = index.entry(name.to_string()).or_default();
+                // Topics are visited in order, so a repeated package only needs checking the last
+                if topics.last().map(String::as_str) != Some(topic.as_str()) {
+                    topics.push(topic.to_string());
+                }
+            }
+        }
+        index
+    }
+
+    /// Iterate over conventional topics flagged as security updates
+    pub fn security_topics(&self) -> impl Iterator<Item = (&str, &Conventional)> {
+        self.topics.iter().filter_map(|(k, v)| match v {
+            Manifest::Conventional(c) if c.is_security_update() => Some((k.as_str(), c)),
+            _ => None,
+        })
+    }
+
+    /// Get a list of topics carrying caution messages
+    ///
+    /// Cumulative topics are included if any of their topics carries a caution message.
+    pub fn topics_with_caution(&self) -> Vec<&str> {
+        let has_caution = |topic: &str| match self.topics.get(topic) {
+            Some(Manifest::Conventional(c)) => c.has_caution(),
+            _ => false,
+        };
+        self.topics
+            .iter()
+            .filter(|(k, v)| match v {
+                Manifest::Conventional(_) => has_caution(k),
+                Manifest::Cumulative(c) => c.get_topics().iter().any(|t| has_caution(t)),
+            })
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+
+    /// Count conventional topics flagged as security updates
+    pub fn count_security_topics(&self) -> usize {
+        #[cfg(not(feature = "parallel"))]
+        let iter = self.topics.values();
+        #[cfg(feature = "parallel")]
+        let iter = self.topics.par_iter().map(|(_, v)| v);
+
+        iter.filter(|v| matches!(v, Manifest::Conventional(c) if c.is_security_update()))
+            .count()
+    }
+
+    /// Get a list of topics dated before `today`
+    #[cfg(feature = "chrono")]
+    pub fn expired(&self, today: chrono::NaiveDate) -> Vec<&str> {
+        self.topics
+            .iter()
+            .filter(|(_, v)| matches!(v.get_date(), Some(date) if date < today))
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+
     /// Get a list of missing topics in the manifest collection
+    ///
+    /// The list is sorted by topic name, and so are the missing topics of each topic, with or
+    /// without the `parallel` feature.
     pub fn find_missing_topics(&self) -> Vec<(String, Vec<String>)> {
         #[cfg(not(feature = "parallel"))]
         let iter = self.topics.iter();
         #[cfg(feature = "parallel")]
         let iter = self.topics.par_iter();
 
-        iter.filter_map(|(k, v)| {
+        let mut result: Vec<(String, Vec<String>)> = iter.filter_map(|(k, v)| {
             match v {
                 Manifest::Conventional(_) => None,
                 Manifest::Cumulative(c) => Some((k, c.get_topics())),
             }
         }).filter_map(|(k, v)| {
-            let missing: Vec<String> = v.iter().filter_map(|topic: &String| {
-                if ! self.topics.contains_key(topic) {
+            let mut missing: Vec<String> = v.iter().filter_map(|topic: &String| {
+                if ! self.topics.contains_key(topic.as_str()) {
                     Some(topic.to_string())
                 } else {
                     None
                 }
             }).collect();
-            if missing.is_empty() {
-                None
-            } else {
-                Some((k.to_string(), missing))
-            }
-        }).collect()
+                if missing.is_empty() {
+                    None
+                } else {
+                    missing.sort_unstable();
+                    Some((k.to_string(), missing))
+                }
+            })
+            .collect();
+        result.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        result
+    }
+
+    /// Get a list of conventional topics not referenced by any cumulative topic
+    ///
+    /// These are the conventional [roots](DependencyGraph::roots) of the dependency graph, and
+    /// candidates for archival. Cumulative topics are never orphans, as they are top-level topics.
+    pub fn orphan_conventional_topics(&self) -> Vec<&str> {
+        let referenced: BTreeSet<&str> = self
+            .topics
+            .values()
+            .filter_map(|v| match v {
+                Manifest::Conventional(_) => None,
+                Manifest::Cumulative(c) => Some(c.get_topics()),
+            })
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        self.topics
+            .iter()
+            .filter(|(k, v)| v.is_conventional() && !referenced.contains(k.as_str()))
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+
+    /// Get a list of cumulative topics referencing themselves
+    pub fn find_self_references(&self) -> Vec<String> {
+        self.topics
+            .iter()
+            .filter(|(k, v)| match v {
+                Manifest::Conventional(_) => false,
+                Manifest::Cumulative(c) => c.contains_topic(k),
+            })
+            .map(|(k, _)| k.to_string())
+            .collect()
+    }
+
+    /// Get all locales used in localized fields of the collection, `default` excluded
+    pub fn available_locales(&self) -> BTreeSet<Locale> {
+        self.topics
+            .values()
+            .flat_map(|v| v.locales())
+            .cloned()
+            .collect()
+    }
+
+    /// Get a list of topics with locales not spelled in the canonical BCP 47 form (`zh-CN`)
+    ///
+    /// Original spellings of the locales are returned. They are still accepted, but serialized in
+    /// the canonical form.
+    pub fn find_noncanonical_locales(&self) -> Vec<(String, Vec<String>)> {
+        self.topics
+            .iter()
+            .filter_map(|(k, v)| {
+                let noncanonical: BTreeSet<&str> =
+                    v.locales().filter_map(|l| l.get_original()).collect();
+                if noncanonical.is_empty() {
+                    None
+                } else {
+                    Some((
+                        k.to_string(),
+                        noncanonical.into_iter().map(str::to_string).collect(),
+                    ))
+                }
+            })
+            .collect()
+    }
+
+    /// Get a list of topics with invalid locales, see [`Locale::is_valid`]
+    ///
+    /// Locales are returned in their original spellings.
+    pub fn find_invalid_locales(&self) -> Vec<(String, Vec<String>)> {
+        self.topics
+            .iter()
+            .filter_map(|(k, v)| {
+                let invalid: BTreeSet<String> = v
+                    .locales()
+                    .filter(|l| !l.is_valid())
+                    .map(|l| match l.get_original() {
+                        Some(original) => original.to_string(),
+                        None => l.to_string(),
+                    })
+                    .collect();
+                if invalid.is_empty() {
+                    None
+                } else {
+                    Some((k.to_string(), invalid.into_iter().collect()))
+                }
+            })
+            .collect()
     }
 
     /// Is this topic manifest collection consistent
     pub fn is_consistent(&self) -> bool {
         self.find_missing_topics().is_empty()
     }
+
+    /// Parse a manifest collection from JSON
+    #[cfg(feature = "json")]
+    pub fn from_json_str(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
+    /// Serialize the collection to compact JSON
+    #[cfg(feature = "json")]
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Serialize the collection to pretty-printed JSON
+    #[cfg(feature = "json")]
+    pub fn to_json_string_pretty(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Serialize the collection to canonical JSON, suitable for signing
+    ///
+    /// The canonical form has no whitespace, and keys of every object are sorted by Unicode code
+    /// point, regardless of struct field order or the `preserve-order` feature. Arrays keep their
+    /// order, and strings are escaped the same way as [`ManifestCollection::to_json_string`].
+    #[cfg(feature = "json")]
+    pub fn to_canonical_json(&self) -> String {
+        let value = serde_json::to_value(self).expect("manifests always serialize to JSON");
+        let mut out = String::new();
+        write_canonical_json(&value, &mut out);
+        out
+    }
+
+    /// Serialize the collection to a writer in the given format
+    #[cfg(feature = "json")]
+    pub fn to_writer<W: Write>(&self, format: OutputFormat, writer: W) -> Result<(), OutputError> {
+        match format {
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(writer, self).map_err(OutputError::Json)
+            }
+            OutputFormat::JsonCompact => {
+                serde_json::to_writer(writer, self).map_err(OutputError::Json)
+            }
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml => serde_yaml::to_writer(writer, self).map_err(OutputError::Yaml),
+            #[cfg(feature = "msgpack")]
+            OutputFormat::MsgPack => {
+                let mut writer = writer;
+                rmp_serde::encode::write_named(&mut writer, self).map_err(OutputError::MsgPack)
+            }
+        }
+    }
+
+    /// Generate the JSON Schema (draft 7) of manifest collections
+    #[cfg(feature = "schemars")]
+    pub fn json_schema() -> schemars::Schema {
+        schemars::generate::SchemaSettings::draft07()
+            .into_generator()
+            .into_root_schema_for::<Self>()
+    }
 }
 
 #[cfg(test)]
 mod test {
     use eyre::Result;
 
-    use std::collections::BTreeMap;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::{BTreeMap, BTreeSet, HashSet};
+    use std::hash::{Hash, Hasher};
 
-    use super::{Manifest, ManifestCollection};
+    use super::test_util::{conventional, cumulative, topics};
+    use super::{
+        Conventional, Cumulative, Locale, Manifest, ManifestCollection, Packages, TopicId,
+    };
 
     #[test]
     fn test_manifest_serde() -> Result<()> {
@@ -165,18 +777,40 @@ mod test {
         let converted2 = toml::from_str::<Manifest>(example2)?;
         assert!(matches!(converted1, Manifest::Conventional(_)));
         assert!(matches!(converted2, Manifest::Cumulative(_)));
+        assert_eq!(converted1.get_name()["zh_CN"], "KDE 更新（2023 年冬季）");
+        assert_eq!(
+            converted1.get_name_for_locale(&Locale::new("zh-CN")),
+            Some("KDE 更新（2023 年冬季）")
+        );
+        assert_eq!(
+            converted1.get_name_for_locale(&Locale::new("en-GB")),
+            Some("KDE Updates (Winter 2023)")
+        );
+        assert_eq!(
+            converted2.get_name().get_default().map(|s| s.as_str()),
+            Some("Winter 2023 Cumulative Update for amd64 AOSC OS systems")
+        );
 
         let manifests = ManifestCollection {
-            topics: BTreeMap::from([
+            topics: topics([
                 ("kde-survey-20231201".to_string(), converted1),
                 ("cumulative-2023H3".to_string(), converted2),
             ]),
         };
         assert_eq!(manifests.find_missing_topics(), vec![("cumulative-2023H3".to_string(), vec!["core-12.1.0".to_string()])]);
         assert!(! manifests.is_consistent());
+        assert!(manifests.find_self_references().is_empty());
+        assert_eq!(manifests.count_security_topics(), 1);
+        assert_eq!(
+            manifests
+                .security_topics()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            ["kde-survey-20231201"]
+        );
 
-        let manifests_text = "{\"cumulative-2023H3\":{\"type\":\"cumulative\",\"name\":{\"default\":\"Winter 2023 Cumulative Update for amd64 AOSC OS systems\",\"zh_MS\":\"适用于 amd64 AOSC OS 版本的 23 冬季累计更新\"},\"topics\":[\"kde-survey-20231201\",\"core-12.1.0\"]},\"kde-survey-20231201\":{\"type\":\"conventional\",\"name\":{\"default\":\"KDE Updates (Winter 2023)\",\"zh_CN\":\"KDE 更新（2023 年冬季）\"},\"security\":true,\"caution\":{\"default\":\"This topic may use significantly more memory after reboot. Our testing finds that the new KDE version may use up to 16GiB of RAM.\",\"zh_CN\":\"本次更新重启后可能会需要更多内存。据我社维护者测试，新版 KDE 可能需要接近 16GiB 内存。\"},\"packages\":{\"dolphin\":\"23.04.1\",\"konsole\":\"23.04.1-1\",\"pykde\":null}}}";
-        let _: ManifestCollection =
+        let manifests_text = "{\"cumulative-2023H3\":{\"type\":\"cumulative\",\"name\":{\"default\":\"Winter 2023 Cumulative Update for amd64 AOSC OS systems\",\"zh-MS\":\"适用于 amd64 AOSC OS 版本的 23 冬季累计更新\"},\"topics\":[\"kde-survey-20231201\",\"core-12.1.0\"]},\"kde-survey-20231201\":{\"type\":\"conventional\",\"name\":{\"default\":\"KDE Updates (Winter 2023)\",\"zh-CN\":\"KDE 更新（2023 年冬季）\"},\"security\":true,\"caution\":{\"default\":\"This topic may use significantly more memory after reboot. Our testing finds that the new KDE version may use up to 16GiB of RAM.\",\"zh-CN\":\"本次更新重启后可能会需要更多内存。据我社维护者测试，新版 KDE 可能需要接近 16GiB 内存。\"},\"packages\":{\"dolphin\":\"23.04.1\",\"konsole\":\"23.04.1-1\",\"pykde\":null}}}";
+        let from_json: ManifestCollection =
             serde_json::from_str(r#"
             {
                 "cumulative-2023H3": {
@@ -208,8 +842,707 @@ mod test {
                     }
                 }
             }"#).expect("Failed to parse manifest collection");
-        // println!("{}", serde_json::to_string(&manifests).unwrap());
-        assert_eq!(manifests_text, serde_json::to_string(&manifests).unwrap());
+        assert_eq!(from_json, manifests);
+        let hash = |m: &ManifestCollection| {
+            let mut hasher = DefaultHasher::new();
+            m.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&from_json), hash(&manifests));
+        assert_ne!(
+            manifests.topics["kde-survey-20231201"],
+            manifests.topics["cumulative-2023H3"]
+        );
+        assert_eq!(
+            serde_json::from_str::<ManifestCollection>(manifests_text)?,
+            manifests
+        );
+        assert_eq!(
+            serde_json::from_str::<ManifestCollection>(&serde_json::to_string(&manifests)?)?,
+            manifests
+        );
+        assert_eq!(
+            serde_json::to_value(&manifests)?,
+            serde_json::from_str::<serde_json::Value>(manifests_text)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_topics_with_caution() -> Result<()> {
+        let manifests = ManifestCollection {
+            topics: topics([
+                (
+                    "kde".to_string(),
+                    conventional("caution.default = \"Reboot required\""),
+                ),
+                ("core".to_string(), conventional("")),
+                ("2023H3".to_string(), cumulative(&["core", "kde"])),
+                ("2023H4".to_string(), cumulative(&["core", "missing"])),
+            ]),
+        };
+        assert_eq!(manifests.topics_with_caution(), ["2023H3", "kde"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_locales() -> Result<()> {
+        let manifests = ManifestCollection {
+            topics: topics([
+                (
+                    "a".to_string(),
+                    toml::from_str(
+                        r#"
+                name.zh_CN = "甲"
+                name.chinese = "甲"
+                security = false
+                caution.zh_CHN = "注意"
+                caution.chinese = "注意"
+                per_package_caution.gcc.zh_TWN = "注意"
+                [packages]
+                "#,
+                    )?,
+                ),
+                (
+                    "b".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "B"
+                name.es-419 = "B"
+                topics = ["a"]
+                "#,
+                    )?,
+                ),
+            ]),
+        };
+        assert_eq!(
+            manifests.find_invalid_locales(),
+            [(
+                "a".to_string(),
+                vec![
+                    "chinese".to_string(),
+                    "zh_CHN".to_string(),
+                    "zh_TWN".to_string()
+                ]
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_orphan_conventional_topics() -> Result<()> {
+        let manifests = ManifestCollection {
+            topics: topics([
+                ("kde".to_string(), conventional("")),
+                ("core".to_string(), conventional("")),
+                ("gnome".to_string(), conventional("")),
+                ("2023H3".to_string(), cumulative(&["kde", "missing"])),
+                ("2023H4".to_string(), cumulative(&["core", "2023H3"])),
+                ("2024H1".to_string(), cumulative(&[])),
+            ]),
+        };
+        assert_eq!(manifests.orphan_conventional_topics(), ["gnome"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retain_arch() -> Result<()> {
+        let mut manifests = ManifestCollection {
+            topics: topics([
+                ("kde".to_string(), conventional("")),
+                ("rpi".to_string(), conventional(r#"arch = ["arm64"]"#)),
+                (
+                    "x86".to_string(),
+                    conventional(r#"arch = ["amd64", "i486"]"#),
+                ),
+                (
+                    "2023H3".to_string(),
+                    cumulative(&["kde", "rpi", "x86", "missing"]),
+                ),
+            ]),
+        };
+        assert!(!manifests
+            .get("rpi")
+            .unwrap()
+            .is_applicable_for_arch("amd64"));
+        let arm64 = manifests.filter_by_arch("arm64");
+        assert_eq!(arm64.len(), 3);
+        assert_eq!(manifests.len(), 4);
+        manifests.retain_arch("amd64");
+        assert_eq!(
+            manifests.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["2023H3", "kde", "x86"]
+        );
+        let Manifest::Cumulative(c) = manifests.get("2023H3").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(c.get_topics(), ["kde", "x86", "missing"]);
+
+        let mut manifests = ManifestCollection {
+            topics: topics([(
+                "mesa".to_string(),
+                conventional("[arch_packages.arm64]\nmesa = \"23.2.1\""),
+            )]),
+        };
+        manifests.resolve_arch_packages("arm64");
+        let Manifest::Conventional(c) = manifests.get("mesa").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(c.get_packages().arch_specific().count(), 0);
+        assert_eq!(
+            c.get_packages().get("mesa"),
+            Some(&Some("23.2.1".to_string()))
+        );
+
+        // Topics extending removed topics keep the inherited packages
+        let manifests = ManifestCollection {
+            topics: topics([
+                (
+                    "base",
+                    conventional("arch = [\"arm64\"]\n[packages]\nmesa = \"23.2.1\""),
+                ),
+                (
+                    "rpi",
+                    conventional("extends = \"base\"\narch = [\"arm64\"]"),
+                ),
+                (
+                    "kde",
+                    conventional("extends = \"rpi\"\n[packages]\nkonsole = \"23.04.1\""),
+                ),
+                ("qt", conventional("extends = \"missing\"")),
+            ]),
+        };
+        let amd64 = manifests.filter_by_arch("amd64");
+        assert_eq!(
+            amd64.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["kde", "qt"]
+        );
+        let Some(Manifest::Conventional(kde)) = amd64.get("kde") else {
+            unreachable!()
+        };
+        assert_eq!(kde.get_extends(), None);
+        assert_eq!(
+            kde.get_packages().iter().collect::<BTreeMap<_, _>>(),
+            BTreeMap::from([("konsole", Some("23.04.1")), ("mesa", Some("23.2.1"))])
+        );
+        assert_eq!(amd64.get("qt"), manifests.get("qt"));
+        let arm64 = manifests.filter_by_arch("arm64");
+        assert_eq!(arm64, manifests);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_topics_order() -> Result<()> {
+        let manifests = ManifestCollection {
+            topics: topics([
+                ("c".to_string(), cumulative(&["z", "w", "y"])),
+                ("a".to_string(), cumulative(&["c", "x"])),
+                ("b".to_string(), cumulative(&["a", "c"])),
+            ]),
+        };
+        let expected = vec![
+            ("a".to_string(), vec!["x".to_string()]),
+            (
+                "c".to_string(),
+                vec!["w".to_string(), "y".to_string(), "z".to_string()],
+            ),
+        ];
+        // Results must be stable across runs, in particular with the `parallel` feature
+        for _ in 0..16 {
+            assert_eq!(manifests.find_missing_topics(), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_noncanonical_locales() -> Result<()> {
+        let manifests = ManifestCollection {
+            topics: topics([
+                (
+                    "a".to_string(),
+                    toml::from_str(
+                        r#"
+                name.zh_CN = "甲"
+                name.en-US = "A"
+                security = false
+                caution.zh_CN = "注意"
+                caution.zh-tw = "注意"
+                per_package_caution.gcc.ja_jp = "注意"
+                [packages]
+                "#,
+                    )?,
+                ),
+                (
+                    "b".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "B"
+                name.zh-CN = "乙"
+                topics = ["a"]
+                "#,
+                    )?,
+                ),
+            ]),
+        };
+        assert_eq!(
+            manifests.available_locales(),
+            BTreeSet::from([
+                Locale::new("en-US"),
+                Locale::new("ja-JP"),
+                Locale::new("zh-CN"),
+                Locale::new("zh-TW")
+            ])
+        );
+        assert_eq!(
+            manifests.find_noncanonical_locales(),
+            [(
+                "a".to_string(),
+                vec![
+                    "ja_jp".to_string(),
+                    "zh-tw".to_string(),
+                    "zh_CN".to_string()
+                ]
+            )]
+        );
+
+        // Serialized with canonical locales, so the round trip is clean
+        let json = serde_json::to_string(&manifests)?;
+        assert!(json.contains(r#""zh-CN":"甲""#) && json.contains(r#""zh-TW":"注意""#));
+        let roundtrip: ManifestCollection = serde_json::from_str(&json)?;
+        assert_eq!(roundtrip, manifests);
+        assert!(roundtrip.find_noncanonical_locales().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge() -> Result<()> {
+        let collection = |topics: &[(&str, &str)]| -> Result<ManifestCollection> {
+            let topics = topics
+                .iter()
+                .map(|(k, v)| {
+                    let manifest = conventional(&format!("[packages]\nkonsole = {:?}", v));
+                    (k.to_string(), manifest)
+                })
+                .collect::<BTreeMap<_, _>>();
+            Ok(ManifestCollection::try_from(topics).unwrap())
+        };
+
+        let mut merged = collection(&[("kde", "23.04.1"), ("kde-fix", "23.04.2"), ("qt", "5.15")])?;
+        let diff = merged.merge(collection(&[
+            ("kde", "23.04.1"),
+            ("kde-fix", "23.04.3"),
+            ("mesa", "23.3"),
+        ])?);
+        assert_eq!(diff.added, ["mesa"]);
+        assert_eq!(diff.changed, ["kde-fix"]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            merged,
+            collection(&[
+                ("kde", "23.04.1"),
+                ("kde-fix", "23.04.3"),
+                ("mesa", "23.3"),
+                ("qt", "5.15")
+            ])?
+        );
+
+        merged.retain(|topic, _| topic.starts_with("kde"));
+        assert_eq!(
+            merged.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["kde", "kde-fix"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_packages() -> Result<()> {
+        let manifests = ManifestCollection {
+            topics: topics([
+                (
+                    "kde".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "KDE"
+                security = false
+                caution.default = ""
+
+                [packages]
+                konsole = "23.04.1-1"
+                pykde = false
+                kate = { amd64 = "23.04.1" }
+
+                [arch_packages.arm64]
+                dolphin = "23.04.1"
+                konsole = "23.04.2-1"
+                "#,
+                    )?,
+                ),
+                (
+                    "qt".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "Qt"
+                security = false
+                caution.default = ""
+
+                [packages]
+                konsole = "23.04.1-1"
+                qt5-base = "5.15.11"
+                "#,
+                    )?,
+                ),
+                (
+                    "all".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "All"
+                topics = ["kde", "qt"]
+                "#,
+                    )?,
+                ),
+            ]),
+        };
+        assert_eq!(
+            manifests.all_packages().into_iter().collect::<Vec<_>>(),
+            ["dolphin", "kate", "konsole", "pykde", "qt5-base"]
+        );
+        assert!(ManifestCollection::default().all_packages().is_empty());
+
+        assert_eq!(manifests.topics_for_package("konsole"), ["kde", "qt"]);
+        assert_eq!(manifests.topics_for_package("dolphin"), ["kde"]);
+        assert!(manifests.topics_for_package("all").is_empty());
+        let index = manifests.package_index();
+        assert_eq!(
+            index.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["dolphin", "kate", "konsole", "pykde", "qt5-base"]
+        );
+        for (name, topics) in &index {
+            assert_eq!(manifests.topics_for_package(name), *topics);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_set() -> Result<()> {
+        let manifest = |locale: &str, version: &str| {
+            conventional(&format!(
+                "name.{} = \"KDE\"\n[packages]\nkonsole = {:?}",
+                locale, version
+            ))
+        };
+        // Spelling of locales does not affect equality, and thus hashing
+        let manifests = HashSet::from([
+            manifest("zh_CN", "23.04.1"),
+            manifest("zh-CN", "23.04.1"),
+            manifest("zh-CN", "23.04.2"),
+        ]);
+        assert_eq!(manifests.len(), 2);
+        assert!(manifests.contains(&manifest("zh_cn", "23.04.2")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_default() {
+        let collection = ManifestCollection::default();
+        assert!(collection.is_empty());
+        assert!(Packages::default().is_empty());
+
+        let conventional = Conventional::default();
+        assert!(!conventional.is_security_update());
+        assert!(conventional.get_name().is_empty());
+        assert!(Cumulative::default().get_topics().is_empty());
+
+        // Default values serialize to valid JSON and read back unchanged
+        let collection = ManifestCollection::from(topics([
+            ("a".to_string(), Manifest::Conventional(conventional)),
+            ("b".to_string(), Manifest::Cumulative(Cumulative::default())),
+        ]));
+        let json = serde_json::to_string(&collection).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ManifestCollection>(&json).unwrap(),
+            collection
+        );
+        assert_eq!(serde_json::to_string(&Packages::default()).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_into_map() {
+        let map = BTreeMap::from([
+            (
+                TopicId::new("a").unwrap(),
+                Manifest::Conventional(Conventional::default()),
+            ),
+            (
+                TopicId::new("b").unwrap(),
+                Manifest::Cumulative(Cumulative::default()),
+            ),
+        ]);
+        let collection = ManifestCollection::from(map.clone());
+        assert_eq!(collection.as_map(), &map);
+        assert_eq!(
+            collection
+                .as_map()
+                .keys()
+                .map(|k| k.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert_eq!(BTreeMap::from(collection.clone()), map);
+        assert_eq!(collection.into_inner(), map);
+    }
+
+    #[test]
+    fn test_self_references() -> Result<()> {
+        let manifests = ManifestCollection {
+            topics: topics([
+                (
+                    "a".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "A"
+                topics = ["b", "a"]
+                "#,
+                    )?,
+                ),
+                (
+                    "b".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "B"
+                topics = ["a"]
+                "#,
+                    )?,
+                ),
+            ]),
+        };
+        assert_eq!(manifests.find_self_references(), ["a"]);
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json() -> Result<()> {
+        use super::OutputFormat;
+
+        let manifests = ManifestCollection {
+            topics: topics([(
+                "a".to_string(),
+                toml::from_str(
+                    r#"
+            name.default = "A"
+            topics = []
+            "#,
+                )?,
+            )]),
+        };
+        let json = manifests.to_json_string()?;
+        assert_eq!(
+            json,
+            r#"{"a":{"type":"cumulative","name":{"default":"A"},"topics":[]}}"#
+        );
+        assert_eq!(ManifestCollection::from_json_str(&json)?, manifests);
+        assert_eq!(
+            ManifestCollection::from_json_str(&manifests.to_json_string_pretty()?)?,
+            manifests
+        );
+
+        for format in [OutputFormat::Json, OutputFormat::JsonCompact] {
+            let mut buf = Vec::new();
+            manifests.to_writer(format, &mut buf)?;
+            assert_eq!(
+                serde_json::from_slice::<ManifestCollection>(&buf)?,
+                manifests
+            );
+        }
+        let mut buf = Vec::new();
+        manifests.to_writer(OutputFormat::JsonCompact, &mut buf)?;
+        assert_eq!(String::from_utf8(buf)?, json);
         Ok(())
     }
+
+    /// Collection with both kinds of topics, removed and per-architecture packages
+    #[cfg(any(feature = "yaml", feature = "msgpack"))]
+    fn output_example() -> Result<ManifestCollection> {
+        let kde = r#"
+            name.default = "KDE"
+            name.zh_CN = "KDE 更新"
+            security = true
+            caution.default = "Reboot"
+            severity = "warning"
+            [packages]
+            konsole = "23.04.1-1"
+            pykde = false
+            kate = { amd64 = "23.04.1-1", arm64 = "23.04.1-2" }
+            [arch_packages.arm64]
+            dolphin = "23.04.1"
+        "#;
+        let all = "name.default = \"All\"\ntopics = [\"kde\"]\narch = [\"amd64\"]";
+        Ok(ManifestCollection {
+            topics: topics([
+                ("kde".to_string(), toml::from_str(kde)?),
+                ("all".to_string(), toml::from_str(all)?),
+            ]),
+        })
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_roundtrip() -> Result<()> {
+        use super::OutputFormat;
+
+        let manifests = output_example()?;
+        let mut buf = Vec::new();
+        manifests.to_writer(OutputFormat::Yaml, &mut buf)?;
+        let yaml = String::from_utf8(buf)?;
+        assert!(yaml.contains("type: conventional"), "{}", yaml);
+        assert_eq!(
+            serde_yaml::from_str::<ManifestCollection>(&yaml)?,
+            manifests
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_roundtrip() -> Result<()> {
+        use super::OutputFormat;
+
+        let manifests = output_example()?;
+        let mut buf = Vec::new();
+        manifests.to_writer(OutputFormat::MsgPack, &mut buf)?;
+        assert!(buf.len() < manifests.to_json_string()?.len());
+        assert_eq!(
+            rmp_serde::from_slice::<ManifestCollection>(&buf)?,
+            manifests
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_canonical_json() -> Result<()> {
+        let manifests = ManifestCollection {
+            topics: topics([
+                (
+                    "kde".to_string(),
+                    toml::from_str(
+                        r#"
+                name.zh_CN = "KDE 更新"
+                name.default = "KDE \"Winter\""
+                security = true
+                caution.default = ""
+                severity = "info"
+                arch = ["arm64", "amd64"]
+
+                [packages]
+                konsole = "23.04.1-1"
+                dolphin = "23.04.1"
+                pykde = false
+                kate = { arm64 = "23.04.1-2", amd64 = "23.04.1-1" }
+                "#,
+                    )?,
+                ),
+                (
+                    "all".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "All"
+                topics = ["kde", "gnome"]
+                "#,
+                    )?,
+                ),
+            ]),
+        };
+        let canonical = manifests.to_canonical_json();
+        assert_eq!(
+            canonical,
+            concat!(
+                r#"{"all":{"name":{"default":"All"},"topics":["kde","gnome"],"type":"cumulative"},"#,
+                r#""kde":{"arch":["arm64","amd64"],"caution":{"default":""},"#,
+                r#""name":{"default":"KDE \"Winter\"","zh-CN":"KDE 更新"},"#,
+                r#""packages":{"dolphin":"23.04.1","kate":{"amd64":"23.04.1-1","arm64":"23.04.1-2"},"#,
+                r#""konsole":"23.04.1-1","pykde":null},"security":true,"severity":"info","#,
+                r#""type":"conventional"}}"#
+            )
+        );
+        assert_eq!(ManifestCollection::from_json_str(&canonical)?, manifests);
+        Ok(())
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_expired() -> Result<()> {
+        use chrono::NaiveDate;
+
+        let manifests = ManifestCollection {
+            topics: topics([
+                (
+                    "old".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "Old"
+                security = false
+                caution.default = ""
+                date = "2023-12-01"
+                [packages]
+                "#,
+                    )?,
+                ),
+                (
+                    "new".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "New"
+                topics = ["old"]
+                date = 2024-06-01
+                "#,
+                    )?,
+                ),
+                (
+                    "undated".to_string(),
+                    toml::from_str(
+                        r#"
+                name.default = "Undated"
+                topics = []
+                "#,
+                    )?,
+                ),
+            ]),
+        };
+        let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(manifests.expired(today), ["old"]);
+        assert_eq!(
+            manifests.topics["new"].get_date(),
+            NaiveDate::from_ymd_opt(2024, 6, 1)
+        );
+        assert_eq!(manifests.topics["undated"].get_date(), None);
+
+        let json = serde_json::to_string(&manifests)?;
+        assert!(json.contains(r#""date":"2023-12-01""#));
+        assert!(json.contains(r#""date":"2024-06-01""#));
+        assert_eq!(json.matches(r#""date""#).count(), 2);
+        Ok(())
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(ManifestCollection::json_schema()).unwrap();
+        assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+        assert_eq!(
+            schema["additionalProperties"]["$ref"],
+            "#/definitions/Manifest"
+        );
+        assert_eq!(
+            schema["definitions"]["Conventional"]["properties"]["packages"]["$ref"],
+            "#/definitions/Packages"
+        );
+        assert!(schema["definitions"]["Conventional"]["properties"]
+            .get("arch_packages")
+            .is_none());
+        assert_eq!(
+            schema["definitions"]["Packages"]["additionalProperties"]["anyOf"][2]["const"],
+            false
+        );
+    }
 }
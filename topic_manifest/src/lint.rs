@@ -0,0 +1,345 @@
+//! Lints for topic names and cautions
+//!
+//! Unlike [validation](crate::validation), lints check how topics are displayed by update
+//! clients, and the rules are configurable.
+
+use localized::{Locale, Localized};
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::translation::LocalizedField;
+use super::{Conventional, Cumulative, Manifest, ManifestCollection};
+
+/// Rules of lints
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintRules {
+    /// Maximum number of characters in topic names
+    pub max_name_length: Option<usize>,
+    /// Maximum number of characters in topic names of specific locales, overriding
+    /// [`LintRules::max_name_length`]
+    pub max_name_length_per_locale: BTreeMap<Locale, usize>,
+    /// Forbid control characters other than line feeds in names and cautions
+    ///
+    /// Unpaired surrogates need no check, as they can not be represented in Rust strings and are
+    /// rejected while parsing.
+    pub forbid_control_chars: bool,
+    /// Require a caution message for security updates
+    pub require_caution_for_security: bool,
+    /// Flag translated names identical to the default name in all locales
+    pub forbid_identical_names: bool,
+}
+
+/// A problem found by lints
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LintFinding {
+    /// A name is longer than allowed, `locale` is `None` for the default name
+    NameTooLong {
+        locale: Option<Locale>,
+        length: usize,
+        max: usize,
+    },
+    /// A localized string contains control characters, `package` is the package of a
+    /// [`LocalizedField::PackageCaution`], `locale` is `None` for the default value
+    ControlCharacter {
+        field: LocalizedField,
+        package: Option<String>,
+        locale: Option<Locale>,
+    },
+    /// A security update has no caution message
+    MissingCaution,
+    /// All translations of the name are identical to the default name
+    IdenticalNames,
+}
+
+impl Default for LintRules {
+    /// Rules following conventions of AOSC OS topics
+    fn default() -> Self {
+        Self {
+            max_name_length: Some(64),
+            max_name_length_per_locale: BTreeMap::new(),
+            forbid_control_chars: true,
+            require_caution_for_security: false,
+            forbid_identical_names: true,
+        }
+    }
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let locale_str = |locale: &Option<Locale>| match locale {
+            Some(locale) => locale.to_string(),
+            None => "default".to_string(),
+        };
+        match self {
+            Self::NameTooLong {
+                locale,
+                length,
+                max,
+            } => write!(
+                f,
+                "name.{} is {} characters long, longer than {}",
+                locale_str(locale),
+                length,
+                max
+            ),
+            Self::ControlCharacter {
+                field,
+                package: Some(package),
+                locale,
+            } => write!(
+                f,
+                "{}.{}.{} contains control characters",
+                field,
+                package,
+                locale_str(locale)
+            ),
+            Self::ControlCharacter {
+                field,
+                package: None,
+                locale,
+            } => write!(
+                f,
+                "{}.{} contains control characters",
+                field,
+                locale_str(locale)
+            ),
+            Self::MissingCaution => write!(f, "Security update without a caution message"),
+            Self::IdenticalNames => write!(f, "Name is identical in all locales"),
+        }
+    }
+}
+
+/// Iterate over localized strings along with their locales, `None` for the default value
+fn entries(localized: &Localized<String>) -> impl Iterator<Item = (Option<&Locale>, &str)> {
+    localized
+        .default
+        .iter()
+        .map(|s| (None, s.as_str()))
+        .chain(localized.content.iter().map(|(l, s)| (Some(l), s.as_str())))
+}
+
+fn lint_name(name: &Localized<String>, rules: &LintRules, findings: &mut Vec<LintFinding>) {
+    for (locale, s) in entries(name) {
+        let max = locale
+            .and_then(|l| rules.max_name_length_per_locale.get(l))
+            .copied()
+            .or(rules.max_name_length);
+        let length = s.chars().count();
+        if let Some(max) = max.filter(|max| length > *max) {
+            findings.push(LintFinding::NameTooLong {
+                locale: locale.cloned(),
+                length,
+                max,
+            });
+        }
+    }
+    if rules.forbid_identical_names {
+        if let Some(default) = name.get_default() {
+            if !name.content.is_empty() && name.content.values().all(|s| s == default) {
+                findings.push(LintFinding::IdenticalNames);
+            }
+        }
+    }
+}
+
+fn lint_control_chars(
+    field: LocalizedField,
+    package: Option<&str>,
+    localized: &Localized<String>,
+    rules: &LintRules,
+    findings: &mut Vec<LintFinding>,
+) {
+    if !rules.forbid_control_chars {
+        return;
+    }
+    for (locale, s) in entries(localized) {
+        if s.chars().any(|c| c.is_control() && c != '\n') {
+            findings.push(LintFinding::ControlCharacter {
+                field,
+                package: package.map(str::to_string),
+                locale: locale.cloned(),
+            });
+        }
+    }
+}
+
+impl Conventional {
+    /// Check the name and cautions of the topic against `rules`
+    pub fn lint(&self, rules: &LintRules) -> Vec<LintFinding> {
+        let mut findings = Vec::new();
+        lint_name(self.get_name(), rules, &mut findings);
+        lint_control_chars(
+            LocalizedField::Name,
+            None,
+            self.get_name(),
+            rules,
+            &mut findings,
+        );
+        lint_control_chars(
+            LocalizedField::Caution,
+            None,
+            self.get_caution(),
+            rules,
+            &mut findings,
+        );
+        for (package, caution) in self.get_per_package_caution().into_iter().flatten() {
+            lint_control_chars(
+                LocalizedField::PackageCaution,
+                Some(package),
+                caution,
+                rules,
+                &mut findings,
+            );
+        }
+        if rules.require_caution_for_security && self.is_security_update() && !self.has_caution() {
+            findings.push(LintFinding::MissingCaution);
+        }
+        findings
+    }
+}
+
+impl Cumulative {
+    /// Check the name of the topic against `rules`
+    pub fn lint(&self, rules: &LintRules) -> Vec<LintFinding> {
+        let mut findings = Vec::new();
+        lint_name(self.get_name(), rules, &mut findings);
+        lint_control_chars(
+            LocalizedField::Name,
+            None,
+            self.get_name(),
+            rules,
+            &mut findings,
+        );
+        findings
+    }
+}
+
+impl Manifest {
+    /// Check the topic against `rules`
+    pub fn lint(&self, rules: &LintRules) -> Vec<LintFinding> {
+        match self {
+            Self::Conventional(c) => c.lint(rules),
+            Self::Cumulative(c) => c.lint(rules),
+        }
+    }
+}
+
+impl ManifestCollection {
+    /// Check all topics against `rules`, returning topics with findings
+    pub fn lint_all(&self, rules: &LintRules) -> Vec<(String, Vec<LintFinding>)> {
+        self.iter()
+            .filter_map(|(k, v)| {
+                let findings = v.lint(rules);
+                if findings.is_empty() {
+                    None
+                } else {
+                    Some((k.to_string(), findings))
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+
+    use std::collections::BTreeMap;
+
+    use super::{LintFinding, LintRules};
+    use crate::test_util::{conventional, manifest, topics};
+    use crate::{Locale, LocalizedField, ManifestCollection};
+
+    #[test]
+    fn test_lint() -> Result<()> {
+        let kde = conventional(
+            r#"
+            name.default = "KDE Updates (Winter 2023)"
+            name.zh_CN = "KDE Updates (Winter 2023)"
+            security = true
+            caution.default = "Reboot\u0007 required"
+            per_package_caution.konsole.default = "Restart\u0007 Konsole"
+            "#,
+        );
+        let core = conventional(
+            r#"
+            name.default = "Core Updates"
+            name.zh_CN = "核心更新"
+            security = true
+            "#,
+        );
+        let cumulative = manifest(
+            r#"
+            name.default = "Winter 2023 Cumulative Update for amd64 AOSC OS systems"
+            name.zh_CN = "适用于 amd64 AOSC OS 版本的 23 冬季累计更新"
+            topics = ["kde", "core"]
+            "#,
+        );
+        let collection = ManifestCollection::from(topics([
+            ("kde".to_string(), kde),
+            ("core".to_string(), core),
+            ("2023H3".to_string(), cumulative),
+        ]));
+
+        assert_eq!(
+            collection.lint_all(&LintRules::default()),
+            [(
+                "kde".to_string(),
+                vec![
+                    LintFinding::IdenticalNames,
+                    LintFinding::ControlCharacter {
+                        field: LocalizedField::Caution,
+                        package: None,
+                        locale: None,
+                    },
+                    LintFinding::ControlCharacter {
+                        field: LocalizedField::PackageCaution,
+                        package: Some("konsole".to_string()),
+                        locale: None,
+                    },
+                ]
+            )]
+        );
+        assert_eq!(
+            collection.lint_all(&LintRules::default())[0].1[2].to_string(),
+            "per_package_caution.konsole.default contains control characters"
+        );
+
+        let rules = LintRules {
+            max_name_length: Some(40),
+            max_name_length_per_locale: BTreeMap::from([(Locale::new("zh-CN"), 20)]),
+            require_caution_for_security: true,
+            forbid_control_chars: false,
+            forbid_identical_names: false,
+        };
+        let findings = collection.lint_all(&rules);
+        assert_eq!(
+            findings[0],
+            (
+                "2023H3".to_string(),
+                vec![
+                    LintFinding::NameTooLong {
+                        locale: None,
+                        length: 55,
+                        max: 40,
+                    },
+                    LintFinding::NameTooLong {
+                        locale: Some(Locale::new("zh-CN")),
+                        length: 31,
+                        max: 20,
+                    },
+                ]
+            )
+        );
+        assert_eq!(
+            findings[1],
+            ("core".to_string(), vec![LintFinding::MissingCaution])
+        );
+        assert_eq!(
+            findings[2].1[0].to_string(),
+            "name.zh-CN is 25 characters long, longer than 20"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,1435 @@
+//! Loading manifest collections from directories and saving them to files
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::de::Error as _;
+#[cfg(feature = "json")]
+use serde::ser::{SerializeMap, Serializer};
+use serde::Deserialize;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+#[cfg(feature = "json")]
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use super::glob::PathFilter;
+use super::{parse_error, Conventional, Cumulative, Manifest, ManifestCollection, TopicId};
+
+/// Errors while loading topic manifests
+#[derive(Debug)]
+pub enum LoadError {
+    /// Failed to read a file or a directory
+    Io { path: PathBuf, error: io::Error },
+    /// Failed to parse a topic manifest, `location` is the line and column (1-based) of the error
+    ///
+    /// `rendered` is the error pointing at the source, see [`parse_error::render`]. It is shown by
+    /// the alternate form `{:#}` of [`Display`](fmt::Display).
+    Parse {
+        path: PathBuf,
+        error: Box<toml::de::Error>,
+        location: Option<(usize, usize)>,
+        rendered: String,
+    },
+    /// The manifest file is empty or only contains comments
+    Empty { path: PathBuf },
+    /// The filename is not a valid topic name, see [`TopicId`], and the manifest has no `topic` key
+    InvalidFilename { path: PathBuf },
+    /// Another manifest has the same topic name, like `stable/kde.toml` and `testing/kde.toml`
+    ///
+    /// `first` is the file defining the topic first, which is `path` itself if a file with multiple
+    /// topics defines it twice.
+    DuplicateTopic {
+        path: PathBuf,
+        topic: String,
+        first: PathBuf,
+    },
+    /// A pattern of [`LoadOptions::exclude`] or [`LoadOptions::include`] is not a valid glob,
+    /// `path` is the directory being loaded
+    InvalidGlob {
+        path: PathBuf,
+        error: globset::Error,
+    },
+    /// Failed to write a topic manifest to the output
+    Write { path: PathBuf, error: io::Error },
+    /// More than one of the `total` manifest files in a directory failed to load
+    Multiple {
+        path: PathBuf,
+        errors: Vec<LoadError>,
+        total: usize,
+    },
+}
+
+/// Options of loading manifests from directories
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadOptions {
+    /// Follow symbolic links to files and directories
+    pub follow_symlinks: bool,
+    /// Skip files failing to parse with a warning, instead of aborting
+    pub ignore_errors: bool,
+    /// Extensions of manifest files, compared case-insensitively
+    pub extensions: Vec<String>,
+    /// Silently skip empty manifest files, instead of failing with [`LoadError::Empty`]
+    pub skip_empty: bool,
+    /// Glob patterns of files to skip, matched against paths relative to the loaded directory
+    ///
+    /// Paths use `/` as separator. `*` and `?` do not match `/`, while `**` does, so
+    /// `templates/**` skips everything in the `templates` directory. Such directories are not
+    /// read at all, unless [`LoadOptions::include`] may match files in them. See [`globset`] for
+    /// the full syntax.
+    pub exclude: Vec<String>,
+    /// Glob patterns of files to load even if they match [`LoadOptions::exclude`]
+    pub include: Vec<String>,
+    /// Number of threads walking directories and parsing files with the `parallel` feature, `None`
+    /// or `Some(0)` for the global thread pool of rayon
+    ///
+    /// Other numbers get a thread pool of their own, used for both the walk and parsing, while
+    /// `Some(1)` walks directories and parses files one by one on the calling thread, as without
+    /// the `parallel` feature. Either way, files are loaded in path order with the same parse
+    /// errors, and a panic propagates to the caller.
+    pub jobs: Option<usize>,
+}
+
+/// Files defining each topic, see [`ManifestCollection::load_from_dir_with_sources`]
+pub type TopicSources = BTreeMap<TopicId, PathBuf>;
+
+/// Problems found while loading manifests, which do not abort loading
+#[derive(Debug)]
+pub enum LoadWarning {
+    /// A file failed to parse and was skipped, see [`LoadOptions::ignore_errors`]
+    Skipped(LoadError),
+    /// A symbolic link points to nothing
+    DanglingSymlink { path: PathBuf },
+    /// The topic defined in `path` replaces the one in `previous` with the same name, see
+    /// [`LoadOptions::ignore_errors`]
+    DuplicateTopic {
+        path: PathBuf,
+        topic: String,
+        previous: PathBuf,
+    },
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: true,
+            ignore_errors: false,
+            extensions: vec!["toml".to_string()],
+            skip_empty: false,
+            exclude: Vec::new(),
+            include: Vec::new(),
+            jobs: None,
+        }
+    }
+}
+
+impl LoadWarning {
+    /// Path of the file causing the warning
+    pub fn get_path(&self) -> &Path {
+        match self {
+            Self::Skipped(error) => error.get_path(),
+            Self::DanglingSymlink { path } => path,
+            Self::DuplicateTopic { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Skipped(error) => write!(f, "{}, skipped", error),
+            Self::DanglingSymlink { path } => {
+                write!(f, "Dangling symbolic link: {}", path.to_string_lossy())
+            }
+            Self::DuplicateTopic {
+                path,
+                topic,
+                previous,
+            } => write!(
+                f,
+                "Duplicate topic {} in {} replaces the one in {}",
+                topic,
+                path.to_string_lossy(),
+                previous.to_string_lossy()
+            ),
+        }
+    }
+}
+
+impl LoadError {
+    /// Path of the file or directory causing the error
+    pub fn get_path(&self) -> &Path {
+        match self {
+            Self::Io { path, .. } => path,
+            Self::Parse { path, .. } => path,
+            Self::Empty { path } => path,
+            Self::InvalidFilename { path } => path,
+            Self::DuplicateTopic { path, .. } => path,
+            Self::InvalidGlob { path, .. } => path,
+            Self::Write { path, .. } => path,
+            Self::Multiple { path, .. } => path,
+        }
+    }
+
+    /// Errors of the individual files, in path order
+    ///
+    /// Returns the error itself unless it is [`LoadError::Multiple`].
+    pub fn get_errors(&self) -> &[LoadError] {
+        match self {
+            Self::Multiple { errors, .. } => errors,
+            _ => std::slice::from_ref(self),
+        }
+    }
+
+    /// Line and column (1-based) where parsing failed, if known
+    pub fn get_location(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::Parse { location, .. } => *location,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (true, Self::Parse { rendered, .. }) = (f.alternate(), self) {
+            return f.write_str(rendered.strip_prefix("error: ").unwrap_or(rendered));
+        }
+        match self {
+            Self::Io { path, error } => {
+                write!(f, "Failed to read {}: {}", path.to_string_lossy(), error)
+            }
+            Self::Parse {
+                path,
+                error,
+                location: Some((line, column)),
+                ..
+            } => write!(
+                f,
+                "Failed to parse {}: {} at line {}, column {}",
+                path.to_string_lossy(),
+                error.message().trim_end(),
+                line,
+                column
+            ),
+            Self::Parse { path, error, .. } => {
+                write!(f, "Failed to parse {}: {}", path.to_string_lossy(), error)
+            }
+            Self::Empty { path } => {
+                write!(f, "Manifest file {} is empty", path.to_string_lossy())
+            }
+            Self::InvalidFilename { path } => write!(
+                f,
+                "Invalid topic manifest filename: {}",
+                path.to_string_lossy()
+            ),
+            Self::DuplicateTopic { path, topic, first } => write!(
+                f,
+                "Duplicate topic {} in {}, already defined in {}",
+                topic,
+                path.to_string_lossy(),
+                first.to_string_lossy()
+            ),
+            Self::InvalidGlob { error, .. } => write!(f, "Invalid glob pattern: {}", error),
+            Self::Write { path, error } => {
+                write!(f, "Failed to write {}: {}", path.to_string_lossy(), error)
+            }
+            Self::Multiple {
+                path,
+                errors,
+                total,
+            } => write!(
+                f,
+                "{} of {} manifests in {} failed to load",
+                errors.len(),
+                total,
+                path.to_string_lossy()
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { error, .. } => Some(error),
+            Self::Parse { error, .. } => Some(error.as_ref()),
+            Self::Empty { .. } => None,
+            Self::InvalidFilename { .. } => None,
+            Self::DuplicateTopic { .. } => None,
+            Self::InvalidGlob { error, .. } => Some(error),
+            Self::Write { error, .. } => Some(error),
+            Self::Multiple { .. } => None,
+        }
+    }
+}
+
+/// Manifest files and warnings found by [`walk`]
+#[derive(Default)]
+struct Walked {
+    files: Vec<PathBuf>,
+    warnings: Vec<LoadWarning>,
+}
+
+/// Recursively collect manifest files in `dir`, skipping directories under `root` excluded by
+/// `filter`
+///
+/// Subdirectories are walked in parallel on the current rayon thread pool if `parallel` is set.
+#[cfg_attr(not(feature = "parallel"), allow(clippy::only_used_in_recursion))]
+fn walk(
+    dir: &Path,
+    root: &Path,
+    options: &LoadOptions,
+    filter: &PathFilter,
+    visited: &Mutex<BTreeSet<PathBuf>>,
+    parallel: bool,
+) -> Result<Walked, LoadError> {
+    let io_err = |path: &Path| {
+        let path = path.to_path_buf();
+        move |error| LoadError::Io { path, error }
+    };
+
+    // Skip directories already visited through another symbolic link
+    let canonical = fs::canonicalize(dir).map_err(io_err(dir))?;
+    if !visited
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(canonical)
+    {
+        return Ok(Walked::default());
+    }
+    let mut walked = Walked::default();
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(dir).map_err(io_err(dir))? {
+        let entry = entry.map_err(io_err(dir))?;
+        let path = entry.path();
+        let is_symlink = entry.file_type().map_err(io_err(&path))?.is_symlink();
+        if is_symlink && !options.follow_symlinks {
+            continue;
+        }
+        let metadata = match fs::metadata(&path) {
+            Ok(m) => m,
+            Err(_) if is_symlink => {
+                walked.warnings.push(LoadWarning::DanglingSymlink { path });
+                continue;
+            }
+            Err(error) => return Err(LoadError::Io { path, error }),
+        };
+        if metadata.is_dir() {
+            if !filter.skips_dir(path.strip_prefix(root).unwrap_or(&path)) {
+                dirs.push(path);
+            }
+        } else if metadata.is_file()
+            && path.extension().is_some_and(|ext| {
+                options
+                    .extensions
+                    .iter()
+                    .any(|e| ext.eq_ignore_ascii_case(e))
+            })
+        {
+            walked.files.push(path);
+        }
+    }
+
+    let walk_dir = |path: &PathBuf| walk(path, root, options, filter, visited, parallel);
+    #[cfg(feature = "parallel")]
+    let subdirs: Vec<Walked> = if parallel {
+        dirs.par_iter().map(walk_dir).collect::<Result<_, _>>()?
+    } else {
+        dirs.iter().map(walk_dir).collect::<Result<_, _>>()?
+    };
+    #[cfg(not(feature = "parallel"))]
+    let subdirs: Vec<Walked> = dirs.iter().map(walk_dir).collect::<Result<_, _>>()?;
+    for subdir in subdirs {
+        walked.files.extend(subdir.files);
+        walked.warnings.extend(subdir.warnings);
+    }
+    Ok(walked)
+}
+
+impl LoadOptions {
+    /// Number of threads actually walking directories and parsing files, see [`LoadOptions::jobs`]
+    pub fn effective_jobs(&self) -> usize {
+        #[cfg(feature = "parallel")]
+        match self.jobs {
+            None | Some(0) => rayon::current_num_threads(),
+            Some(jobs) => jobs,
+        }
+        #[cfg(not(feature = "parallel"))]
+        1
+    }
+}
+
+/// Run `f` with [`LoadOptions::jobs`] threads, telling it whether to use rayon
+///
+/// `f` runs on a thread pool of its own for numbers other than `None`, `Some(0)` and `Some(1)`,
+/// so everything it does in parallel is bounded by `jobs`.
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn with_jobs<T: Send>(jobs: Option<usize>, f: impl FnOnce(bool) -> T + Send) -> T {
+    #[cfg(feature = "parallel")]
+    match jobs {
+        Some(1) => (),
+        None | Some(0) => return f(true),
+        Some(jobs) => {
+            // Fall back to the global thread pool if threads could not be spawned
+            return match rayon::ThreadPoolBuilder::new().num_threads(jobs).build() {
+                Ok(pool) => pool.install(|| f(true)),
+                Err(_) => f(true),
+            };
+        }
+    }
+    f(false)
+}
+
+/// Collect manifest files to load from `path`, sorted by path
+///
+/// A single file is loaded as is, but it must have one of [`LoadOptions::extensions`]. Files in
+/// a directory are filtered by [`LoadOptions::exclude`] and [`LoadOptions::include`], and
+/// subdirectories are walked in parallel if `parallel` is set.
+fn collect_paths(
+    path: &Path,
+    options: &LoadOptions,
+    parallel: bool,
+    warnings: &mut Vec<LoadWarning>,
+) -> Result<Vec<PathBuf>, LoadError> {
+    if path.is_file() {
+        let matches = path.extension().is_some_and(|ext| {
+            options
+                .extensions
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        });
+        if !matches {
+            return Err(LoadError::InvalidFilename {
+                path: path.to_path_buf(),
+            });
+        }
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let filter = PathFilter::new(&options.exclude, &options.include).map_err(|error| {
+        LoadError::InvalidGlob {
+            path: path.to_path_buf(),
+            error,
+        }
+    })?;
+    let visited = Mutex::new(BTreeSet::new());
+    let Walked {
+        files: mut paths,
+        warnings: found,
+    } = walk(path, path, options, &filter, &visited, parallel)?;
+    warnings.extend(found);
+    paths.retain(|p| filter.is_included(p.strip_prefix(path).unwrap_or(p)));
+    paths.sort();
+    Ok(paths)
+}
+
+/// Line and column (1-based) of a byte offset in `content`
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = content.get(..offset).unwrap_or(content);
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Parse `content` again as the type of topic it most likely is
+///
+/// Deserializing the untagged [`Manifest`] discards the details and location of errors.
+fn precise_error(content: &str) -> Option<toml::de::Error> {
+    let table: toml::Table = match toml::from_str(content) {
+        Ok(table) => table,
+        Err(error) => return Some(error),
+    };
+    if table.contains_key("topics") {
+        toml::from_str::<Cumulative>(content).err()
+    } else {
+        toml::from_str::<Conventional>(content).err()
+    }
+}
+
+/// Build a parse error of `content` read from `path`
+fn parse_error(path: &Path, content: &str, error: toml::de::Error) -> LoadError {
+    LoadError::Parse {
+        path: path.to_path_buf(),
+        location: error.span().map(|span| line_column(content, span.start)),
+        rendered: parse_error::render(path, content, &error),
+        error: Box::new(error),
+    }
+}
+
+/// Build a parse error found after parsing, pointing at `span` of `content`
+fn parse_error_at(
+    path: &Path,
+    content: &str,
+    error: toml::de::Error,
+    span: Range<usize>,
+) -> LoadError {
+    LoadError::Parse {
+        path: path.to_path_buf(),
+        location: Some(line_column(content, span.start)),
+        rendered: parse_error::render_at(path, content, error.message(), span),
+        error: Box::new(error),
+    }
+}
+
+/// Top-level keys of files with multiple topics
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Batch {
+    /// Marker of such files, see [`is_batch`]
+    #[allow(dead_code)]
+    batch: bool,
+    #[serde(default)]
+    topic: Vec<toml::Spanned<toml::Table>>,
+}
+
+/// Does `content` have `batch = true` at the top level, marking a file with multiple topics
+fn is_batch(content: &str) -> bool {
+    #[derive(Deserialize)]
+    struct Marker {
+        #[serde(default)]
+        batch: bool,
+    }
+    toml::from_str::<Marker>(content).is_ok_and(|m| m.batch)
+}
+
+/// Parse a table of a file with multiple topics as a topic manifest
+fn parse_table(table: toml::Table) -> Result<Manifest, toml::de::Error> {
+    let value = toml::Value::Table(table);
+    value.clone().try_into::<Manifest>().map_err(|error| {
+        // Like `precise_error`, keep the details lost by the untagged `Manifest`
+        if value.get("topics").is_some() {
+            value.try_into::<Cumulative>().err()
+        } else {
+            value.try_into::<Conventional>().err()
+        }
+        .unwrap_or(error)
+    })
+}
+
+/// Parse a file with multiple topics in `[[topic]]` tables, named after their `topic` keys
+fn parse_batch(content: &str, path: &Path) -> Result<Vec<(TopicId, Manifest)>, LoadError> {
+    let batch: Batch =
+        toml::from_str(content).map_err(|error| parse_error(path, content, error))?;
+    batch
+        .topic
+        .into_iter()
+        .map(|entry| {
+            let span = entry.span();
+            let error = match parse_table(entry.into_inner()) {
+                Ok(manifest) => match manifest.get_topic_id() {
+                    Some(name) => return Ok((name.clone(), manifest)),
+                    None => toml::de::Error::missing_field("topic"),
+                },
+                Err(error) => error,
+            };
+            Err(parse_error_at(path, content, error, span))
+        })
+        .collect()
+}
+
+/// Placeholder path in errors of [`ManifestCollection::try_from_toml_string`]
+const REGISTRY_PATH: &str = "manifests.toml";
+
+/// Is `path` a registry of topics in one file, named `manifests.toml` or `*.manifests.toml`
+fn is_registry(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| {
+            let stem = stem.to_ascii_lowercase();
+            stem == "manifests" || stem.ends_with(".manifests")
+        })
+}
+
+/// Top-level key of registries with topics in `[topics.<name>]` tables
+///
+/// Other top-level keys are not allowed next to it, so a topic named `topics` must be written as
+/// `[topics.topics]` rather than in the flat form.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Registry {
+    topics: BTreeMap<String, toml::Spanned<toml::Table>>,
+}
+
+/// Parse a registry of topics, see [`ManifestCollection::try_from_toml_string`]
+fn parse_registry(content: &str, path: &Path) -> Result<Vec<(TopicId, Manifest)>, LoadError> {
+    let to_error = |error| parse_error(path, content, error);
+    let top: toml::Table = toml::from_str(content).map_err(to_error)?;
+    let tables = if let Some(topics) = top.get("topics") {
+        if !topics
+            .as_table()
+            .is_some_and(|t| t.values().all(toml::Value::is_table))
+        {
+            #[derive(Deserialize)]
+            struct Topics {
+                topics: toml::Spanned<serde::de::IgnoredAny>,
+            }
+            let span = toml::from_str::<Topics>(content)
+                .map_err(to_error)?
+                .topics
+                .span();
+            let error = toml::de::Error::custom(
+                "`topics` holds the tables of the topics, write a topic named `topics` as \
+                 `[topics.topics]`",
+            );
+            return Err(parse_error_at(path, content, error, span));
+        }
+        toml::from_str::<Registry>(content)
+            .map_err(to_error)?
+            .topics
+    } else {
+        toml::from_str(content).map_err(to_error)?
+    };
+    tables
+        .into_iter()
+        .map(|(key, entry)| {
+            let span = entry.span();
+            let error = match (TopicId::new(&key), parse_table(entry.into_inner())) {
+                (Err(error), _) => toml::de::Error::custom(error),
+                (Ok(_), Err(error)) => error,
+                (Ok(name), Ok(manifest)) => match manifest.get_topic_id() {
+                    Some(topic) if *topic != name => toml::de::Error::custom(format!(
+                        "`topic` key {} does not match the table name {}",
+                        topic, name
+                    )),
+                    _ => return Ok((name, manifest)),
+                },
+            };
+            Err(parse_error_at(path, content, error, span))
+        })
+        .collect()
+}
+
+impl Manifest {
+    /// Parse a topic manifest in TOML format, keeping the location of parse errors
+    ///
+    /// `path` is only used in errors, so it can be a placeholder like `<stdin>`. Files without any
+    /// key, such as those only containing comments, fail with [`LoadError::Empty`].
+    pub fn from_toml_str(content: &str, path: &Path) -> Result<Self, LoadError> {
+        toml::from_str(content).map_err(|error| {
+            if toml::from_str::<toml::Table>(content).is_ok_and(|t| t.is_empty()) {
+                return LoadError::Empty {
+                    path: path.to_path_buf(),
+                };
+            }
+            parse_error(path, content, precise_error(content).unwrap_or(error))
+        })
+    }
+}
+
+/// Read a file to be parsed
+fn read_file(path: &Path) -> Result<String, LoadError> {
+    fs::read_to_string(path).map_err(|error| LoadError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+impl TryFrom<&Path> for Manifest {
+    type Error = LoadError;
+
+    /// Read and parse a topic manifest, keeping the location of parse errors
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        Self::from_toml_str(&read_file(path)?, path)
+    }
+}
+
+/// Parse a topic manifest, named after its `topic` key or its file stem, or a file with multiple
+/// topics, see [`ManifestCollection::from_toml_batch_str`] and
+/// [`ManifestCollection::try_from_toml_string`]
+fn load_file(path: &Path) -> Result<Vec<(TopicId, Manifest)>, LoadError> {
+    let content = read_file(path)?;
+    if is_registry(path) {
+        return parse_registry(&content, path);
+    }
+    if is_batch(&content) {
+        return parse_batch(&content, path);
+    }
+    let manifest = Manifest::from_toml_str(&content, path)?;
+    let name = match manifest.get_topic_id() {
+        Some(topic) => topic.clone(),
+        None => path
+            .file_stem()
+            .and_then(|stem| TopicId::new(stem.to_str()?).ok())
+            .ok_or_else(|| LoadError::InvalidFilename {
+                path: path.to_path_buf(),
+            })?,
+    };
+    Ok(vec![(name, manifest)])
+}
+
+/// Topics in each file, or the error loading it
+type FileResults = Vec<Result<Vec<(TopicId, Manifest)>, LoadError>>;
+
+/// Load files in `paths` in order, in parallel on the current rayon thread pool if `parallel` is
+/// set
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn load_files(paths: &[PathBuf], parallel: bool) -> FileResults {
+    #[cfg(feature = "parallel")]
+    if parallel {
+        return paths.par_iter().map(|p| load_file(p)).collect();
+    }
+    paths.iter().map(|p| load_file(p)).collect()
+}
+
+impl ManifestCollection {
+    /// Parse a file with multiple topics in TOML format
+    ///
+    /// Such files are marked with `batch = true` at the top level, and have a `[[topic]]` table
+    /// for each topic, named after its `topic` key, see [`Manifest::get_topic_id`]. Other keys are
+    /// not allowed at the top level. `path` is only used in errors.
+    pub fn from_toml_batch_str(content: &str, path: &Path) -> Result<Self, LoadError> {
+        let mut topics = BTreeMap::new();
+        for (name, manifest) in parse_batch(content, path)? {
+            if topics.contains_key(&name) {
+                return Err(LoadError::DuplicateTopic {
+                    path: path.to_path_buf(),
+                    topic: name.into(),
+                    first: path.to_path_buf(),
+                });
+            }
+            topics.insert(name, manifest);
+        }
+        Ok(topics.into())
+    }
+
+    /// Parse a registry of topics in one TOML document
+    ///
+    /// Topics are either tables under `topics`, like `[topics.kde-survey-20231201]`, or top-level
+    /// tables named after the topics, like `[kde-survey-20231201]`. A top-level `topics` table
+    /// always holds the topics, and no other top-level key is allowed next to it, so a topic named
+    /// `topics` has to be written as `[topics.topics]`. A `topic` key in a table must match its
+    /// name. Files named `manifests.toml` or `*.manifests.toml` are loaded this way by
+    /// [`ManifestCollection::load_from_dir`]. Errors refer to the document as `manifests.toml`.
+    pub fn try_from_toml_string(s: &str) -> Result<Self, LoadError> {
+        let topics = parse_registry(s, Path::new(REGISTRY_PATH))?;
+        Ok(topics.into_iter().collect::<BTreeMap<_, _>>().into())
+    }
+
+    /// Load all manifests in a directory recursively, named after their file stems
+    ///
+    /// Manifests with a `topic` key are named after it instead, see [`Manifest::get_topic_id`].
+    /// Files marked with `batch = true` may have multiple topics, see
+    /// [`ManifestCollection::from_toml_batch_str`], and so do registries named `manifests.toml`,
+    /// see [`ManifestCollection::try_from_toml_string`].
+    ///
+    /// `dir` may also be a single manifest file, which is loaded as a collection of one topic.
+    /// Files are parsed in parallel with the `parallel` feature, see [`LoadOptions::jobs`]. Unless
+    /// [`LoadOptions::ignore_errors`] is set, all files are still parsed, and the error of the
+    /// file failing to load is returned, or [`LoadError::Multiple`] if several files fail.
+    /// Files with the same topic name, like the same stem in different directories, fail with
+    /// [`LoadError::DuplicateTopic`], or the last one in path order wins if errors are ignored.
+    /// Warnings are sorted by path.
+    pub fn load_from_dir(
+        dir: &Path,
+        options: &LoadOptions,
+    ) -> Result<(Self, Vec<LoadWarning>), LoadError> {
+        let (collection, _, warnings) = Self::load_from_dir_with_sources(dir, options)?;
+        Ok((collection, warnings))
+    }
+
+    /// Same as [`ManifestCollection::load_from_dir`], also returning the file defining each topic
+    pub fn load_from_dir_with_sources(
+        dir: &Path,
+        options: &LoadOptions,
+    ) -> Result<(Self, TopicSources, Vec<LoadWarning>), LoadError> {
+        let mut warnings = Vec::new();
+        let (paths, results) = with_jobs(options.jobs, |parallel| {
+            let paths = collect_paths(dir, options, parallel, &mut warnings)?;
+            let results = load_files(&paths, parallel);
+            Ok::<_, LoadError>((paths, results))
+        })?;
+        let mut topics = BTreeMap::new();
+        let mut sources: BTreeMap<TopicId, &Path> = BTreeMap::new();
+        let mut errors = Vec::new();
+        for (path, result) in paths.iter().zip(results) {
+            match result {
+                Ok(entries) => {
+                    for (name, manifest) in entries {
+                        if let Some(first) = sources.get(&name) {
+                            let (path, first) = (path.to_path_buf(), first.to_path_buf());
+                            if !options.ignore_errors {
+                                errors.push(LoadError::DuplicateTopic {
+                                    path,
+                                    topic: name.into(),
+                                    first,
+                                });
+                                continue;
+                            }
+                            warnings.push(LoadWarning::DuplicateTopic {
+                                path,
+                                topic: name.to_string(),
+                                previous: first,
+                            });
+                        }
+                        sources.insert(name.clone(), path);
+                        topics.insert(name, manifest);
+                    }
+                }
+                Err(LoadError::Empty { .. }) if options.skip_empty => (),
+                Err(error) if options.ignore_errors => warnings.push(LoadWarning::Skipped(error)),
+                Err(error) => errors.push(error),
+            }
+        }
+        if errors.len() > 1 {
+            return Err(LoadError::Multiple {
+                path: dir.to_path_buf(),
+                errors,
+                total: paths.len(),
+            });
+        }
+        if let Some(error) = errors.pop() {
+            return Err(error);
+        }
+        warnings.sort_by(|a, b| a.get_path().cmp(b.get_path()));
+        let sources = sources
+            .into_iter()
+            .map(|(name, path)| (name, path.to_path_buf()))
+            .collect();
+        Ok((topics.into(), sources, warnings))
+    }
+
+    /// Load TOML manifests in a directory and write them to `writer` as pretty-printed JSON
+    ///
+    /// Unlike [`ManifestCollection::load_from_dir`], manifests are parsed one at a time and written
+    /// out immediately, so memory usage does not grow with the number of topics. Topics are written
+    /// in the order of their source paths, not sorted by name like [`ManifestCollection`] output,
+    /// no consistency checks are done, and `extends` of topics is written as is rather than
+    /// resolved. Returns the number of topics written.
+    ///
+    /// [`LoadOptions::ignore_errors`] is not supported, writing stops at the first error.
+    #[cfg(feature = "json")]
+    pub fn write_streaming<W: Write>(
+        dir: &Path,
+        options: &LoadOptions,
+        writer: W,
+    ) -> Result<usize, LoadError> {
+        let paths = with_jobs(options.jobs, |parallel| {
+            collect_paths(dir, options, parallel, &mut Vec::new())
+        })?;
+
+        let write_err = |path: &Path| {
+            let path = path.to_path_buf();
+            move |error: serde_json::Error| LoadError::Write {
+                path,
+                error: error.into(),
+            }
+        };
+
+        let mut ser = serde_json::Serializer::pretty(writer);
+        let mut map = ser.serialize_map(None).map_err(write_err(dir))?;
+        let mut sources: BTreeMap<TopicId, &Path> = BTreeMap::new();
+        for path in &paths {
+            let entries = match load_file(path) {
+                Err(LoadError::Empty { .. }) if options.skip_empty => continue,
+                result => result?,
+            };
+            for (name, manifest) in entries {
+                if let Some(first) = sources.get(&name) {
+                    return Err(LoadError::DuplicateTopic {
+                        path: path.to_path_buf(),
+                        topic: name.into(),
+                        first: first.to_path_buf(),
+                    });
+                }
+                map.serialize_entry(&name, &manifest)
+                    .map_err(write_err(path))?;
+                sources.insert(name, path);
+            }
+        }
+        map.end().map_err(write_err(dir))?;
+        ser.into_inner().flush().map_err(|error| LoadError::Write {
+            path: dir.to_path_buf(),
+            error,
+        })?;
+        Ok(sources.len())
+    }
+
+    /// Save the collection to a file as pretty-printed JSON
+    #[cfg(feature = "json")]
+    pub fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+    use tempfile::TempDir;
+
+    use std::collections::HashSet;
+    use std::fs;
+    #[cfg(feature = "json")]
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use super::{LoadError, LoadOptions, LoadWarning};
+    use crate::{Manifest, ManifestCollection};
+
+    fn data_dir() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../data"))
+    }
+
+    #[test]
+    fn test_load() -> Result<()> {
+        let collection = ManifestCollection::load_from_dir(data_dir(), &LoadOptions::default())?.0;
+        assert_eq!(collection.len(), 2);
+        assert!(collection
+            .get("kde-survey-20231201")
+            .unwrap()
+            .is_conventional());
+        assert!(collection.get("cumulative-2023H3").unwrap().is_cumulative());
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_save() -> Result<()> {
+        let collection = ManifestCollection::load_from_dir(data_dir(), &LoadOptions::default())?.0;
+        let dir = TempDir::new()?;
+        let path = dir.path().join("topics.json");
+        collection.save_to_file(&path)?;
+        let saved: ManifestCollection = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(saved, collection);
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_write_streaming() -> Result<()> {
+        let mut output = Vec::new();
+        let options = LoadOptions::default();
+        let count = ManifestCollection::write_streaming(data_dir(), &options, &mut output)?;
+        assert_eq!(count, 2);
+        let streamed: ManifestCollection = serde_json::from_slice(&output)?;
+        assert_eq!(
+            streamed,
+            ManifestCollection::load_from_dir(data_dir(), &options)?.0
+        );
+
+        let dir = TempDir::new()?;
+        fs::create_dir_all(dir.path().join("nested"))?;
+        for path in ["kde.toml", "nested/kde.toml"] {
+            fs::copy(
+                data_dir().join("kde-survey-20231201.toml"),
+                dir.path().join(path),
+            )?;
+        }
+        let err =
+            ManifestCollection::write_streaming(dir.path(), &options, io::sink()).unwrap_err();
+        assert!(matches!(err, LoadError::DuplicateTopic { topic, .. } if topic == "kde"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_topics() -> Result<()> {
+        let dir = TempDir::new()?;
+        for path in ["stable/kde.toml", "testing/kde.toml", "testing/qt/kde.toml"] {
+            fs::create_dir_all(dir.path().join(path).parent().unwrap())?;
+            fs::copy(
+                data_dir().join("kde-survey-20231201.toml"),
+                dir.path().join(path),
+            )?;
+        }
+        fs::copy(
+            data_dir().join("cumulative-2023H3.toml"),
+            dir.path().join("testing/qt/kde.toml"),
+        )?;
+
+        let err =
+            ManifestCollection::load_from_dir(dir.path(), &LoadOptions::default()).unwrap_err();
+        let pairs: Vec<(PathBuf, PathBuf)> = err
+            .get_errors()
+            .iter()
+            .map(|e| match e {
+                LoadError::DuplicateTopic { path, topic, first } => {
+                    assert_eq!(topic, "kde");
+                    let relative = |p: &PathBuf| p.strip_prefix(dir.path()).unwrap().to_owned();
+                    (relative(first), relative(path))
+                }
+                _ => panic!("unexpected error {}", e),
+            })
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("stable/kde.toml".into(), "testing/kde.toml".into()),
+                ("stable/kde.toml".into(), "testing/qt/kde.toml".into()),
+            ]
+        );
+        assert!(err.get_errors()[0]
+            .to_string()
+            .contains(", already defined in "));
+
+        let options = LoadOptions {
+            ignore_errors: true,
+            ..Default::default()
+        };
+        let (collection, sources, warnings) =
+            ManifestCollection::load_from_dir_with_sources(dir.path(), &options)?;
+        assert_eq!(collection.len(), 1);
+        // The last file in path order wins
+        assert!(collection.get("kde").unwrap().is_cumulative());
+        assert_eq!(sources["kde"], dir.path().join("testing/qt/kde.toml"));
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings
+            .iter()
+            .all(|w| matches!(w, LoadWarning::DuplicateTopic { .. })));
+        assert_eq!(
+            warnings[1].get_path(),
+            dir.path().join("testing/qt/kde.toml")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_jobs() -> Result<()> {
+        let dir = TempDir::new()?;
+        // Spread files over nested directories, so that walking them is parallel too
+        for (i, entry) in fs::read_dir(data_dir())?.enumerate() {
+            let path = entry?.path();
+            let subdir = dir.path().join(format!("{}/{}", i % 3, i % 2));
+            fs::create_dir_all(&subdir)?;
+            fs::copy(&path, subdir.join(path.file_name().unwrap()))?;
+        }
+        let load = |jobs| {
+            let options = LoadOptions {
+                jobs,
+                ..Default::default()
+            };
+            ManifestCollection::load_from_dir(dir.path(), &options)
+        };
+        let expected = load(None)?.0;
+        assert!(!expected.is_empty());
+        for jobs in [Some(0), Some(1), Some(2)] {
+            assert_eq!(load(jobs)?.0, expected);
+        }
+
+        fs::write(dir.path().join("0/a.toml"), "name = 1")?;
+        fs::write(dir.path().join("1/b.toml"), "")?;
+        let expected = load(None).unwrap_err().to_string();
+        for jobs in [Some(1), Some(2)] {
+            assert_eq!(load(jobs).unwrap_err().to_string(), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_nested_and_broken() -> Result<()> {
+        let dir = TempDir::new()?;
+        let options = LoadOptions::default();
+        fs::create_dir_all(dir.path().join("nested"))?;
+        fs::copy(
+            data_dir().join("kde-survey-20231201.toml"),
+            dir.path().join("nested/kde.TOML"),
+        )?;
+        fs::write(dir.path().join("README.md"), "Not a manifest")?;
+        let collection = ManifestCollection::load_from_dir(dir.path(), &options)?.0;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["kde"]
+        );
+
+        fs::write(dir.path().join("b.toml"), "name = 1")?;
+        fs::write(dir.path().join("a.toml"), "name = 1")?;
+        let err = ManifestCollection::load_from_dir(dir.path(), &options).unwrap_err();
+        assert!(matches!(err, LoadError::Multiple { total: 3, .. }));
+        assert_eq!(err.get_path(), dir.path());
+        assert_eq!(
+            err.get_errors()
+                .iter()
+                .map(|e| e.get_path())
+                .collect::<Vec<_>>(),
+            [dir.path().join("a.toml"), dir.path().join("b.toml")]
+        );
+        assert!(err
+            .get_errors()
+            .iter()
+            .all(|e| matches!(e, LoadError::Parse { .. })));
+        assert!(err.to_string().starts_with("2 of 3 manifests in "));
+
+        fs::remove_file(dir.path().join("b.toml"))?;
+        let err = ManifestCollection::load_from_dir(dir.path(), &options).unwrap_err();
+        assert!(matches!(err, LoadError::Parse { .. }));
+        assert_eq!(err.get_errors().len(), 1);
+        assert_eq!(err.get_path(), dir.path().join("a.toml"));
+
+        fs::write(dir.path().join("a.toml"), "# 注释\nname.default = 1\n")?;
+        let err = ManifestCollection::load_from_dir(dir.path(), &options).unwrap_err();
+        assert_eq!(err.get_location(), Some((2, 16)));
+        assert!(
+            err.to_string()
+                .ends_with("invalid type: integer `1`, expected a string at line 2, column 16"),
+            "{}",
+            err
+        );
+        assert_eq!(
+            format!("{:#}", err),
+            format!(
+                "invalid type: integer `1`, expected a string\n --> {}:2:16\n  |\n2 | name.default = 1\n  |                ^",
+                dir.path().join("a.toml").display()
+            )
+        );
+
+        let (collection, _) =
+            ManifestCollection::load_from_dir(&dir.path().join("nested/kde.TOML"), &options)?;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["kde"]
+        );
+        let err =
+            ManifestCollection::load_from_dir(&dir.path().join("README.md"), &options).unwrap_err();
+        assert!(matches!(err, LoadError::InvalidFilename { .. }));
+
+        fs::write(dir.path().join("a.toml"), "# comment\n\n")?;
+        let err = ManifestCollection::load_from_dir(dir.path(), &options).unwrap_err();
+        assert!(matches!(err, LoadError::Empty { .. }));
+        assert_eq!(
+            err.to_string(),
+            format!("Manifest file {} is empty", err.get_path().display())
+        );
+        let skip_empty = LoadOptions {
+            skip_empty: true,
+            ..Default::default()
+        };
+        let (collection, warnings) = ManifestCollection::load_from_dir(dir.path(), &skip_empty)?;
+        assert_eq!(collection.len(), 1);
+        assert!(warnings.is_empty());
+
+        let err =
+            ManifestCollection::load_from_dir(&dir.path().join("missing"), &options).unwrap_err();
+        assert!(matches!(err, LoadError::Io { .. }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_include() -> Result<()> {
+        let dir = TempDir::new()?;
+        let kde = data_dir().join("kde-survey-20231201.toml");
+        for path in [
+            "kde.toml",
+            "templates/conventional.toml",
+            "templates/nested/cumulative.toml",
+        ] {
+            fs::create_dir_all(dir.path().join(path).parent().unwrap())?;
+            fs::copy(&kde, dir.path().join(path))?;
+        }
+        fs::create_dir_all(dir.path().join("tools"))?;
+        fs::write(
+            dir.path().join("tools/taplo.toml"),
+            "[formatting]\nalign_entries = true\n",
+        )?;
+        assert!(ManifestCollection::load_from_dir(dir.path(), &LoadOptions::default()).is_err());
+
+        let mut options = LoadOptions {
+            exclude: vec!["templates/**".to_string(), "**/taplo.toml".to_string()],
+            ..Default::default()
+        };
+        let (collection, _) = ManifestCollection::load_from_dir(dir.path(), &options)?;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["kde"]
+        );
+
+        options.include = vec!["templates/nested/*".to_string()];
+        let (collection, _) = ManifestCollection::load_from_dir(dir.path(), &options)?;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["cumulative", "kde"]
+        );
+
+        // Excluded directories are not read at all
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("missing", dir.path().join("templates/dangling"))?;
+            let (_, warnings) = ManifestCollection::load_from_dir(dir.path(), &options)?;
+            assert_eq!(warnings.len(), 1);
+            options.include.clear();
+            let (_, warnings) = ManifestCollection::load_from_dir(dir.path(), &options)?;
+            assert!(warnings.is_empty());
+        }
+        options.exclude.push("[".to_string());
+        let err = ManifestCollection::load_from_dir(dir.path(), &options).unwrap_err();
+        assert!(matches!(err, LoadError::InvalidGlob { .. }));
+        assert_eq!(err.get_path(), dir.path());
+
+        // A single file is loaded regardless of the patterns
+        let (collection, _) = ManifestCollection::load_from_dir(
+            &dir.path().join("templates/conventional.toml"),
+            &options,
+        )?;
+        assert_eq!(collection.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_topic_key() -> Result<()> {
+        let dir = TempDir::new()?;
+        let conventional = |key: &str| {
+            format!(
+                "{}\nname.default = \"Core\"\nsecurity = false\ncaution.default = \"\"\n[packages]\ngcc = \"13.2.0\"",
+                key
+            )
+        };
+        fs::write(
+            dir.path().join("core 12.toml"),
+            conventional("topic = \"core-12.1.0\""),
+        )?;
+        fs::write(
+            dir.path().join("2023H3.toml"),
+            "id = \"cumulative-2023H3\"\nname.default = \"Cumulative\"\ntopics = [\"core-12.1.0\"]",
+        )?;
+        let (collection, _) =
+            ManifestCollection::load_from_dir(dir.path(), &LoadOptions::default())?;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["core-12.1.0", "cumulative-2023H3"]
+        );
+        assert!(collection.is_consistent());
+        let manifest = collection.get("core-12.1.0").unwrap();
+        assert_eq!(
+            manifest.get_topic_id().map(|t| t.as_str()),
+            Some("core-12.1.0")
+        );
+        // The key does not leak into the serialized value
+        let json = serde_json::to_string(manifest)?;
+        assert!(!json.contains("core-12.1.0"));
+        let from_json = serde_json::from_str::<Manifest>(&json)?;
+        assert_eq!(from_json.get_topic_id(), None);
+        // Nor into comparisons and hashes
+        assert_eq!(from_json, *manifest);
+        assert_eq!(HashSet::from([from_json, manifest.clone()]).len(), 1);
+        assert!(manifest
+            .to_toml_string()
+            .starts_with("topic = \"core-12.1.0\"\n"));
+
+        // Topic keys collide with file stems
+        fs::write(dir.path().join("core-12.1.0.toml"), conventional(""))?;
+        let err =
+            ManifestCollection::load_from_dir(dir.path(), &LoadOptions::default()).unwrap_err();
+        assert!(matches!(err, LoadError::DuplicateTopic { topic, .. } if topic == "core-12.1.0"));
+
+        fs::remove_file(dir.path().join("core-12.1.0.toml"))?;
+        fs::write(
+            dir.path().join("core 12.toml"),
+            conventional("topic = \"core/12\""),
+        )?;
+        let err =
+            ManifestCollection::load_from_dir(dir.path(), &LoadOptions::default()).unwrap_err();
+        assert!(matches!(err, LoadError::Parse { .. }));
+        assert_eq!(err.get_location(), Some((1, 9)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch() -> Result<()> {
+        let dir = TempDir::new()?;
+        let batch = r#"
+batch = true
+
+[[topic]]
+topic = "mesa-23.3"
+name.default = "Mesa 23.3"
+security = false
+caution.default = ""
+[topic.packages]
+mesa = "23.3.0"
+
+[[topic]]
+id = "graphics-2023H4"
+name.default = "Graphics"
+topics = ["mesa-23.3", "kde"]
+"#;
+        fs::write(dir.path().join("graphics.toml"), batch)?;
+        fs::copy(
+            data_dir().join("kde-survey-20231201.toml"),
+            dir.path().join("kde.toml"),
+        )?;
+        let (collection, _) =
+            ManifestCollection::load_from_dir(dir.path(), &LoadOptions::default())?;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["graphics-2023H4", "kde", "mesa-23.3"]
+        );
+        assert!(collection.is_consistent());
+        let parse = |content: &str| {
+            ManifestCollection::from_toml_batch_str(content, Path::new("graphics.toml"))
+        };
+        assert_eq!(parse(batch)?.len(), 2);
+
+        let err = parse(&batch.replace("graphics-2023H4", "mesa-23.3")).unwrap_err();
+        assert!(matches!(err, LoadError::DuplicateTopic { topic, .. } if topic == "mesa-23.3"));
+
+        // Errors point at the table of the topic
+        let err = parse(&batch.replace("id = ", "# id = ")).unwrap_err();
+        assert_eq!(err.get_location(), Some((12, 1)));
+        assert_eq!(
+            format!("{:#}", err),
+            "missing field `topic`\n  --> graphics.toml:12:1\n   |\n12 | [[topic]]\n   | ^^^^^^^^^"
+        );
+        let err = parse(&batch.replace("false", "\"no\"")).unwrap_err();
+        assert_eq!(err.get_location(), Some((4, 1)));
+        assert!(err
+            .to_string()
+            .contains("invalid type: string \"no\", expected a boolean"));
+        let err = parse(&format!("security = true\n{}", batch)).unwrap_err();
+        assert!(err.to_string().contains("unknown field `security`"));
+
+        // Without the marker, files are single topics
+        let single = fs::read_to_string(dir.path().join("kde.toml"))?;
+        fs::write(
+            dir.path().join("graphics.toml"),
+            format!("batch = false\n{}", single),
+        )?;
+        let (collection, _) =
+            ManifestCollection::load_from_dir(dir.path(), &LoadOptions::default())?;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["graphics", "kde"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_registry() -> Result<()> {
+        let registry = r#"
+[topics.mesa-23_3]
+name.default = "Mesa 23.3"
+security = false
+caution.default = ""
+[topics.mesa-23_3.packages]
+mesa = "23.3.0"
+
+[topics.graphics-2023H4]
+name.default = "Graphics"
+topics = ["mesa-23_3"]
+"#;
+        let collection = ManifestCollection::try_from_toml_string(registry)?;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["graphics-2023H4", "mesa-23_3"]
+        );
+        assert!(collection.get("graphics-2023H4").unwrap().is_cumulative());
+        assert!(collection.is_consistent());
+
+        // Top-level tables named after topics
+        let flat = registry.replace("[topics.", "[");
+        assert_eq!(ManifestCollection::try_from_toml_string(&flat)?, collection);
+        assert!(ManifestCollection::try_from_toml_string("")?.is_empty());
+
+        let err =
+            ManifestCollection::try_from_toml_string(&flat.replace("false", "\"no\"")).unwrap_err();
+        assert_eq!(err.get_path(), Path::new("manifests.toml"));
+        assert_eq!(err.get_location(), Some((2, 1)));
+        assert!(err
+            .to_string()
+            .contains("invalid type: string \"no\", expected a boolean"));
+        let err = ManifestCollection::try_from_toml_string(&flat.replace("mesa-23_3]", "mesa 23]"))
+            .unwrap_err();
+        assert!(matches!(err, LoadError::Parse { .. }));
+        let err = ManifestCollection::try_from_toml_string(&registry.replace(
+            "[topics.graphics-2023H4]",
+            "[topics.graphics-2023H4]\ntopic = \"gfx\"",
+        ))
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("`topic` key gfx does not match the table name graphics-2023H4"));
+        let err = ManifestCollection::try_from_toml_string("security = true").unwrap_err();
+        assert!(err.to_string().contains("expected a map"), "{}", err);
+
+        // A top-level `topics` table is never a topic of the flat form
+        let nested = "[topics.topics]\nname.default = \"Topics\"\ntopics = [\"mesa-23_3\"]\n";
+        let err =
+            ManifestCollection::try_from_toml_string(&format!("{}{}", flat, nested)).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `mesa-23_3`"),
+            "{}",
+            err
+        );
+        let topics = ManifestCollection::try_from_toml_string(&format!("{}{}", registry, nested))?;
+        assert!(topics.get("topics").unwrap().is_cumulative());
+        let err =
+            ManifestCollection::try_from_toml_string(&nested.replace(".topics]", "]")).unwrap_err();
+        assert_eq!(err.get_location(), Some((1, 1)));
+        assert!(err
+            .to_string()
+            .contains("write a topic named `topics` as `[topics.topics]`"));
+        let err = ManifestCollection::try_from_toml_string("topics = []").unwrap_err();
+        assert_eq!(err.get_location(), Some((1, 10)));
+
+        // Registries are detected by file name
+        let dir = TempDir::new()?;
+        fs::write(dir.path().join("stable.manifests.toml"), registry)?;
+        fs::copy(
+            data_dir().join("kde-survey-20231201.toml"),
+            dir.path().join("kde.toml"),
+        )?;
+        let (loaded, _) = ManifestCollection::load_from_dir(dir.path(), &LoadOptions::default())?;
+        assert_eq!(
+            loaded.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["graphics-2023H4", "kde", "mesa-23_3"]
+        );
+        fs::rename(
+            dir.path().join("stable.manifests.toml"),
+            dir.path().join("registry.toml"),
+        )?;
+        let err =
+            ManifestCollection::load_from_dir(dir.path(), &LoadOptions::default()).unwrap_err();
+        assert_eq!(err.get_path(), dir.path().join("registry.toml"));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_options() -> Result<()> {
+        let dir = TempDir::new()?;
+        let linked = TempDir::new()?;
+        fs::copy(
+            data_dir().join("kde-survey-20231201.toml"),
+            linked.path().join("kde.toml"),
+        )?;
+        std::os::unix::fs::symlink(linked.path(), dir.path().join("linked"))?;
+        std::os::unix::fs::symlink(dir.path().join("missing"), dir.path().join("dangling"))?;
+        fs::copy(
+            data_dir().join("cumulative-2023H3.toml"),
+            dir.path().join("2023H3.topic"),
+        )?;
+        fs::write(dir.path().join("broken.toml"), "name = 1")?;
+
+        let mut options = LoadOptions {
+            ignore_errors: true,
+            ..Default::default()
+        };
+        let (collection, warnings) = ManifestCollection::load_from_dir(dir.path(), &options)?;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["kde"]
+        );
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].get_path(), dir.path().join("broken.toml"));
+        assert!(matches!(
+            warnings[0],
+            LoadWarning::Skipped(LoadError::Parse { .. })
+        ));
+        assert!(matches!(warnings[1], LoadWarning::DanglingSymlink { .. }));
+
+        options.follow_symlinks = false;
+        options.extensions = vec!["topic".to_string()];
+        let (collection, warnings) = ManifestCollection::load_from_dir(dir.path(), &options)?;
+        assert_eq!(
+            collection.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["2023H3"]
+        );
+        assert!(warnings.is_empty());
+        Ok(())
+    }
+}
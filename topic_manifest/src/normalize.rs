@@ -0,0 +1,200 @@
+//! Normalization of localized strings
+//!
+//! Multi-line TOML strings often carry the indentation of the manifest and trailing newlines.
+//! Normalization removes them, so update clients can display the strings as is.
+
+use localized::{Locale, Localized};
+
+use super::translation::LocalizedField;
+use super::{Manifest, ManifestCollection};
+
+/// Options of manifest normalization
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Maximum number of characters in topic names, longer names are reported but kept as is
+    pub max_name_length: Option<usize>,
+}
+
+/// A topic name longer than [`NormalizeOptions::max_name_length`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameTooLong {
+    /// Locale of the name, `None` for the default name
+    pub locale: Option<Locale>,
+    /// Number of characters in the name
+    pub length: usize,
+}
+
+/// Normalize whitespace in a string
+///
+/// Trailing whitespace and common indentation of lines are removed, runs of blank lines are
+/// collapsed into one, and leading and trailing blank lines are dropped.
+pub fn normalize_str(s: &str) -> String {
+    let lines: Vec<&str> = s.lines().map(str::trim_end).collect();
+    let indent = lines
+        .iter()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or(0);
+
+    let mut result = String::with_capacity(s.len());
+    let mut blank = false;
+    for line in lines {
+        if line.is_empty() {
+            blank = !result.is_empty();
+            continue;
+        }
+        if !result.is_empty() {
+            result.push('\n');
+            if blank {
+                result.push('\n');
+            }
+        }
+        blank = false;
+        result.extend(line.chars().skip(indent));
+    }
+    result
+}
+
+fn normalize_localized(localized: &mut Localized<String>) {
+    for s in localized
+        .default
+        .iter_mut()
+        .chain(localized.content.values_mut())
+    {
+        *s = normalize_str(s);
+    }
+}
+
+impl Manifest {
+    /// Normalize whitespace in localized strings of the topic, see [`normalize_str`]
+    ///
+    /// Returns names longer than [`NormalizeOptions::max_name_length`].
+    pub fn normalize(&mut self, options: &NormalizeOptions) -> Vec<NameTooLong> {
+        let fields: Vec<(LocalizedField, &mut Localized<String>)> = match self {
+            Self::Conventional(c) => c.localized_fields_mut().into_iter().collect(),
+            Self::Cumulative(c) => c.localized_fields_mut().into_iter().collect(),
+        };
+
+        let mut too_long = Vec::new();
+        for (field, localized) in fields {
+            normalize_localized(localized);
+            let Some(max) = options.max_name_length else {
+                continue;
+            };
+            if field != LocalizedField::Name {
+                continue;
+            }
+            let names = localized
+                .default
+                .iter()
+                .map(|s| (None, s))
+                .chain(localized.content.iter().map(|(l, s)| (Some(l), s)));
+            for (locale, name) in names {
+                let length = name.chars().count();
+                if length > max {
+                    too_long.push(NameTooLong {
+                        locale: locale.cloned(),
+                        length,
+                    });
+                }
+            }
+        }
+        too_long
+    }
+}
+
+impl ManifestCollection {
+    /// Normalize all topics in the collection, see [`Manifest::normalize`]
+    ///
+    /// Returns topics with names longer than [`NormalizeOptions::max_name_length`].
+    pub fn normalize_all(&mut self, options: &NormalizeOptions) -> Vec<(String, Vec<NameTooLong>)> {
+        self.topics
+            .iter_mut()
+            .filter_map(|(k, v)| {
+                let too_long = v.normalize(options);
+                if too_long.is_empty() {
+                    None
+                } else {
+                    Some((k.to_string(), too_long))
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+
+    use super::{normalize_str, NameTooLong, NormalizeOptions};
+    use crate::test_util::topics;
+    use crate::{Locale, Manifest, ManifestCollection};
+
+    #[test]
+    fn test_normalize_str() {
+        assert_eq!(normalize_str("  KDE Updates  "), "KDE Updates");
+        assert_eq!(normalize_str(""), "");
+        assert_eq!(normalize_str(" \n \n"), "");
+        assert_eq!(
+            normalize_str(
+                "\n    First line.\n      Indented.\n\n   \n\n    Second paragraph.  \n    "
+            ),
+            "First line.\n  Indented.\n\nSecond paragraph."
+        );
+        // Full-width spaces are whitespace too
+        assert_eq!(normalize_str("\u{3000}\u{3000}注意\n"), "注意");
+    }
+
+    #[test]
+    fn test_normalize_all() -> Result<()> {
+        let manifest: Manifest = toml::from_str(
+            r#"
+            name.default = "  KDE Updates (Winter 2023) "
+            name.zh_CN = "KDE 更新（2023 年冬季）"
+            security = false
+            caution.default = """
+            This topic may use significantly more memory after reboot.
+
+            Please reboot.
+            """
+            [packages]
+            "#,
+        )?;
+        let mut collection = ManifestCollection::from(topics([("kde".to_string(), manifest)]));
+
+        let too_long = collection.normalize_all(&NormalizeOptions {
+            max_name_length: Some(20),
+        });
+        assert_eq!(
+            too_long,
+            [(
+                "kde".to_string(),
+                vec![NameTooLong {
+                    locale: None,
+                    length: 25,
+                }]
+            )]
+        );
+        let Manifest::Conventional(c) = collection.get("kde").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(c.get_name()[""], "KDE Updates (Winter 2023)");
+        assert_eq!(
+            c.get_caution()[""],
+            "This topic may use significantly more memory after reboot.\n\nPlease reboot."
+        );
+        assert!(collection
+            .normalize_all(&NormalizeOptions::default())
+            .is_empty());
+        assert_eq!(
+            collection.normalize_all(&NormalizeOptions {
+                max_name_length: Some(10),
+            })[0]
+                .1[1]
+                .locale,
+            Some(Locale::new("zh-CN"))
+        );
+        Ok(())
+    }
+}
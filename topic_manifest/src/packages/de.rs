@@ -1,24 +1,186 @@
-use serde::de::{Error, MapAccess, Unexpected, Visitor};
+use serde::de::{DeserializeSeed, Deserializer, Error, MapAccess, Visitor};
 use serde::Deserialize;
+use toml_edit::{Item, Value};
 
 use std::collections::BTreeMap;
 use std::fmt;
 use std::marker::PhantomData;
 
-pub use super::Packages;
+pub use super::{ArchVersions, PackageMap, Packages};
 
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum PackageVersion {
-    Bool(bool),
-    Ver(String),
-    OptionVer(Option<String>),
+/// Version of a package as written in manifests
+enum PackageValue {
+    /// A version string, or `None` for removal of the package
+    Scalar(Option<String>),
+    /// Versions per architecture, and the version for other architectures under `default`
+    ArchSpecific(Option<Option<String>>, ArchVersions),
+}
+
+/// Seed for deserializing the version of a package, carrying the package name for error messages
+struct PackageValueSeed<'a> {
+    key: &'a str,
+    /// Accept tables of versions per architecture
+    arch_specific: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for PackageValueSeed<'_> {
+    type Value = PackageValue;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl PackageValueSeed<'_> {
+    fn invalid<E: Error, T: fmt::Display>(&self, value: T) -> E {
+        E::custom(format!(
+            "package `{}`: `{}` is not a valid value; use a version string or `false` for removal",
+            self.key, value
+        ))
+    }
+
+    fn number<E: Error, T: fmt::Display>(&self, value: T) -> Result<PackageValue, E> {
+        Err(E::custom(format!(
+            "package `{}`: `{}` is a number, not a version string; quote it as `{} = \"{}\"`",
+            self.key, value, self.key, value
+        )))
+    }
+}
+
+impl<'de> Visitor<'de> for PackageValueSeed<'_> {
+    type Value = PackageValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if self.arch_specific {
+            write!(
+                formatter,
+                "a version string, a table of versions per architecture or `false` for package `{}`",
+                self.key
+            )
+        } else {
+            write!(
+                formatter,
+                "a version string or `false` for package `{}`",
+                self.key
+            )
+        }
+    }
+
+    fn visit_bool<E: Error>(self, v: bool) -> Result<Self::Value, E> {
+        if v {
+            Err(self.invalid(v))
+        } else {
+            Ok(PackageValue::Scalar(None))
+        }
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        self.number(v)
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        self.number(v)
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Self::Value, E> {
+        self.number(v)
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(PackageValue::Scalar(Some(v.to_string())))
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(PackageValue::Scalar(Some(v)))
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        self.visit_bool(false)
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        self.visit_bool(false)
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        if !self.arch_specific {
+            return Err(M::Error::custom(format!(
+                "package `{}`: versions per architecture can not be nested",
+                self.key
+            )));
+        }
+        let mut default = None;
+        let mut versions = ArchVersions::new();
+        while let Some(arch) = map.next_key::<String>()? {
+            let key = format!("{}.{}", self.key, arch);
+            let v = map.next_value_seed(PackageValueSeed {
+                key: &key,
+                arch_specific: false,
+            })?;
+            if let PackageValue::Scalar(v) = v {
+                if arch == "default" {
+                    default = Some(v);
+                } else {
+                    versions.insert(arch, v);
+                }
+            }
+        }
+        Ok(PackageValue::ArchSpecific(default, versions))
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl Packages {
+    /// Deserialize packages from a TOML table, accepting unquoted numbers as versions
+    ///
+    /// This is meant for migrating existing manifests. Numbers are taken as written, so `1.10`
+    /// becomes `"1.10"` rather than `"1.1"`.
+    pub fn from_toml_lenient(s: &str) -> Result<Self, toml::de::Error> {
+        let Ok(document) = toml_edit::ImDocument::parse(s) else {
+            return toml::from_str(s);
+        };
+        let mut values = Vec::new();
+        for (_, item) in document.iter() {
+            match item {
+                Item::Value(Value::InlineTable(table)) => {
+                    values.extend(table.iter().map(|(_, v)| v))
+                }
+                Item::Value(value) => values.push(value),
+                Item::Table(table) => values.extend(table.iter().filter_map(|(_, i)| i.as_value())),
+                _ => (),
+            }
+        }
+        let mut spans: Vec<_> = values
+            .into_iter()
+            .filter(|v| v.is_integer() || v.is_float())
+            .filter_map(Value::span)
+            .collect();
+        spans.sort_by_key(|span| span.start);
+        let mut quoted = s.to_string();
+        for span in spans.into_iter().rev() {
+            quoted.insert(span.end, '"');
+            quoted.insert(span.start, '"');
+        }
+        toml::from_str(&quoted)
+    }
 }
 
 impl<'de> Deserialize<'de> for Packages {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
-        D: serde::Deserializer<'de>,
+        D: Deserializer<'de>,
     {
         struct PackagesVisitor {
             marker: PhantomData<fn() -> Packages>,
@@ -37,24 +199,33 @@ impl<'de> Deserialize<'de> for Packages {
             {
                 // False positive, the hash function won't read the mutable fields
                 #[allow(clippy::mutable_key_type)]
-                let mut inner = BTreeMap::new();
-                while let Some((k, v)) = map.next_entry::<String, PackageVersion>()? {
-                    inner.insert(
-                        k,
-                        match v {
-                            PackageVersion::Bool(false) => None,
-                            PackageVersion::Bool(true) => {
-                                return Err(Error::invalid_value(
-                                    Unexpected::Bool(false),
-                                    &"false or a string",
-                                ))
+                let mut inner = PackageMap::new();
+                let mut arch_specific = BTreeMap::new();
+                while let Some(k) = map.next_key::<String>()? {
+                    let v = map.next_value_seed(PackageValueSeed {
+                        key: &k,
+                        arch_specific: true,
+                    })?;
+                    match v {
+                        PackageValue::Scalar(v) => {
+                            inner.insert(k, v);
+                        }
+                        // Only a version for other architectures is a single version
+                        PackageValue::ArchSpecific(Some(default), v) if v.is_empty() => {
+                            inner.insert(k, default);
+                        }
+                        PackageValue::ArchSpecific(default, v) => {
+                            if let Some(default) = default {
+                                inner.insert(k.clone(), default);
                             }
-                            PackageVersion::Ver(ver) => Some(ver),
-                            PackageVersion::OptionVer(ver) => ver,
-                        },
-                    );
+                            arch_specific.insert(k, v);
+                        }
+                    }
                 }
-                Ok(Self::Value { inner })
+                Ok(Self::Value {
+                    inner,
+                    arch_specific,
+                })
             }
         }
 
@@ -68,7 +239,14 @@ impl<'de> Deserialize<'de> for Packages {
 mod test {
     use eyre::Result;
 
-    use super::Packages;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use proptest::collection::btree_map;
+    use proptest::option;
+    use proptest::prelude::*;
+
+    use super::{ArchVersions, Packages};
+    use crate::packages::PackageVersion;
 
     #[test]
     fn test_de() -> Result<()> {
@@ -85,6 +263,448 @@ mod test {
         assert_eq!(converted.as_ref()["konsole"], Some("23.04.1-1".to_string()));
         assert_eq!(converted.as_ref()["dolphin"], Some("23.04.1".to_string()));
         assert_eq!(converted.as_ref()["pykde"], None);
+
+        assert_eq!(converted.len(), 3);
+        assert!(converted.contains("konsole"));
+        assert!(!converted.contains("kate"));
+        assert_eq!(converted.get("pykde"), Some(&None));
+        assert_eq!(converted.get("kate"), None);
+        #[cfg(not(feature = "preserve-order"))]
+        assert_eq!(
+            converted.iter().collect::<Vec<_>>(),
+            [
+                ("dolphin", Some("23.04.1")),
+                ("konsole", Some("23.04.1-1")),
+                ("pykde", None)
+            ]
+        );
+        #[cfg(not(feature = "preserve-order"))]
+        assert_eq!(
+            converted.updated().collect::<Vec<_>>(),
+            [("dolphin", "23.04.1"), ("konsole", "23.04.1-1")]
+        );
+        #[cfg(feature = "preserve-order")]
+        assert_eq!(
+            converted.updated().collect::<Vec<_>>(),
+            [("konsole", "23.04.1-1"), ("dolphin", "23.04.1")]
+        );
+        assert_eq!(converted.removed().collect::<Vec<_>>(), ["pykde"]);
+
+        let mut modified = converted.clone();
+        assert_eq!(modified.insert("kate".into(), Some("23.04.1".into())), None);
+        assert_eq!(
+            modified.insert("konsole".into(), None),
+            Some(Some("23.04.1-1".into()))
+        );
+        assert_eq!(modified.remove("pykde"), Some(None));
+        assert_eq!(modified.remove("pykde"), None);
+        assert_eq!(modified.removed().collect::<Vec<_>>(), ["konsole"]);
+        assert_eq!(modified.len(), 3);
+
+        let parsed = converted.parsed()?;
+        assert_eq!(parsed["konsole"].as_ref().unwrap().revision(), Some("1"));
+        assert_eq!(parsed["pykde"], None);
+
+        let versions = converted.parsed_versions()?;
+        assert_eq!(versions.len(), 3);
+        assert!(versions["konsole"] > versions["dolphin"]);
+        assert_eq!(
+            versions["dolphin"],
+            Some(PackageVersion::parse("0:23.04.1")?)
+        );
+        assert_eq!(versions["pykde"], None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hand_built() -> Result<()> {
+        let example = r#"
+        dolphin = "23.04.1"
+        konsole = "23.04.1-1"
+        pykde = false
+        "#;
+        let parsed = toml::from_str::<Packages>(example)?;
+
+        let mut built = Packages::new();
+        assert!(built.is_empty());
+        built.insert("dolphin".into(), Some("23.04.1".into()));
+        built.insert("konsole".into(), Some("23.04.1-1".into()));
+        built.insert("pykde".into(), None);
+        assert_eq!(built, parsed);
+        assert_eq!(
+            serde_json::to_string(&built)?,
+            serde_json::to_string(&parsed)?
+        );
+        assert_eq!(
+            serde_json::to_string(&built)?,
+            r#"{"dolphin":"23.04.1","konsole":"23.04.1-1","pykde":null}"#
+        );
+
+        let collected: Packages = [
+            ("dolphin".to_string(), Some("23.04.1".to_string())),
+            ("konsole".to_string(), Some("23.04.1-1".to_string())),
+            ("pykde".to_string(), None),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            serde_json::to_string(&collected)?,
+            serde_json::to_string(&parsed)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_eq() -> Result<()> {
+        let a = toml::from_str::<Packages>(
+            r#"
+            konsole = "23.04.1-1"
+            pykde = false
+            "#,
+        )?;
+        // Equality does not depend on the order packages are written in
+        let b = toml::from_str::<Packages>(
+            r#"
+            pykde = false
+            konsole = "23.04.1-1"
+            "#,
+        )?;
+        assert_eq!(a, b);
+        assert_ne!(a, toml::from_str::<Packages>(r#"konsole = "23.04.1-1""#)?);
+        assert_ne!(
+            a,
+            toml::from_str::<Packages>(
+                r#"
+                konsole = "23.04.1-1"
+                pykde = "5.0"
+                "#
+            )?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parsed_invalid() -> Result<()> {
+        let converted = toml::from_str::<Packages>(r#"konsole = "23.04 1""#)?;
+        let err = converted.parsed().unwrap_err();
+        assert_eq!(err.get_package(), Some("konsole"));
+        assert_eq!(err.get_version(), "23.04 1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_downgrades() -> Result<()> {
+        let base = toml::from_str::<Packages>(
+            r#"
+            konsole = "23.04.1-1"
+            dolphin = "23.04.1"
+            kate = "23.04.1"
+            pykde = "5.0"
+            "#,
+        )?;
+        let new = toml::from_str::<Packages>(
+            r#"
+            dolphin = "1:22.12.0"
+            kate = "23.04.1~rc1"
+            konsole = "23.04.1"
+            okular = "1.0"
+            pykde = false
+            "#,
+        )?;
+        assert_eq!(new.downgrades_relative_to(&base), ["kate", "konsole"]);
+        assert!(base.downgrades_relative_to(&base).is_empty());
+
+        // Versions per architecture are compared with those for the same architecture
+        let base = toml::from_str::<Packages>(
+            r#"
+            dolphin = { default = "23.04.1", arm64 = "23.04.2" }
+            kate = "23.04.1"
+            konsole = { amd64 = "23.04.1" }
+            "#,
+        )?;
+        let new = toml::from_str::<Packages>(
+            r#"
+            dolphin = "23.04.1"
+            kate = { default = "23.04.2", riscv64 = "23.04.0" }
+            konsole = { amd64 = "23.04.2", arm64 = "23.04.0" }
+            "#,
+        )?;
+        assert_eq!(new.downgrades_relative_to(&base), ["dolphin", "kate"]);
+        assert!(base.downgrades_relative_to(&new).contains(&"konsole"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_de_invalid() {
+        let err = toml::from_str::<Packages>("pykde = true").unwrap_err();
+        assert!(err.to_string().contains(
+            "package `pykde`: `true` is not a valid value; use a version string or `false` for removal"
+        ));
+        let err = toml::from_str::<Packages>("konsole = 23").unwrap_err();
+        assert!(err.to_string().contains(
+            "package `konsole`: `23` is a number, not a version string; quote it as `konsole = \"23\"`"
+        ));
+        let err = toml::from_str::<Packages>("dolphin = 1.5").unwrap_err();
+        assert!(err.to_string().contains(
+            "package `dolphin`: `1.5` is a number, not a version string; quote it as `dolphin = \"1.5\"`"
+        ));
+        let err = toml::from_str::<Packages>("kate = [\"1.0\"]").unwrap_err();
+        assert!(err.to_string().contains(
+            "expected a version string, a table of versions per architecture or `false` for package `kate`"
+        ));
+        let err = toml::from_str::<Packages>("kate = { amd64 = true }").unwrap_err();
+        assert!(err.to_string().contains(
+            "package `kate.amd64`: `true` is not a valid value; use a version string or `false` for removal"
+        ));
+        let err = toml::from_str::<Packages>("kate = { amd64 = { a = \"1\" } }").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("package `kate.amd64`: versions per architecture can not be nested"));
+
+        let converted: Packages =
+            serde_json::from_str(r#"{"konsole": "23.04.1-1", "pykde": null, "kate": false}"#)
+                .unwrap();
+        assert_eq!(
+            converted.get("konsole"),
+            Some(&Some("23.04.1-1".to_string()))
+        );
+        assert_eq!(converted.get("pykde"), Some(&None));
+        assert_eq!(converted.get("kate"), Some(&None));
+    }
+
+    #[test]
+    fn test_de_arch_specific() -> Result<()> {
+        let converted = toml::from_str::<Packages>(
+            r#"
+            konsole = { amd64 = "23.04.1-1", arm64 = "23.04.1-2" }
+            dolphin = "23.04.1"
+            pykde = false
+            "#,
+        )?;
+        assert_eq!(converted.len(), 3);
+        assert!(converted.contains("konsole"));
+        assert_eq!(converted.get("konsole"), None);
+        assert_eq!(
+            converted.get_arch_specific("konsole"),
+            Some(&ArchVersions::from([
+                ("amd64".to_string(), Some("23.04.1-1".to_string())),
+                ("arm64".to_string(), Some("23.04.1-2".to_string())),
+            ]))
+        );
+        assert_eq!(
+            converted.version_for_arch("konsole", "arm64"),
+            Some(Some("23.04.1-2"))
+        );
+        assert_eq!(converted.version_for_arch("konsole", "riscv64"), None);
+        assert_eq!(
+            converted.version_for_arch("dolphin", "riscv64"),
+            Some(Some("23.04.1"))
+        );
+        assert_eq!(converted.version_for_arch("pykde", "amd64"), Some(None));
+        let arm64 = converted.for_arch("arm64");
+        assert_eq!(arm64.get("konsole"), Some(&Some("23.04.1-2".to_string())));
+        assert_eq!(arm64.get("pykde"), Some(&None));
+        assert_eq!(arm64.arch_specific().count(), 0);
+        assert!(!converted.for_arch("riscv64").contains("konsole"));
+        assert_eq!(converted.for_arch("riscv64").len(), 2);
+        assert_eq!(
+            converted
+                .arch_specific()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            ["konsole"]
+        );
+
+        let json = serde_json::to_string(&converted)?;
+        assert!(json.contains(r#""konsole":{"amd64":"23.04.1-1","arm64":"23.04.1-2"}"#));
+        assert_eq!(serde_json::from_str::<Packages>(&json)?, converted);
+
+        let mut modified = converted.clone();
+        modified.insert("konsole".into(), Some("23.04.2".into()));
+        assert_eq!(modified.get_arch_specific("konsole"), None);
+        assert_eq!(modified.len(), 3);
+        modified.remove("konsole");
+        assert!(!modified.contains("konsole"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_de_arch_default() -> Result<()> {
+        let converted = toml::from_str::<Packages>(
+            r#"
+            konsole = { default = "23.04.1-1", arm64 = "23.04.1-2" }
+            dolphin = { default = false, arm64 = "23.04.1" }
+            pykde = { amd64 = false }
+            kate = { default = "23.04.1" }
+            "#,
+        )?;
+        assert_eq!(converted.len(), 4);
+        assert_eq!(converted.names().count(), 4);
+        assert_eq!(
+            converted.get("konsole"),
+            Some(&Some("23.04.1-1".to_string()))
+        );
+        assert_eq!(
+            converted.version_for_arch("konsole", "arm64"),
+            Some(Some("23.04.1-2"))
+        );
+        assert_eq!(
+            converted.version_for_arch("konsole", "amd64"),
+            Some(Some("23.04.1-1"))
+        );
+        assert_eq!(converted.version_for_arch("dolphin", "amd64"), Some(None));
+        assert_eq!(converted.version_for_arch("pykde", "amd64"), Some(None));
+        assert_eq!(converted.version_for_arch("pykde", "arm64"), None);
+        // Only a version for other architectures is a single version
+        assert_eq!(converted.get("kate"), Some(&Some("23.04.1".to_string())));
+        assert_eq!(converted.get_arch_specific("kate"), None);
+        assert_eq!(converted.for_arch("amd64").len(), 4);
+        assert_eq!(converted.for_arch("riscv64").len(), 3);
+        assert_eq!(
+            converted.updated().collect::<BTreeSet<_>>(),
+            BTreeSet::from([
+                ("dolphin", "23.04.1"),
+                ("kate", "23.04.1"),
+                ("konsole", "23.04.1-1"),
+                ("konsole", "23.04.1-2"),
+            ])
+        );
+        assert_eq!(
+            converted.removed().collect::<BTreeSet<_>>(),
+            BTreeSet::from(["dolphin", "pykde"])
+        );
+
+        let json = serde_json::to_string(&converted)?;
+        assert!(json.contains(r#""dolphin":{"default":null,"arm64":"23.04.1"}"#));
+        assert!(json.contains(r#""kate":"23.04.1""#));
+        assert_eq!(serde_json::from_str::<Packages>(&json)?, converted);
+        let toml = toml::to_string(&converted.to_toml_value())?;
+        assert_eq!(toml::from_str::<Packages>(&toml)?, converted);
+
+        let mut modified = converted.clone();
+        modified.insert_for_arch("kate".into(), "arm64".into(), None);
+        assert_eq!(modified.version_for_arch("kate", "arm64"), Some(None));
+        assert_eq!(
+            modified.version_for_arch("kate", "amd64"),
+            Some(Some("23.04.1"))
+        );
+        assert_eq!(modified.len(), 4);
+
+        assert!(toml::from_str::<Packages>("konsole = { default = { amd64 = \"1\" } }").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_de_lenient() -> Result<()> {
+        let converted = Packages::from_toml_lenient(
+            r#"
+            konsole = 23
+            dolphin = 1.5
+            kate = "23.04.1"
+            pykde = false
+            okular = 1.10
+            plasma = { amd64 = 5.20, arm64 = "5.27" }
+            [qt]
+            default = 5.10
+            "#,
+        )?;
+        assert_eq!(converted.get("konsole"), Some(&Some("23".to_string())));
+        assert_eq!(converted.get("dolphin"), Some(&Some("1.5".to_string())));
+        // Versions are taken as written, not as the value of the number
+        assert_eq!(converted.get("okular"), Some(&Some("1.10".to_string())));
+        assert_eq!(
+            converted.version_for_arch("plasma", "amd64"),
+            Some(Some("5.20"))
+        );
+        assert_eq!(converted.get("qt"), Some(&Some("5.10".to_string())));
+        assert_eq!(converted.get("kate"), Some(&Some("23.04.1".to_string())));
+        assert_eq!(converted.get("pykde"), Some(&None));
+        assert!(Packages::from_toml_lenient("pykde = true").is_err());
+        assert!(Packages::from_toml_lenient("pykde = ").is_err());
+        Ok(())
+    }
+
+    /// Package names, mostly like real ones, sometimes with characters that need escaping
+    fn package_name() -> impl Strategy<Value = String> {
+        prop_oneof![
+            3 => "[a-z0-9][a-z0-9+.-]{0,30}",
+            1 => r#"(?s)[a-zA-Z0-9 .+~:_"'\\=#\[}\t\né包🦀-]{0,12}"#,
+        ]
+    }
+
+    /// Versions with optional epoch and release, sometimes arbitrary strings
+    fn version() -> impl Strategy<Value = String> {
+        prop_oneof![
+            3 => "([0-9]{1,2}:)?[0-9][0-9a-z.+~]{0,10}(-[0-9]{1,2})?",
+            1 => "(?s).{0,12}",
+        ]
+    }
+
+    /// Package maps where about a quarter of the packages are removed
+    fn package_map() -> impl Strategy<Value = BTreeMap<String, Option<String>>> {
+        btree_map(package_name(), option::weighted(0.75, version()), 0..16)
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip_json(map in package_map()) {
+            let packages: Packages = map.into_iter().collect();
+            let json = serde_json::to_string(&packages)?;
+            let from_json: Packages = serde_json::from_str(&json)?;
+            prop_assert_eq!(from_json, packages, "JSON: {}", json);
+        }
+
+        #[test]
+        fn test_roundtrip_toml(map in package_map()) {
+            let packages: Packages = map.clone().into_iter().collect();
+            // TOML has no null, so removed packages go through `false`
+            let value = packages.to_toml_value();
+            for (name, version) in &map {
+                match version {
+                    Some(v) => prop_assert_eq!(value[name.as_str()].as_str(), Some(v.as_str())),
+                    None => prop_assert_eq!(value[name.as_str()].as_bool(), Some(false)),
+                }
+            }
+            let toml = toml::to_string(&value)?;
+            let from_toml: Packages = toml::from_str(&toml)?;
+            prop_assert_eq!(from_toml.iter().count(), map.len());
+            for (name, version) in &map {
+                prop_assert_eq!(from_toml.get(name), Some(version));
+            }
+            prop_assert_eq!(from_toml, packages, "TOML: {}", toml);
+        }
+    }
+
+    #[cfg(feature = "preserve-order")]
+    #[test]
+    fn test_preserve_order() -> Result<()> {
+        let converted = toml::from_str::<Packages>(
+            r#"
+            konsole = "23.04.1-1"
+            dolphin = "23.04.1"
+            pykde = false
+            "#,
+        )?;
+        assert_eq!(
+            converted.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["konsole", "dolphin", "pykde"]
+        );
+        assert_eq!(
+            serde_json::to_string(&converted)?,
+            r#"{"konsole":"23.04.1-1","dolphin":"23.04.1","pykde":null}"#
+        );
+
+        // Packages with versions per architecture are written last
+        let converted = toml::from_str::<Packages>(
+            r#"
+            konsole = { amd64 = "23.04.1-1" }
+            dolphin = "23.04.1"
+            "#,
+        )?;
+        assert_eq!(
+            serde_json::to_string(&converted)?,
+            r#"{"dolphin":"23.04.1","konsole":{"amd64":"23.04.1-1"}}"#
+        );
         Ok(())
     }
 }
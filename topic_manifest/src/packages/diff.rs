@@ -0,0 +1,185 @@
+//! Differences between package collections
+
+use serde::Serialize;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::Packages;
+
+/// Differences between two versions of a package collection, sorted by package name
+///
+/// Packages with versions per architecture on either side are compared per architecture, and
+/// reported as `name:arch` for each architecture whose version changed. The plain name then
+/// stands for architectures without their own versions.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PackagesDiff {
+    /// Packages absent from the old collection, and their versions
+    pub added: BTreeMap<String, String>,
+    /// Packages newly marked for removal
+    pub removed: Vec<String>,
+    /// Packages whose versions changed, along with the old and new versions
+    pub updated: BTreeMap<String, (Option<String>, String)>,
+    /// Packages in the old collection no longer mentioned at all
+    pub deleted: Vec<String>,
+}
+
+impl PackagesDiff {
+    /// Are the collections identical
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.updated.is_empty()
+            && self.deleted.is_empty()
+    }
+}
+
+impl Packages {
+    /// Compare this collection against an older version of it
+    pub fn diff_against(&self, old: &Packages) -> PackagesDiff {
+        let mut diff = PackagesDiff::default();
+        for name in self.names() {
+            let archs: BTreeSet<&str> = [self, old]
+                .into_iter()
+                .filter_map(|packages| packages.get_arch_specific(name))
+                .flat_map(|versions| versions.keys().map(String::as_str))
+                .collect();
+            let single = (
+                name.to_string(),
+                old.get(name).map(Option::as_deref),
+                self.get(name).map(Option::as_deref),
+            );
+            let per_arch = archs.into_iter().map(|arch| {
+                (
+                    format!("{}:{}", name, arch),
+                    old.version_for_arch(name, arch),
+                    self.version_for_arch(name, arch),
+                )
+            });
+            for (label, old_version, version) in std::iter::once(single).chain(per_arch) {
+                match (old_version, version) {
+                    (None, Some(Some(v))) => {
+                        diff.added.insert(label, v.to_string());
+                    }
+                    (Some(old_v), Some(Some(v))) if old_v != Some(v) => {
+                        diff.updated
+                            .insert(label, (old_v.map(str::to_string), v.to_string()));
+                    }
+                    (None | Some(Some(_)), Some(None)) => diff.removed.push(label),
+                    (Some(_), None) => diff.deleted.push(label),
+                    _ => (),
+                }
+            }
+        }
+        diff.deleted.extend(
+            old.names()
+                .filter(|name| !self.contains(name))
+                .map(str::to_string),
+        );
+        diff.removed.sort_unstable();
+        diff.deleted.sort_unstable();
+        diff
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::PackagesDiff;
+    use crate::test_util::packages;
+
+    #[test]
+    fn test_diff() {
+        let old = packages(
+            r#"
+            konsole = "23.04.1-1"
+            dolphin = "23.04.1"
+            pykde = "5.0"
+            kdelibs = false
+            kate = "23.04.1"
+            plasma = { amd64 = "5.27.5" }
+            "#,
+        );
+        let new = packages(
+            r#"
+            konsole = "23.04.2"
+            dolphin = "23.04.1"
+            pykde = false
+            kdelibs = "4.14.38"
+            okular = "23.04.1"
+            qt5 = false
+            "#,
+        );
+
+        assert_eq!(
+            new.diff_against(&old),
+            PackagesDiff {
+                added: BTreeMap::from([("okular".to_string(), "23.04.1".to_string())]),
+                removed: vec!["pykde".to_string(), "qt5".to_string()],
+                updated: BTreeMap::from([
+                    ("kdelibs".to_string(), (None, "4.14.38".to_string())),
+                    (
+                        "konsole".to_string(),
+                        (Some("23.04.1-1".to_string()), "23.04.2".to_string())
+                    ),
+                ]),
+                deleted: vec!["kate".to_string(), "plasma".to_string()],
+            }
+        );
+        assert!(new.diff_against(&new).is_empty());
+    }
+
+    #[test]
+    fn test_diff_arch_specific() {
+        let old = packages(
+            r#"
+            konsole = { amd64 = "23.04.1-1", arm64 = "23.04.1-2" }
+            dolphin = "23.04.1"
+            kate = { default = "23.04.1", riscv64 = "23.04.0" }
+            okular = { amd64 = "23.04.1" }
+            "#,
+        );
+        let new = packages(
+            r#"
+            konsole = "23.04.2"
+            dolphin = { default = "23.04.1", arm64 = "23.04.2" }
+            kate = { default = "23.04.1", riscv64 = false }
+            okular = { amd64 = "23.04.1", arm64 = "23.04.1" }
+            plasma = { amd64 = "5.27.5" }
+            "#,
+        );
+
+        let diff = new.diff_against(&old);
+        assert_eq!(
+            diff.added,
+            BTreeMap::from([
+                ("konsole".to_string(), "23.04.2".to_string()),
+                ("okular:arm64".to_string(), "23.04.1".to_string()),
+                ("plasma:amd64".to_string(), "5.27.5".to_string()),
+            ])
+        );
+        assert_eq!(
+            diff.updated,
+            BTreeMap::from([
+                (
+                    "dolphin:arm64".to_string(),
+                    (Some("23.04.1".to_string()), "23.04.2".to_string())
+                ),
+                (
+                    "konsole:amd64".to_string(),
+                    (Some("23.04.1-1".to_string()), "23.04.2".to_string())
+                ),
+                (
+                    "konsole:arm64".to_string(),
+                    (Some("23.04.1-2".to_string()), "23.04.2".to_string())
+                ),
+            ])
+        );
+        assert_eq!(diff.removed, ["kate:riscv64"]);
+        assert!(diff.deleted.is_empty());
+
+        let diff = old.diff_against(&new);
+        assert_eq!(diff.deleted, ["konsole", "okular:arm64", "plasma"]);
+        assert!(new.diff_against(&new).is_empty());
+    }
+}
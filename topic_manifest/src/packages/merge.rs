@@ -0,0 +1,248 @@
+//! Merging package collections
+
+use std::error::Error;
+use std::fmt;
+
+use super::{ArchVersions, Packages};
+
+/// How to resolve packages present in both collections with different versions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageMergePolicy {
+    /// Versions from the other collection take priority
+    OtherWins,
+    /// Versions from this collection take priority
+    SelfWins,
+    /// Fail on any disagreement, removal and update of the same package included
+    Strict,
+}
+
+/// Statistics of a successful merge
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PackageMergeStats {
+    /// Number of packages added from the other collection
+    pub added: usize,
+    /// Number of packages whose versions were replaced by the other collection
+    pub overridden: usize,
+}
+
+/// Error returned by a strict merge when versions disagree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageMergeConflict {
+    packages: Vec<String>,
+}
+
+impl Packages {
+    /// Merge packages from `other` into this collection
+    ///
+    /// With [`PackageMergePolicy::Strict`], this collection is left untouched if any conflict is
+    /// found.
+    pub fn merge(
+        &mut self,
+        other: &Packages,
+        policy: PackageMergePolicy,
+    ) -> Result<PackageMergeStats, PackageMergeConflict> {
+        // Packages disagree unless both their single versions and versions per architecture agree
+        let mut conflicts: Vec<String> = other
+            .names()
+            .filter(|k| self.contains(k) && self.entry(k) != other.entry(k))
+            .map(str::to_string)
+            .collect();
+        conflicts.sort_unstable();
+
+        if policy == PackageMergePolicy::Strict && !conflicts.is_empty() {
+            return Err(PackageMergeConflict {
+                packages: conflicts,
+            });
+        }
+
+        let mut stats = PackageMergeStats::default();
+        if policy == PackageMergePolicy::OtherWins {
+            for k in conflicts {
+                self.remove(&k);
+                self.insert_entry_from(other, k);
+                stats.overridden += 1;
+            }
+        }
+        for k in other.names() {
+            if !self.contains(k) {
+                self.insert_entry_from(other, k.to_string());
+                stats.added += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Get both the single version and the versions per architecture of a package
+    fn entry(&self, name: &str) -> (Option<&Option<String>>, Option<&ArchVersions>) {
+        (self.inner.get(name), self.arch_specific.get(name))
+    }
+
+    /// Copy both the single version and the versions per architecture of a package from `other`
+    fn insert_entry_from(&mut self, other: &Packages, name: String) {
+        let (version, versions) = other.entry(&name);
+        if let Some(versions) = versions {
+            self.arch_specific.insert(name.clone(), versions.clone());
+        }
+        if let Some(version) = version {
+            self.inner.insert(name, version.clone());
+        }
+    }
+}
+
+impl PackageMergeConflict {
+    /// Packages with conflicting versions
+    pub fn get_packages(&self) -> &[String] {
+        &self.packages
+    }
+}
+
+impl fmt::Display for PackageMergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Conflicting versions for packages: {}",
+            self.packages.join(", ")
+        )
+    }
+}
+
+impl Error for PackageMergeConflict {}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+
+    use super::{PackageMergePolicy, PackageMergeStats};
+    use crate::test_util::packages;
+
+    #[test]
+    fn test_merge() -> Result<()> {
+        let base = packages(
+            r#"
+            konsole = "23.04.1-1"
+            dolphin = "23.04.1"
+            pykde = false
+            "#,
+        );
+        let other = packages(
+            r#"
+            konsole = "23.04.2"
+            dolphin = "23.04.1"
+            pykde = "5.0"
+            kate = "23.04.1"
+            "#,
+        );
+
+        let mut merged = base.clone();
+        let stats = merged.merge(&other, PackageMergePolicy::OtherWins)?;
+        assert_eq!(
+            stats,
+            PackageMergeStats {
+                added: 1,
+                overridden: 2
+            }
+        );
+        assert_eq!(merged.get("konsole"), Some(&Some("23.04.2".into())));
+        assert_eq!(merged.get("pykde"), Some(&Some("5.0".into())));
+
+        let mut merged = base.clone();
+        let stats = merged.merge(&other, PackageMergePolicy::SelfWins)?;
+        assert_eq!(
+            stats,
+            PackageMergeStats {
+                added: 1,
+                overridden: 0
+            }
+        );
+        assert_eq!(merged.get("konsole"), Some(&Some("23.04.1-1".into())));
+        assert_eq!(merged.get("pykde"), Some(&None));
+        assert_eq!(merged.get("kate"), Some(&Some("23.04.1".into())));
+
+        let mut merged = base.clone();
+        let err = merged
+            .merge(&other, PackageMergePolicy::Strict)
+            .unwrap_err();
+        assert_eq!(err.get_packages(), ["konsole", "pykde"]);
+        assert_eq!(merged, base);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_arch_specific() -> Result<()> {
+        let base = packages(
+            r#"
+            konsole = { amd64 = "23.04.1-1", arm64 = "23.04.1-2" }
+            dolphin = "23.04.1"
+            "#,
+        );
+        let other = packages(
+            r#"
+            konsole = "23.04.2"
+            dolphin = { amd64 = "23.04.1" }
+            kate = { amd64 = "23.04.1" }
+            "#,
+        );
+
+        let mut merged = base.clone();
+        let stats = merged.merge(&other, PackageMergePolicy::OtherWins)?;
+        assert_eq!(
+            stats,
+            PackageMergeStats {
+                added: 1,
+                overridden: 2
+            }
+        );
+        assert_eq!(merged, other);
+
+        let mut merged = base.clone();
+        let stats = merged.merge(&other, PackageMergePolicy::SelfWins)?;
+        assert_eq!(
+            stats,
+            PackageMergeStats {
+                added: 1,
+                overridden: 0
+            }
+        );
+        assert_eq!(merged.get("dolphin"), Some(&Some("23.04.1".into())));
+        assert!(merged.get_arch_specific("konsole").is_some());
+
+        let mut merged = base.clone();
+        let err = merged
+            .merge(&other, PackageMergePolicy::Strict)
+            .unwrap_err();
+        assert_eq!(err.get_packages(), ["dolphin", "konsole"]);
+        assert_eq!(merged, base);
+        assert!(merged
+            .clone()
+            .merge(&base, PackageMergePolicy::Strict)
+            .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict_never_changes_values() {
+        let values = [None, Some("1.0"), Some("1.0-1"), Some("2.0")];
+        for a in values {
+            for b in values {
+                let to_toml = |v: Option<&str>| match v {
+                    Some(v) => format!("pkg = {:?}", v),
+                    None => "pkg = false".to_string(),
+                };
+                let mut this = packages(&to_toml(a));
+                let other = packages(&to_toml(b));
+                let orig = this.clone();
+                match this.merge(&other, PackageMergePolicy::Strict) {
+                    Ok(stats) => {
+                        assert_eq!(a, b);
+                        assert_eq!(stats, PackageMergeStats::default());
+                    }
+                    Err(e) => {
+                        assert_ne!(a, b);
+                        assert_eq!(e.get_packages(), ["pkg"]);
+                    }
+                }
+                assert_eq!(this, orig);
+            }
+        }
+    }
+}
@@ -1,20 +1,370 @@
 //! Collection of package names and versions
+//!
+//! Versions are stored as raw strings. Use [`Packages::parsed`] to get [`PackageVersion`]s, which
+//! are ordered with the dpkg algorithm and can be compared against installed versions:
+//!
+//! ```rust
+//! use topic_manifest::packages::{PackageVersion, Packages};
+//!
+//! let packages: Packages = toml::from_str(r#"
+//! konsole = "23.04.1-1"
+//! pykde = false
+//! "#).unwrap();
+//! let installed = PackageVersion::parse("23.04.0-2").unwrap();
+//!
+//! let parsed = packages.parsed().unwrap();
+//! assert!(parsed["konsole"].as_ref().unwrap() > &installed);
+//! assert_eq!(parsed["pykde"], None);
+//! ```
 
 mod de;
+mod diff;
+mod merge;
+mod name;
+#[cfg(feature = "schemars")]
+mod schema;
+mod version;
 
-use serde::Serialize;
+use serde::ser::{Serialize, SerializeMap, Serializer};
 
 use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+pub use diff::PackagesDiff;
+pub use merge::{PackageMergeConflict, PackageMergePolicy, PackageMergeStats};
+pub use name::{check_package_name, InvalidPackageName};
+pub use version::{PackageVersion, ParsedVersion, VersionParseError};
+
+/// Map backing [`Packages`], sorted by package name
+#[cfg(not(feature = "preserve-order"))]
+pub type PackageMap = BTreeMap<String, Option<String>>;
+
+/// Map backing [`Packages`], in the order packages are inserted or written
+///
+/// Only single versions are kept in this map, so packages with versions per architecture are
+/// serialized after the others, sorted by name.
+#[cfg(feature = "preserve-order")]
+pub type PackageMap = indexmap::IndexMap<String, Option<String>>;
+
+/// Versions of a package per architecture, keyed by architecture names
+///
+/// `None` stands for removal of the package on that architecture.
+pub type ArchVersions = BTreeMap<String, Option<String>>;
 
 /// Collection of package names and versions
-#[derive(Clone, Debug, Serialize)]
+///
+/// A package either has a single version for all architectures (or `None` for removal), or
+/// different versions per architecture, written as `konsole = { amd64 = "23.04.1-1" }`, or both,
+/// where the single version applies to architectures without their own versions, written as
+/// `konsole = { default = "23.04.1-1", arm64 = "23.04.1-2" }`. Methods dealing with
+/// `Option<String>` versions only see single versions, see [`Packages::get_arch_specific`] and
+/// [`Packages::version_for_arch`] for versions per architecture.
+///
+/// With the `preserve-order` feature, packages with versions per architecture are always
+/// serialized after the others, regardless of where they were written, see [`PackageMap`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Packages {
-    #[serde(flatten)]
-    inner: BTreeMap<String, Option<String>>,
+    inner: PackageMap,
+    arch_specific: BTreeMap<String, ArchVersions>,
+}
+
+impl Packages {
+    /// Create an empty collection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of packages
+    pub fn len(&self) -> usize {
+        self.names().count()
+    }
+
+    /// Is the collection empty
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty() && self.arch_specific.is_empty()
+    }
+
+    /// Is this package a part of the collection
+    pub fn contains(&self, name: &str) -> bool {
+        self.inner.contains_key(name) || self.arch_specific.contains_key(name)
+    }
+
+    /// Get the version of a package, `Some(None)` stands for removal of the package
+    ///
+    /// For packages with versions per architecture, this is the version for other architectures,
+    /// `None` if there is none.
+    pub fn get(&self, name: &str) -> Option<&Option<String>> {
+        self.inner.get(name)
+    }
+
+    /// Get versions per architecture of a package
+    pub fn get_arch_specific(&self, name: &str) -> Option<&ArchVersions> {
+        self.arch_specific.get(name)
+    }
+
+    /// Get the version of a package for an architecture, `Some(None)` stands for removal
+    ///
+    /// Returns `None` if the package is not a part of the collection, or has versions per
+    /// architecture but neither for `arch` nor for other architectures.
+    pub fn version_for_arch(&self, name: &str, arch: &str) -> Option<Option<&str>> {
+        match self.arch_specific.get(name).and_then(|v| v.get(arch)) {
+            Some(ver) => Some(ver.as_deref()),
+            None => Some(self.inner.get(name)?.as_deref()),
+        }
+    }
+
+    /// Get the packages and versions applicable to `arch`
+    ///
+    /// Packages with versions per architecture get the version for `arch`, or the version for
+    /// other architectures, or are left out if there is neither.
+    pub fn for_arch(&self, arch: &str) -> Packages {
+        let mut packages = Packages {
+            inner: self.inner.clone(),
+            arch_specific: BTreeMap::new(),
+        };
+        for (name, versions) in &self.arch_specific {
+            if let Some(version) = versions.get(arch) {
+                packages.inner.insert(name.clone(), version.clone());
+            }
+        }
+        packages
+    }
+
+    /// Set the version of a package, `None` stands for removal of the package
+    ///
+    /// Versions per architecture of the package are dropped. Returns the previous version of the
+    /// package, if any.
+    pub fn insert(&mut self, name: String, version: Option<String>) -> Option<Option<String>> {
+        self.arch_specific.remove(&name);
+        self.inner.insert(name, version)
+    }
+
+    /// Set versions per architecture of a package
+    ///
+    /// The single version of the package is dropped, see [`Packages::insert_for_arch`] to keep it
+    /// for other architectures. Returns the previous versions per architecture of the package, if
+    /// any.
+    pub fn insert_arch_specific(
+        &mut self,
+        name: String,
+        versions: ArchVersions,
+    ) -> Option<ArchVersions> {
+        self.remove_scalar(&name);
+        self.arch_specific.insert(name, versions)
+    }
+
+    /// Set the version of a package for one architecture, `None` stands for removal
+    ///
+    /// Other versions of the package are kept, so a single version still applies to other
+    /// architectures. Returns the previous version of the package for `arch`, if any.
+    pub fn insert_for_arch(
+        &mut self,
+        name: String,
+        arch: String,
+        version: Option<String>,
+    ) -> Option<Option<String>> {
+        self.arch_specific
+            .entry(name)
+            .or_default()
+            .insert(arch, version)
+    }
+
+    /// Remove a package from the collection
+    ///
+    /// Returns the removed version of the package, if any. Versions per architecture of the
+    /// package are removed as well.
+    pub fn remove(&mut self, name: &str) -> Option<Option<String>> {
+        self.arch_specific.remove(name);
+        self.remove_scalar(name)
+    }
+
+    fn remove_scalar(&mut self, name: &str) -> Option<Option<String>> {
+        #[cfg(not(feature = "preserve-order"))]
+        return self.inner.remove(name);
+        #[cfg(feature = "preserve-order")]
+        return self.inner.shift_remove(name);
+    }
+
+    /// Iterate over packages with versions per architecture, sorted by package name
+    pub fn arch_specific(&self) -> impl Iterator<Item = (&str, &ArchVersions)> {
+        self.arch_specific.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Iterate over names of all packages, each package once
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        let arch_only = self
+            .arch_specific
+            .keys()
+            .filter(|k| !self.inner.contains_key(*k));
+        self.inner.keys().chain(arch_only).map(String::as_str)
+    }
+
+    /// Iterate over package names and versions, in the order of [`PackageMap`]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.inner.iter().map(|(k, v)| (k.as_str(), v.as_deref()))
+    }
+
+    /// Iterate over updated packages and their new versions
+    ///
+    /// Single versions come first, then versions per architecture, so packages with several
+    /// versions are listed once for each of them.
+    pub fn updated(&self) -> impl Iterator<Item = (&str, &str)> {
+        let per_arch = self.arch_specific.iter().flat_map(|(name, versions)| {
+            versions
+                .values()
+                .flatten()
+                .map(move |v| (name.as_str(), v.as_str()))
+        });
+        self.inner
+            .iter()
+            .filter_map(|(k, v)| Some((k.as_str(), v.as_deref()?)))
+            .chain(per_arch)
+    }
+
+    /// Iterate over packages removed on all or some architectures, each package once
+    pub fn removed(&self) -> impl Iterator<Item = &str> {
+        self.names().filter(|name| {
+            self.inner.get(*name).is_some_and(Option::is_none)
+                || self
+                    .arch_specific
+                    .get(*name)
+                    .is_some_and(|versions| versions.values().any(Option::is_none))
+        })
+    }
+
+    /// Parse all single package versions, `None` stands for removal of the package
+    ///
+    /// Versions per architecture are not parsed, and packages only having such versions are left
+    /// out. Call [`Packages::for_arch`] first to parse the versions for one architecture.
+    pub fn parsed(&self) -> Result<BTreeMap<&str, Option<PackageVersion>>, VersionParseError> {
+        self.inner
+            .iter()
+            .map(|(name, ver)| {
+                let parsed = match ver {
+                    Some(v) => Some(PackageVersion::parse(v).map_err(|e| e.with_package(name))?),
+                    None => None,
+                };
+                Ok((name.as_str(), parsed))
+            })
+            .collect()
+    }
+
+    /// Same as [`Packages::parsed`], but keyed by owned package names
+    pub fn parsed_versions(
+        &self,
+    ) -> Result<BTreeMap<String, Option<ParsedVersion>>, VersionParseError> {
+        Ok(self
+            .parsed()?
+            .into_iter()
+            .map(|(name, version)| (name.to_string(), version))
+            .collect())
+    }
+
+    /// Get names of packages whose versions are lower than those in `base`
+    ///
+    /// Single versions are compared with each other, and versions per architecture with the
+    /// versions for the same architecture in `base`, see [`Packages::version_for_arch`]. A package
+    /// is a downgrade if any of these is lower. Packages missing from either side, removed
+    /// packages, and packages with invalid versions are not taken into account.
+    pub fn downgrades_relative_to(&self, base: &Packages) -> Vec<&str> {
+        let is_lower = |ver: Option<&str>, base_ver: Option<&str>| {
+            let (Some(ver), Some(base_ver)) = (ver, base_ver) else {
+                return false;
+            };
+            match (PackageVersion::parse(ver), PackageVersion::parse(base_ver)) {
+                (Ok(ver), Ok(base_ver)) => ver < base_ver,
+                _ => false,
+            }
+        };
+        self.names()
+            .filter(|name| {
+                let ver = self.inner.get(*name).and_then(Option::as_deref);
+                if is_lower(ver, base.inner.get(*name).and_then(Option::as_deref)) {
+                    return true;
+                }
+                [self, base]
+                    .into_iter()
+                    .filter_map(|packages| packages.arch_specific.get(*name))
+                    .flat_map(|versions| versions.keys())
+                    .any(|arch| {
+                        is_lower(
+                            self.version_for_arch(name, arch).flatten(),
+                            base.version_for_arch(name, arch).flatten(),
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+impl FromIterator<(String, Option<String>)> for Packages {
+    fn from_iter<I: IntoIterator<Item = (String, Option<String>)>>(iter: I) -> Self {
+        let mut packages = Self::new();
+        packages.extend(iter);
+        packages
+    }
+}
+
+impl Extend<(String, Option<String>)> for Packages {
+    fn extend<I: IntoIterator<Item = (String, Option<String>)>>(&mut self, iter: I) {
+        for (name, version) in iter {
+            self.insert(name, version);
+        }
+    }
+}
+
+/// Versions of a package with versions per architecture, as written in manifests
+struct ArchEntry<'a> {
+    /// Version for other architectures, under the `default` key
+    default: Option<&'a Option<String>>,
+    versions: &'a ArchVersions,
+}
+
+impl Serialize for ArchEntry<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = self.versions.len() + usize::from(self.default.is_some());
+        let mut map = serializer.serialize_map(Some(len))?;
+        if let Some(default) = self.default {
+            map.serialize_entry("default", default)?;
+        }
+        for (arch, version) in self.versions {
+            map.serialize_entry(arch, version)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Packages {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (name, version) in &self.inner {
+            if !self.arch_specific.contains_key(name) {
+                map.serialize_entry(name, version)?;
+            }
+        }
+        for (name, versions) in &self.arch_specific {
+            let default = self.inner.get(name);
+            map.serialize_entry(name, &ArchEntry { default, versions })?;
+        }
+        map.end()
+    }
 }
 
-impl AsRef<BTreeMap<String, Option<String>>> for Packages {
-    fn as_ref(&self) -> &BTreeMap<String, Option<String>> {
+impl AsRef<PackageMap> for Packages {
+    fn as_ref(&self) -> &PackageMap {
         &self.inner
     }
 }
+
+impl Hash for Packages {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hash in sorted order, so it agrees with `PartialEq` regardless of the backing map
+        self.inner.len().hash(state);
+        let mut entries: Vec<_> = self.inner.iter().collect();
+        entries.sort_unstable();
+        for entry in entries {
+            entry.hash(state);
+        }
+        self.arch_specific.hash(state);
+    }
+}
@@ -0,0 +1,102 @@
+//! Package name policy
+
+use std::error::Error;
+use std::fmt;
+
+use super::Packages;
+
+/// A package name violating the packaging policy
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidPackageName {
+    name: String,
+    reason: &'static str,
+}
+
+/// Check a package name against the packaging policy
+///
+/// Package names may only contain lowercase letters, digits, `+`, `-` and `.`, must be at least two
+/// characters long, and must start with a letter or a digit.
+pub fn check_package_name(name: &str) -> Result<(), InvalidPackageName> {
+    let err = |reason| InvalidPackageName {
+        name: name.to_string(),
+        reason,
+    };
+    if name.len() < 2 {
+        return Err(err("must be at least two characters long"));
+    }
+    if !name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit()) {
+        return Err(err("must start with a lowercase letter or a digit"));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c))
+    {
+        return Err(err(
+            "may only contain lowercase letters, digits, `+`, `-` and `.`",
+        ));
+    }
+    Ok(())
+}
+
+impl Packages {
+    /// Check names of all packages against the packaging policy
+    pub fn validate_names(&self) -> Vec<InvalidPackageName> {
+        self.names()
+            .filter_map(|name| check_package_name(name).err())
+            .collect()
+    }
+}
+
+impl InvalidPackageName {
+    /// The invalid package name
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for InvalidPackageName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid package name `{}`: {}", self.name, self.reason)
+    }
+}
+
+impl Error for InvalidPackageName {}
+
+#[cfg(test)]
+mod test {
+    use super::{check_package_name, Packages};
+
+    #[test]
+    fn test_check_package_name() {
+        for name in ["konsole", "gtk+3", "libqt5-core", "7zip", "python-3.12"] {
+            assert!(check_package_name(name).is_ok(), "{} should be valid", name);
+        }
+        for name in [
+            "", "k", "Konsole", "Konsole!", "-foo", ".foo", "foo_bar", "foo bar",
+        ] {
+            assert!(
+                check_package_name(name).is_err(),
+                "{} should be invalid",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_names() {
+        let packages: Packages = toml::from_str(
+            r#"
+            "" = false
+            "Konsole!" = "23.04.1"
+            konsole = "23.04.1-1"
+            "#,
+        )
+        .unwrap();
+        let invalid: Vec<_> = packages
+            .validate_names()
+            .iter()
+            .map(|e| e.get_name().to_string())
+            .collect();
+        assert_eq!(invalid, ["", "Konsole!"]);
+    }
+}
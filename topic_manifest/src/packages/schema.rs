@@ -0,0 +1,37 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+
+use std::borrow::Cow;
+
+use super::Packages;
+
+impl JsonSchema for Packages {
+    fn schema_name() -> Cow<'static, str> {
+        "Packages".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        // `false` is accepted as an alias of `null` while deserializing
+        json_schema!({
+            "description": "Package names and versions, `null` or `false` stands for removal of the package",
+            "type": "object",
+            "additionalProperties": {
+                "anyOf": [
+                    { "type": "string" },
+                    { "type": "null" },
+                    { "const": false },
+                    {
+                        "description": "Versions per architecture, and under `default` the version for other architectures",
+                        "type": "object",
+                        "additionalProperties": {
+                            "anyOf": [
+                                { "type": "string" },
+                                { "type": "null" },
+                                { "const": false },
+                            ],
+                        },
+                    },
+                ],
+            },
+        })
+    }
+}
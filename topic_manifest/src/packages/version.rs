@@ -0,0 +1,332 @@
+//! Structured package versions
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A dpkg-style package version in the form of `[epoch:]upstream[-revision]`
+///
+/// Versions are ordered with the dpkg comparison algorithm, so `1.0` and `0:1.0` compare equal.
+#[derive(Clone, Debug)]
+pub struct PackageVersion {
+    raw: String,
+    epoch: u32,
+    upstream: String,
+    revision: Option<String>,
+}
+
+/// Parsed version of a package, see [`Packages::parsed_versions`](super::Packages::parsed_versions)
+pub type ParsedVersion = PackageVersion;
+
+/// Error returned when a version string could not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionParseError {
+    package: Option<String>,
+    version: String,
+    reason: &'static str,
+}
+
+impl PackageVersion {
+    /// Parse a version string
+    pub fn parse<S: AsRef<str>>(version: S) -> Result<Self, VersionParseError> {
+        let raw = version.as_ref();
+        let err = |reason| VersionParseError {
+            package: None,
+            version: raw.to_string(),
+            reason,
+        };
+
+        let (epoch, rest) = match raw.split_once(':') {
+            Some((e, rest)) => {
+                if e.is_empty() || !e.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(err("epoch must be a number"));
+                }
+                (e.parse().map_err(|_| err("epoch is too large"))?, rest)
+            }
+            None => (0, raw),
+        };
+        let (upstream, revision) = match rest.rsplit_once('-') {
+            Some((u, r)) => (u, Some(r)),
+            None => (rest, None),
+        };
+
+        match upstream.chars().next() {
+            None => return Err(err("upstream version is empty")),
+            Some(c) if !c.is_ascii_alphanumeric() => {
+                return Err(err(
+                    "upstream version must start with an alphanumeric character",
+                ))
+            }
+            _ => (),
+        }
+        if !upstream
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".+~-".contains(c))
+        {
+            return Err(err("upstream version contains invalid characters"));
+        }
+        if let Some(r) = revision {
+            if r.is_empty() {
+                return Err(err("revision is empty"));
+            }
+            if !r
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ".+~".contains(c))
+            {
+                return Err(err("revision contains invalid characters"));
+            }
+        }
+
+        Ok(Self {
+            raw: raw.to_string(),
+            epoch,
+            upstream: upstream.to_string(),
+            revision: revision.map(|r| r.to_string()),
+        })
+    }
+
+    /// Get the epoch of the version, `0` if not specified
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    /// Get the upstream part of the version
+    pub fn upstream(&self) -> &str {
+        &self.upstream
+    }
+
+    /// Get the revision part of the version
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    /// Get the original version string
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+/// Sort weight of a character in the non-digit part of a version
+fn order(c: Option<u8>) -> i32 {
+    match c {
+        None => 0,
+        Some(b'~') => -1,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => c as i32,
+        Some(c) => c as i32 + 256,
+    }
+}
+
+/// Compare two version parts with the dpkg algorithm
+fn verrevcmp(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() || j < b.len() {
+        // Compare the non-digit prefix character by character
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let ac = order(a.get(i).copied());
+            let bc = order(b.get(j).copied());
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            i += 1;
+            j += 1;
+        }
+
+        // Compare the digit part numerically, ignoring leading zeros
+        while a.get(i) == Some(&b'0') {
+            i += 1;
+        }
+        while b.get(j) == Some(&b'0') {
+            j += 1;
+        }
+        let mut first_diff = Ordering::Equal;
+        while i < a.len() && a[i].is_ascii_digit() && j < b.len() && b[j].is_ascii_digit() {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if i < a.len() && a[i].is_ascii_digit() {
+            return Ordering::Greater;
+        }
+        if j < b.len() && b[j].is_ascii_digit() {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+
+    Ordering::Equal
+}
+
+impl Ord for PackageVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| verrevcmp(&self.upstream, &other.upstream))
+            .then_with(|| {
+                verrevcmp(
+                    self.revision.as_deref().unwrap_or(""),
+                    other.revision.as_deref().unwrap_or(""),
+                )
+            })
+    }
+}
+
+impl PartialOrd for PackageVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PackageVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PackageVersion {}
+
+impl FromStr for PackageVersion {
+    type Err = VersionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for PackageVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl VersionParseError {
+    /// Attach the name of the package to the error
+    pub fn with_package<S: Into<String>>(mut self, package: S) -> Self {
+        self.package = Some(package.into());
+        self
+    }
+
+    /// Name of the package with the invalid version, if known
+    pub fn get_package(&self) -> Option<&str> {
+        self.package.as_deref()
+    }
+
+    /// The invalid version string
+    pub fn get_version(&self) -> &str {
+        &self.version
+    }
+}
+
+impl fmt::Display for VersionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.package {
+            Some(p) => write!(
+                f,
+                "Invalid version `{}` for package `{}`: {}",
+                self.version, p, self.reason
+            ),
+            None => write!(f, "Invalid version `{}`: {}", self.version, self.reason),
+        }
+    }
+}
+
+impl Error for VersionParseError {}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use super::PackageVersion;
+
+    #[test]
+    fn test_parse() {
+        let ver = PackageVersion::parse("23.04.1-1").unwrap();
+        assert_eq!(ver.epoch(), 0);
+        assert_eq!(ver.upstream(), "23.04.1");
+        assert_eq!(ver.revision(), Some("1"));
+
+        let ver = PackageVersion::parse("2:1.0~rc1-git-3").unwrap();
+        assert_eq!(ver.epoch(), 2);
+        assert_eq!(ver.upstream(), "1.0~rc1-git");
+        assert_eq!(ver.revision(), Some("3"));
+        assert_eq!(ver.to_string(), "2:1.0~rc1-git-3");
+
+        let ver = PackageVersion::parse("0:23.04.1").unwrap();
+        assert_eq!(ver.epoch(), 0);
+        assert_eq!(ver.revision(), None);
+        assert_eq!(ver.to_string(), "0:23.04.1");
+
+        let mut versions: Vec<PackageVersion> = ["23.04.2", "1:0.1", "23.04.1-1", "23.04.1~rc1"]
+            .iter()
+            .map(|v| v.parse().unwrap())
+            .collect();
+        versions.sort();
+        assert_eq!(
+            versions.iter().map(|v| v.as_str()).collect::<Vec<_>>(),
+            ["23.04.1~rc1", "23.04.1-1", "23.04.2", "1:0.1"]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for v in [
+            "", "-1", "1.0 beta", "a:1.0", ":1.0", "1.0-", "1.0-r 1", "1:-1.0",
+        ] {
+            assert!(
+                PackageVersion::parse(v).is_err(),
+                "{:?} should be invalid",
+                v
+            );
+        }
+        let err = PackageVersion::parse("1.0 beta")
+            .unwrap_err()
+            .with_package("konsole");
+        assert_eq!(
+            err.to_string(),
+            "Invalid version `1.0 beta` for package `konsole`: upstream version contains invalid characters"
+        );
+    }
+
+    #[test]
+    fn test_ord() {
+        use Ordering::*;
+
+        // Test vectors from dpkg's t/dpkg_version.c
+        let cases = [
+            ("1.0", "1.0", Equal),
+            ("0:1.0", "1.0", Equal),
+            ("1.0-0", "1.0", Equal),
+            ("1.0~rc1", "1.0", Less),
+            ("1.0~~", "1.0~~a", Less),
+            ("1.0~~a", "1.0~", Less),
+            ("1.0~", "1.0", Less),
+            ("1.0", "1.0a", Less),
+            ("2:1.0", "1:9.9", Greater),
+            ("1:0.4", "10.3", Greater),
+            ("1:1.2-4", "1:1.2-3", Greater),
+            ("1.0-1", "1.0-1~rc", Greater),
+            ("1.002-1+b2", "1.00", Greater),
+            ("1.2.10", "1.2.9", Greater),
+            ("1.0.0", "1.0", Greater),
+            ("1.0a", "1.0+", Less),
+            ("0.9.9.9", "1.0", Less),
+            ("23.04.1-1", "23.04.2", Less),
+            ("007", "7", Equal),
+        ];
+        for (a, b, expected) in cases {
+            let (va, vb) = (
+                PackageVersion::parse(a).unwrap(),
+                PackageVersion::parse(b).unwrap(),
+            );
+            assert_eq!(va.cmp(&vb), expected, "{} vs {}", a, b);
+            assert_eq!(vb.cmp(&va), expected.reverse(), "{} vs {}", b, a);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Rendering of TOML parse errors
+//!
+//! Errors are rendered like those of Cargo manifests, pointing at the offending source line:
+//!
+//! ```text
+//! error: invalid type: integer `1`, expected a string
+//!  --> topics/kde.toml:2:16
+//!   |
+//! 2 | name.default = 1
+//!   |                ^
+//! ```
+
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::Path;
+
+/// Render a parse error of `content`, read from `path`, with the offending line and a caret
+///
+/// Errors without location only get the message and the path.
+pub fn render(path: &Path, content: &str, error: &toml::de::Error) -> String {
+    match error.span() {
+        Some(span) => render_at(path, content, error.message(), span),
+        None => format!(
+            "error: {}\n --> {}",
+            error.message().trim_end(),
+            path.to_string_lossy()
+        ),
+    }
+}
+
+/// Render `message` pointing at `span` of `content`, for errors found after parsing
+pub(crate) fn render_at(path: &Path, content: &str, message: &str, span: Range<usize>) -> String {
+    let mut out = format!("error: {}", message.trim_end());
+    let start = floor_boundary(content, span.start);
+    let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[start..]
+        .find('\n')
+        .map_or(content.len(), |i| start + i);
+    let line = content[line_start..line_end].trim_end_matches('\r');
+    let line_no = content[..start].matches('\n').count() + 1;
+    let column = content[line_start..start].chars().count() + 1;
+
+    // Keep tabs in the indentation, so the caret lines up with the source
+    let indent: String = content[line_start..start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let end = floor_boundary(content, span.end).clamp(start, line_start + line.len());
+    let width = content[start..end].chars().count().max(1);
+
+    let gutter = " ".repeat(line_no.to_string().len());
+    let _ = write!(
+        out,
+        "\n{gutter}--> {}:{}:{}\n{gutter} |\n{} | {}\n{gutter} | {}{}",
+        path.to_string_lossy(),
+        line_no,
+        column,
+        line_no,
+        line,
+        indent,
+        "^".repeat(width)
+    );
+    out
+}
+
+/// Largest char boundary of `s` not after `offset`
+fn floor_boundary(s: &str, offset: usize) -> usize {
+    let mut offset = offset.min(s.len());
+    while !s.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::render;
+    use crate::{Conventional, Manifest};
+
+    fn render_conventional(content: &str) -> String {
+        let error = toml::from_str::<Conventional>(content).unwrap_err();
+        render(Path::new("topics/kde.toml"), content, &error)
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render_conventional("name.default = \"\\uD800\"\n"),
+            r#"error: invalid unicode 4-digit hex code
+value is out of range
+ --> topics/kde.toml:1:19
+  |
+1 | name.default = "\uD800"
+  |                   ^"#
+        );
+        assert_eq!(
+            render_conventional("name.default = \"KDE\"\nname.default = \"KDE\"\n"),
+            r#"error: duplicate key `default` in document root
+ --> topics/kde.toml:2:1
+  |
+2 | name.default = "KDE"
+  | ^"#
+        );
+        assert_eq!(
+            render_conventional("name.default = \"KDE\"\nsecurity = \"yes\"\n"),
+            r#"error: invalid type: string "yes", expected a boolean
+ --> topics/kde.toml:2:12
+  |
+2 | security = "yes"
+  |            ^^^^^"#
+        );
+        // Columns count characters, and tabs are kept to line up the caret
+        assert_eq!(
+            render_conventional("# 注释\n\tname.default = 1\n"),
+            "error: invalid type: integer `1`, expected a string\n --> topics/kde.toml:2:17\n  |\n2 | \tname.default = 1\n  | \t               ^"
+        );
+
+        let error = toml::from_str::<Manifest>("name = 1").unwrap_err();
+        assert_eq!(
+            render(Path::new("topics/kde.toml"), "name = 1", &error),
+            "error: data did not match any variant of untagged enum ManifestUntagged\n --> topics/kde.toml"
+        );
+    }
+}
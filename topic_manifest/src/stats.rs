@@ -0,0 +1,143 @@
+//! Size statistics of manifest collections, to monitor the growth of the output
+
+use serde::Serialize;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use super::{Manifest, ManifestCollection, OutputFormat};
+
+/// Counts and JSON sizes of a collection, see [`ManifestCollection::stats`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CollectionStats {
+    /// Number of topics
+    pub topics: usize,
+    /// Number of distinct packages updated or removed by conventional topics
+    pub packages: usize,
+    /// Size in bytes of the collection written as [`OutputFormat::Json`]
+    pub json_bytes: usize,
+    /// Size in bytes of the collection written as [`OutputFormat::JsonCompact`]
+    pub json_compact_bytes: usize,
+    /// Statistics of each topic, keyed by topic names
+    pub per_topic: BTreeMap<String, TopicStats>,
+}
+
+/// Counts and JSON size of a single topic
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TopicStats {
+    /// Number of distinct packages, including per architecture, always 0 for cumulative topics
+    pub packages: usize,
+    /// Size in bytes of the topic written as compact JSON, without its key in the collection
+    pub json_compact_bytes: usize,
+}
+
+/// Writer counting the bytes written to it
+#[derive(Default)]
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ManifestCollection {
+    /// Get the number of topics and packages, and the size of the collection as JSON
+    pub fn stats(&self) -> CollectionStats {
+        let json_bytes = |format| {
+            let mut counter = ByteCounter::default();
+            self.to_writer(format, &mut counter)
+                .expect("manifests always serialize to JSON");
+            counter.0
+        };
+        let per_topic = self
+            .iter()
+            .map(|(topic, manifest)| {
+                let packages = match manifest {
+                    Manifest::Conventional(c) => c.package_names().collect::<BTreeSet<_>>().len(),
+                    Manifest::Cumulative(_) => 0,
+                };
+                let mut counter = ByteCounter::default();
+                serde_json::to_writer(&mut counter, manifest)
+                    .expect("manifests always serialize to JSON");
+                let stats = TopicStats {
+                    packages,
+                    json_compact_bytes: counter.0,
+                };
+                (topic.to_string(), stats)
+            })
+            .collect();
+        CollectionStats {
+            topics: self.len(),
+            packages: self.all_packages().len(),
+            json_bytes: json_bytes(OutputFormat::Json),
+            json_compact_bytes: json_bytes(OutputFormat::JsonCompact),
+            per_topic,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+
+    use crate::test_util::{conventional, manifest, topics};
+    use crate::ManifestCollection;
+
+    #[test]
+    fn test_stats() -> Result<()> {
+        let collection = ManifestCollection::from(topics([
+            (
+                "kde".to_string(),
+                conventional(
+                    r#"
+                    name.default = "KDE"
+                    [packages]
+                    konsole = "23.04.1"
+                    pykde = false
+                    [arch_packages.arm64]
+                    konsole = "23.04.0"
+                    dolphin = "23.04.1"
+                    "#,
+                ),
+            ),
+            (
+                "qt".to_string(),
+                conventional("name.default = \"Qt\"\n[packages]\nkonsole = \"23.04.1\""),
+            ),
+            (
+                "all".to_string(),
+                manifest("name.default = \"All\"\ntopics = [\"kde\", \"qt\"]"),
+            ),
+        ]));
+
+        let stats = collection.stats();
+        assert_eq!(stats.topics, 3);
+        assert_eq!(stats.packages, 3);
+        assert_eq!(stats.json_bytes, collection.to_json_string_pretty()?.len());
+        assert_eq!(stats.json_compact_bytes, collection.to_json_string()?.len());
+        assert!(stats.json_compact_bytes < stats.json_bytes);
+        assert_eq!(stats.per_topic["kde"].packages, 3);
+        assert_eq!(stats.per_topic["qt"].packages, 1);
+        assert_eq!(stats.per_topic["all"].packages, 0);
+        assert_eq!(
+            stats.per_topic["all"].json_compact_bytes,
+            serde_json::to_string(collection.get("all").unwrap())?.len()
+        );
+        // Each topic is followed by a comma but the last, and the collection is wrapped in braces
+        let keys: usize = stats.per_topic.keys().map(|k| k.len() + 3).sum();
+        let topics: usize = stats.per_topic.values().map(|t| t.json_compact_bytes).sum();
+        assert_eq!(
+            stats.json_compact_bytes,
+            keys + topics + stats.topics - 1 + 2
+        );
+
+        assert_eq!(ManifestCollection::default().stats().json_compact_bytes, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+//! Helpers shared by unit tests
+
+use std::collections::BTreeMap;
+
+use super::{Manifest, Packages, TopicId};
+
+/// Key manifests by topic names, panicking on invalid ones
+pub(crate) fn topics<S: Into<String>, const N: usize>(
+    entries: [(S, Manifest); N],
+) -> BTreeMap<TopicId, Manifest> {
+    entries
+        .into_iter()
+        .map(|(name, manifest)| (TopicId::try_from(name.into()).unwrap(), manifest))
+        .collect()
+}
+
+/// Parse a manifest, panicking on errors
+pub(crate) fn manifest(s: &str) -> Manifest {
+    toml::from_str(s).unwrap()
+}
+
+/// Parse a conventional topic, filling in the required fields `s` leaves out
+///
+/// Unless `s` says otherwise, the topic is named `Conventional`, is not a security update, has an
+/// empty caution and updates no packages.
+pub(crate) fn conventional(s: &str) -> Manifest {
+    let mut table: toml::Table = s.parse().unwrap();
+    let defaults: toml::Table =
+        "name.default = \"Conventional\"\nsecurity = false\ncaution.default = \"\"\npackages = {}"
+            .parse()
+            .unwrap();
+    for (key, value) in defaults {
+        table.entry(key).or_insert(value);
+    }
+    toml::Value::Table(table).try_into().unwrap()
+}
+
+/// A cumulative topic named `Cumulative` including `topics`
+pub(crate) fn cumulative(topics: &[&str]) -> Manifest {
+    manifest(&format!(
+        "name.default = \"Cumulative\"\ntopics = {:?}",
+        topics
+    ))
+}
+
+/// Parse packages, panicking on errors
+pub(crate) fn packages(s: &str) -> Packages {
+    toml::from_str(s).unwrap()
+}
@@ -0,0 +1,311 @@
+//! Serialization of manifests back to TOML
+//!
+//! TOML has no `null`, so removed packages are written as `false`, and localized strings are
+//! written as dotted keys with canonical locales (`name.zh-CN = "..."`) to match hand-written manifests.
+
+use localized::Localized;
+use toml_edit::{value, DocumentMut, InlineTable, Item, Table};
+
+use super::{Conventional, Cumulative, Manifest, Packages};
+
+fn localized_item(localized: &Localized<String>) -> Item {
+    let mut table = Table::new();
+    table.set_dotted(true);
+    if let Some(default) = localized.get_default() {
+        table.insert("default", value(default));
+    }
+    for (locale, content) in &localized.content {
+        table.insert(&locale.to_string(), value(content));
+    }
+    Item::Table(table)
+}
+
+fn string_array_item(strings: &[String]) -> Item {
+    value(strings.iter().collect::<toml_edit::Array>())
+}
+
+fn version_value(ver: Option<&str>) -> toml::Value {
+    match ver {
+        Some(v) => toml::Value::String(v.to_string()),
+        None => toml::Value::Boolean(false),
+    }
+}
+
+fn version_item_value(ver: Option<&str>) -> toml_edit::Value {
+    match ver {
+        Some(v) => v.into(),
+        None => false.into(),
+    }
+}
+
+impl Packages {
+    /// Convert to a TOML table, writing removed packages as `false`
+    ///
+    /// Versions per architecture are written as inline tables, with the version for other
+    /// architectures under `default`.
+    pub fn to_toml_value(&self) -> toml::Value {
+        toml::Value::Table(
+            self.iter()
+                .filter(|(name, _)| self.get_arch_specific(name).is_none())
+                .map(|(name, ver)| (name.to_string(), version_value(ver)))
+                .chain(self.arch_specific().map(|(name, versions)| {
+                    let default = self.get(name).map(|ver| version_value(ver.as_deref()));
+                    let versions = default
+                        .map(|ver| ("default".to_string(), ver))
+                        .into_iter()
+                        .chain(
+                            versions
+                                .iter()
+                                .map(|(arch, v)| (arch.to_string(), version_value(v.as_deref()))),
+                        )
+                        .collect();
+                    (name.to_string(), toml::Value::Table(versions))
+                }))
+                .collect(),
+        )
+    }
+
+    fn to_toml_table(&self) -> Table {
+        let mut table = Table::new();
+        for (name, ver) in self.iter() {
+            if self.get_arch_specific(name).is_none() {
+                table.insert(name, value(version_item_value(ver)));
+            }
+        }
+        for (name, versions) in self.arch_specific() {
+            let mut inline = InlineTable::new();
+            if let Some(ver) = self.get(name) {
+                inline.insert("default", version_item_value(ver.as_deref()));
+            }
+            for (arch, ver) in versions {
+                inline.insert(arch, version_item_value(ver.as_deref()));
+            }
+            table.insert(name, value(inline));
+        }
+        table
+    }
+}
+
+impl Conventional {
+    fn to_toml_document(&self) -> DocumentMut {
+        let mut doc = DocumentMut::new();
+        if let Some(topic) = self.get_topic_id() {
+            doc.insert("topic", value(topic.as_str()));
+        }
+        if let Some(extends) = self.get_extends() {
+            doc.insert("extends", value(extends));
+        }
+        doc.insert("name", localized_item(self.get_name()));
+        doc.insert("security", value(self.is_security_update()));
+        doc.insert("caution", localized_item(self.get_caution()));
+        if let Some(severity) = self.get_severity() {
+            doc.insert("severity", value(severity.as_str()));
+        }
+        if let Some(url) = self.get_url() {
+            doc.insert("url", value(url));
+        }
+        if let Some(arch) = self.get_arch() {
+            doc.insert("arch", string_array_item(arch));
+        }
+        #[cfg(feature = "chrono")]
+        if let Some(date) = self.get_date() {
+            doc.insert("date", value(date.to_string()));
+        }
+        doc.insert("packages", Item::Table(self.get_packages().to_toml_table()));
+        if let Some(cautions) = self.get_per_package_caution() {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            for (name, caution) in cautions {
+                let mut item = localized_item(caution);
+                if let Item::Table(caution) = &mut item {
+                    caution.set_dotted(false);
+                }
+                table.insert(name, item);
+            }
+            doc.insert("per_package_caution", Item::Table(table));
+        }
+        doc
+    }
+}
+
+impl Cumulative {
+    fn to_toml_document(&self) -> DocumentMut {
+        let mut doc = DocumentMut::new();
+        if let Some(topic) = self.get_topic_id() {
+            doc.insert("topic", value(topic.as_str()));
+        }
+        doc.insert("name", localized_item(self.get_name()));
+        doc.insert("topics", string_array_item(self.get_topics()));
+        if let Some(arch) = self.get_arch() {
+            doc.insert("arch", string_array_item(arch));
+        }
+        #[cfg(feature = "chrono")]
+        if let Some(date) = self.get_date() {
+            doc.insert("date", value(date.to_string()));
+        }
+        doc
+    }
+}
+
+impl Manifest {
+    /// Serialize the manifest to a TOML document in the same style as hand-written manifests
+    pub fn to_toml_string(&self) -> String {
+        match self {
+            Self::Conventional(c) => c.to_toml_document(),
+            Self::Cumulative(c) => c.to_toml_document(),
+        }
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+
+    use crate::{Manifest, Packages};
+
+    #[test]
+    fn test_conventional_roundtrip() -> Result<()> {
+        let example = r#"
+        name.default = "KDE Updates (Winter 2023)"
+        name.zh_CN = "KDE 更新（2023 年冬季）"
+        # Security update (true/false)?
+        security = true
+        # OPTIONAL: PSA message for users.
+        caution.default = """This topic may use significantly more memory after reboot. Our testing finds that the new KDE version may use up to 16GiB of RAM."""
+        caution.zh_CN = """本次更新重启后可能会需要更多内存。据我社维护者测试，新版 KDE 可能需要接近 16GiB 内存。"""
+        severity = "Warning"
+        url = "https://kde.org/announcements/gear/23.04.1/"
+        arch = ["amd64", "arm64"]
+
+        [packages]
+        konsole = "23.04.1-1"
+        dolphin = "23.04.1"
+        # Package removed as part of the topic.
+        pykde = false
+        kate = { arm64 = "23.04.1-2", amd64 = "23.04.1-1" }
+        "#;
+
+        let parsed = toml::from_str::<Manifest>(example)?;
+        let emitted = parsed.to_toml_string();
+        #[cfg(not(feature = "preserve-order"))]
+        assert_eq!(
+            emitted,
+            r#"name.default = "KDE Updates (Winter 2023)"
+name.zh-CN = "KDE 更新（2023 年冬季）"
+security = true
+caution.default = "This topic may use significantly more memory after reboot. Our testing finds that the new KDE version may use up to 16GiB of RAM."
+caution.zh-CN = "本次更新重启后可能会需要更多内存。据我社维护者测试，新版 KDE 可能需要接近 16GiB 内存。"
+severity = "warning"
+url = "https://kde.org/announcements/gear/23.04.1/"
+arch = ["amd64", "arm64"]
+
+[packages]
+dolphin = "23.04.1"
+konsole = "23.04.1-1"
+pykde = false
+kate = { amd64 = "23.04.1-1", arm64 = "23.04.1-2" }
+"#
+        );
+        #[cfg(feature = "preserve-order")]
+        assert!(emitted.ends_with(
+            r#"[packages]
+konsole = "23.04.1-1"
+dolphin = "23.04.1"
+pykde = false
+kate = { amd64 = "23.04.1-1", arm64 = "23.04.1-2" }
+"#
+        ));
+        assert_eq!(toml::from_str::<Manifest>(&emitted)?, parsed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_arch_packages_roundtrip() -> Result<()> {
+        let example = r#"
+        name.default = "Mesa"
+        security = false
+        caution.default = ""
+
+        [packages]
+        mesa = "23.3.0"
+
+        [arch_packages.arm64]
+        mesa = "23.2.1"
+        libdrm = false
+        "#;
+
+        let parsed = toml::from_str::<Manifest>(example)?;
+        let emitted = parsed.to_toml_string();
+        assert!(emitted.ends_with(
+            r#"[packages]
+libdrm = { arm64 = false }
+mesa = { default = "23.3.0", arm64 = "23.2.1" }
+"#
+        ));
+        assert!(!emitted.contains("arch_packages"));
+        assert_eq!(toml::from_str::<Manifest>(&emitted)?, parsed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_package_caution_roundtrip() -> Result<()> {
+        let example = r#"
+        name.default = "Core Updates"
+        security = false
+        caution.default = ""
+
+        [packages]
+        "linux+kernel" = "6.6.3"
+        firefox = "120.0"
+
+        [per_package_caution."linux+kernel"]
+        default = "Reboot to use the new kernel."
+        zh_CN = "重启以使用新内核。"
+        "#;
+
+        let parsed = toml::from_str::<Manifest>(example)?;
+        let emitted = parsed.to_toml_string();
+        assert!(emitted.ends_with(
+            r#"[per_package_caution."linux+kernel"]
+default = "Reboot to use the new kernel."
+zh-CN = "重启以使用新内核。"
+"#
+        ));
+        assert!(!emitted.contains("[per_package_caution]\n"));
+        assert_eq!(toml::from_str::<Manifest>(&emitted)?, parsed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cumulative_roundtrip() -> Result<()> {
+        let example = r#"
+        name.default = "Winter 2023 Cumulative Update for amd64 AOSC OS systems"
+        name.zh_MS = "适用于 amd64 AOSC OS 版本的 23 冬季累计更新"
+        topics = ["kde-survey-20231201", "core-12.1.0"]
+        arch = ["amd64"]
+        "#;
+
+        let parsed = toml::from_str::<Manifest>(example)?;
+        let emitted = parsed.to_toml_string();
+        assert_eq!(toml::from_str::<Manifest>(&emitted)?, parsed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_packages_to_toml_value() -> Result<()> {
+        let packages: Packages = toml::from_str(
+            r#"
+            konsole = "23.04.1-1"
+            pykde = false
+            kate = { amd64 = "23.04.1-1", arm64 = "23.04.1-2" }
+            "#,
+        )?;
+        let value = packages.to_toml_value();
+        assert_eq!(value["konsole"].as_str(), Some("23.04.1-1"));
+        assert_eq!(value["kate"]["arm64"].as_str(), Some("23.04.1-2"));
+        assert_eq!(value["pykde"].as_bool(), Some(false));
+        assert_eq!(value.try_into::<Packages>()?, packages);
+        Ok(())
+    }
+}
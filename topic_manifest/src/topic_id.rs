@@ -0,0 +1,173 @@
+//! Topic names
+
+use serde::{Deserialize, Serialize};
+
+use std::borrow::Borrow;
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Maximum number of characters in a topic name
+const MAX_LENGTH: usize = 128;
+
+/// Name of a topic, safe to use as a filename
+///
+/// Topic names may only contain ASCII letters, digits, `-`, `_` and `.`, must be 1 to 128
+/// characters long, must not start or end with `-`, and must not be `.` or `..`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(try_from = "String", into = "String")]
+#[repr(transparent)]
+pub struct TopicId(String);
+
+/// A topic name violating the naming rules of [`TopicId`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidTopicIdError {
+    id: String,
+    reason: &'static str,
+}
+
+impl TopicId {
+    /// Check a topic name against the naming rules
+    pub fn new(s: &str) -> Result<Self, InvalidTopicIdError> {
+        let err = |reason| InvalidTopicIdError {
+            id: s.to_string(),
+            reason,
+        };
+        if s.is_empty() || s.len() > MAX_LENGTH {
+            return Err(err("must be 1 to 128 characters long"));
+        }
+        if s.starts_with('-') || s.ends_with('-') {
+            return Err(err("must not start or end with `-`"));
+        }
+        if s == "." || s == ".." {
+            return Err(err("must not be `.` or `..`"));
+        }
+        if !s
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        {
+            return Err(err(
+                "may only contain ASCII letters, digits, `-`, `_` and `.`",
+            ));
+        }
+        Ok(Self(s.to_string()))
+    }
+
+    /// Get the topic name as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for TopicId {
+    type Error = InvalidTopicIdError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(&value)
+    }
+}
+
+impl FromStr for TopicId {
+    type Err = InvalidTopicIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl From<TopicId> for String {
+    fn from(value: TopicId) -> Self {
+        value.0
+    }
+}
+
+impl Deref for TopicId {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Borrow<str> for TopicId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for TopicId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for TopicId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl InvalidTopicIdError {
+    /// The invalid topic name
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl fmt::Display for InvalidTopicIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid topic name `{}`: {}", self.id, self.reason)
+    }
+}
+
+impl Error for InvalidTopicIdError {}
+
+#[cfg(test)]
+mod test {
+    use super::TopicId;
+    use crate::ManifestCollection;
+
+    #[test]
+    fn test_topic_id() {
+        for id in [
+            "kde-survey-20231201",
+            "cumulative-2023H3",
+            "a",
+            "qt_6.6",
+            "x".repeat(128).as_str(),
+        ] {
+            assert_eq!(TopicId::new(id).unwrap().as_str(), id);
+        }
+        for id in [
+            "",
+            "-kde",
+            "kde-",
+            "kde survey",
+            "kde/survey",
+            "核心",
+            &"x".repeat(129),
+            ".",
+            "..",
+        ] {
+            assert!(TopicId::new(id).is_err(), "{:?} should be invalid", id);
+        }
+        assert_eq!(TopicId::new("...").unwrap().as_str(), "...");
+        assert_eq!(
+            TopicId::new("..").unwrap_err().to_string(),
+            "Invalid topic name `..`: must not be `.` or `..`"
+        );
+        assert_eq!(
+            "kde/survey".parse::<TopicId>().unwrap_err().to_string(),
+            "Invalid topic name `kde/survey`: may only contain ASCII letters, digits, `-`, `_` and `.`"
+        );
+
+        let id: TopicId = serde_json::from_str(r#""kde""#).unwrap();
+        assert_eq!(String::from(id), "kde");
+        assert!(serde_json::from_str::<TopicId>(r#""kde survey""#).is_err());
+
+        let collection = r#"{"kde survey": {"type": "cumulative", "name": {}, "topics": []}}"#;
+        assert!(serde_json::from_str::<ManifestCollection>(collection).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! Translation coverage of manifest collections
+
+use localized::{Locale, Localized};
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{Manifest, ManifestCollection};
+
+/// Localized field of a topic
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LocalizedField {
+    /// Name of the topic
+    Name,
+    /// Caution message of a conventional topic
+    Caution,
+    /// Caution message of a single package in a conventional topic
+    #[serde(rename = "per_package_caution")]
+    PackageCaution,
+}
+
+impl fmt::Display for LocalizedField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name => write!(f, "name"),
+            Self::Caution => write!(f, "caution"),
+            Self::PackageCaution => write!(f, "per_package_caution"),
+        }
+    }
+}
+
+/// A localized field lacking the translation for a locale
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MissingTranslation {
+    /// Topic of the field
+    pub topic: String,
+    /// The untranslated field
+    pub field: LocalizedField,
+    /// Package of the caution if `field` is [`LocalizedField::PackageCaution`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+}
+
+/// Does `localized` have any non-empty string
+fn has_content(localized: &Localized<String>) -> bool {
+    localized
+        .default
+        .iter()
+        .chain(localized.content.values())
+        .any(|s| !s.trim().is_empty())
+}
+
+impl Manifest {
+    /// Localized fields with content along with the packages of their cautions, empty caution
+    /// messages are skipped
+    fn translatable_fields(&self) -> Vec<(LocalizedField, Option<&str>, &Localized<String>)> {
+        match self {
+            Self::Conventional(c) => {
+                let mut fields = vec![(LocalizedField::Name, None, c.get_name())];
+                if has_content(c.get_caution()) {
+                    fields.push((LocalizedField::Caution, None, c.get_caution()));
+                }
+                for (package, caution) in c.get_per_package_caution().into_iter().flatten() {
+                    if has_content(caution) {
+                        fields.push((
+                            LocalizedField::PackageCaution,
+                            Some(package.as_str()),
+                            caution,
+                        ));
+                    }
+                }
+                fields
+            }
+            Self::Cumulative(c) => vec![(LocalizedField::Name, None, c.get_name())],
+        }
+    }
+}
+
+impl ManifestCollection {
+    /// Get a list of localized fields without a translation for `locale`, sorted by topic name
+    ///
+    /// Empty caution messages need no translation and are not reported.
+    pub fn missing_translations(&self, locale: &Locale) -> Vec<MissingTranslation> {
+        self.iter()
+            .flat_map(|(topic, manifest)| {
+                manifest
+                    .translatable_fields()
+                    .into_iter()
+                    .filter(|(_, _, localized)| !localized.content.contains_key(locale))
+                    .map(move |(field, package, _)| MissingTranslation {
+                        topic: topic.to_string(),
+                        field,
+                        package: package.map(str::to_string),
+                    })
+            })
+            .collect()
+    }
+
+    /// Get topics whose names lack a translation for `locale`, sorted by topic name
+    pub fn untranslated_topics(&self, locale: &Locale) -> Vec<&str> {
+        self.iter()
+            .filter(|(_, manifest)| !manifest.get_name().content.contains_key(locale))
+            .map(|(topic, _)| topic)
+            .collect()
+    }
+
+    /// Get the percentage of localized fields translated to each locale used in the collection
+    ///
+    /// Empty caution messages need no translation and are not counted.
+    pub fn translation_coverage(&self) -> BTreeMap<Locale, f32> {
+        let mut total = 0;
+        let mut translated = BTreeMap::new();
+        for (_, manifest) in self.iter() {
+            for (_, _, localized) in manifest.translatable_fields() {
+                total += 1;
+                for locale in localized.content.keys() {
+                    *translated.entry(locale.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        translated
+            .into_iter()
+            .map(|(locale, count)| (locale, count as f32 * 100.0 / total as f32))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eyre::Result;
+
+    use super::{LocalizedField, MissingTranslation};
+    use crate::test_util::topics;
+    use crate::{Locale, ManifestCollection};
+
+    #[test]
+    fn test_coverage() -> Result<()> {
+        let manifests = ManifestCollection::from(topics([
+            (
+                "kde".to_string(),
+                toml::from_str(
+                    r#"
+                    name.default = "KDE Updates"
+                    name.zh_CN = "KDE 更新"
+                    name.zh_TW = "KDE 更新"
+                    security = false
+                    caution.default = "Reboot required"
+                    caution.zh_TW = "需要重新開機"
+                    [packages]
+                    "#,
+                )?,
+            ),
+            (
+                "core".to_string(),
+                toml::from_str(
+                    r#"
+                    name.default = "Core Updates"
+                    name.zh_CN = "核心更新"
+                    security = false
+                    caution.default = ""
+                    per_package_caution.gcc.default = "Reboot required"
+                    per_package_caution.gcc.zh_CN = "需要重启"
+                    [packages]
+                    gcc = "13.2.0"
+                    "#,
+                )?,
+            ),
+            (
+                "2023H3".to_string(),
+                toml::from_str(
+                    r#"
+                    name.default = "Cumulative Update"
+                    topics = ["kde", "core"]
+                    "#,
+                )?,
+            ),
+        ]));
+
+        assert_eq!(
+            manifests.missing_translations(&Locale::new("zh_CN")),
+            [
+                MissingTranslation {
+                    topic: "2023H3".to_string(),
+                    field: LocalizedField::Name,
+                    package: None,
+                },
+                MissingTranslation {
+                    topic: "kde".to_string(),
+                    field: LocalizedField::Caution,
+                    package: None,
+                },
+            ]
+        );
+        assert_eq!(
+            manifests.untranslated_topics(&Locale::new("zh_TW")),
+            ["2023H3", "core"]
+        );
+        assert_eq!(
+            serde_json::to_string(&manifests.missing_translations(&Locale::new("zh_TW")))?,
+            concat!(
+                r#"[{"topic":"2023H3","field":"name"},{"topic":"core","field":"name"},"#,
+                r#"{"topic":"core","field":"per_package_caution","package":"gcc"}]"#
+            )
+        );
+
+        // 5 fields in total, the empty caution of `core` is not counted
+        let coverage = manifests.translation_coverage();
+        assert_eq!(coverage.len(), 2);
+        assert_eq!(coverage[&Locale::new("zh_CN")], 60.0);
+        assert_eq!(coverage[&Locale::new("zh_TW")], 40.0);
+        assert_eq!(
+            serde_json::to_string(&coverage)?,
+            r#"{"zh-CN":60.0,"zh-TW":40.0}"#
+        );
+        Ok(())
+    }
+}
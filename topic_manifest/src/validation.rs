@@ -0,0 +1,714 @@
+//! Validation of manifest collections
+
+use std::fmt;
+
+use super::graph::DependencyGraph;
+use super::packages::{InvalidPackageName, PackageVersion, VersionParseError};
+use super::translation::LocalizedField;
+use super::{Localized, Manifest, ManifestCollection};
+
+/// Checks run by [`ManifestCollection::validate`], all enabled by default
+///
+/// Each check reports either errors, which make the collection unusable, or warnings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidateOptions {
+    /// Cumulative topics referencing topics missing from the collection, errors
+    pub missing_topics: bool,
+    /// Cumulative topics referencing themselves, errors
+    pub self_references: bool,
+    /// Cumulative topics referencing each other in cycles, errors
+    pub cycles: bool,
+    /// Conventional topics extending missing or cumulative topics, or each other in cycles, errors
+    pub extends: bool,
+    /// Names, cautions with translations, and per-package cautions lacking non-empty default
+    /// values, errors
+    pub missing_defaults: bool,
+    /// Per-package cautions of packages missing from the topic and the topics it extends, errors
+    pub package_cautions: bool,
+    /// Package names violating the packaging policy, errors
+    pub package_names: bool,
+    /// Versions of updated packages failing to parse as [`PackageVersion`], warnings
+    pub package_versions: bool,
+    /// Invalid locales of localized fields, warnings
+    pub invalid_locales: bool,
+    /// Topics listed more than once in cumulative topics, warnings
+    pub duplicate_topics: bool,
+    /// URLs not passing [`is_valid_http_url`], warnings
+    pub urls: bool,
+}
+
+/// A problem found while validating a manifest collection
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// A cumulative topic references topics missing from the collection
+    MissingTopics { topic: String, missing: Vec<String> },
+    /// A cumulative topic references itself
+    SelfReference { topic: String },
+    /// A cumulative topic lists the same topics more than once
+    DuplicateTopics {
+        topic: String,
+        duplicates: Vec<String>,
+    },
+    /// A localized field lacks a non-empty default value
+    MissingDefault {
+        topic: String,
+        field: LocalizedField,
+    },
+    /// The caution of a package lacks a non-empty default value
+    MissingPackageCautionDefault { topic: String, package: String },
+    /// A conventional topic has cautions for packages it does not update
+    UnknownCautionPackages {
+        topic: String,
+        packages: Vec<String>,
+    },
+    /// Localized fields of a topic use invalid locales
+    InvalidLocales { topic: String, locales: Vec<String> },
+    /// A package name violates the packaging policy
+    InvalidPackageName {
+        topic: String,
+        error: InvalidPackageName,
+    },
+    /// A cumulative topic is part of, or references, a cycle of topics referencing each other
+    DependencyCycle { topic: String },
+    /// A package version fails to parse
+    InvalidVersion {
+        topic: String,
+        error: VersionParseError,
+    },
+    /// The URL of a conventional topic is not a well-formed HTTP or HTTPS URL
+    InvalidUrl { topic: String, url: String },
+    /// A conventional topic extends a topic which can not be resolved, see
+    /// [`ManifestCollection::resolve_extends`]
+    InvalidExtends { topic: String, reason: String },
+}
+
+impl Default for ValidateOptions {
+    fn default() -> Self {
+        Self {
+            missing_topics: true,
+            self_references: true,
+            cycles: true,
+            extends: true,
+            missing_defaults: true,
+            package_cautions: true,
+            package_names: true,
+            package_versions: true,
+            invalid_locales: true,
+            duplicate_topics: true,
+            urls: true,
+        }
+    }
+}
+
+/// Result of validating a manifest collection
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Problems making the collection unusable
+    pub errors: Vec<ValidationIssue>,
+    /// Problems worth fixing, but not fatal
+    pub warnings: Vec<ValidationIssue>,
+}
+
+impl ValidationIssue {
+    /// Topic where the problem is found
+    pub fn get_topic(&self) -> &str {
+        match self {
+            Self::MissingTopics { topic, .. } => topic,
+            Self::SelfReference { topic } => topic,
+            Self::DuplicateTopics { topic, .. } => topic,
+            Self::MissingDefault { topic, .. } => topic,
+            Self::MissingPackageCautionDefault { topic, .. } => topic,
+            Self::UnknownCautionPackages { topic, .. } => topic,
+            Self::InvalidLocales { topic, .. } => topic,
+            Self::InvalidPackageName { topic, .. } => topic,
+            Self::DependencyCycle { topic } => topic,
+            Self::InvalidVersion { topic, .. } => topic,
+            Self::InvalidUrl { topic, .. } => topic,
+            Self::InvalidExtends { topic, .. } => topic,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTopics { topic, missing } => write!(
+                f,
+                "Missing dependency for cumulative topic {}: {:?}",
+                topic, missing
+            ),
+            Self::SelfReference { topic } => {
+                write!(f, "Cumulative topic {} references itself", topic)
+            }
+            Self::DuplicateTopics { topic, duplicates } => write!(
+                f,
+                "Duplicate dependency for cumulative topic {}: {:?}",
+                topic, duplicates
+            ),
+            Self::MissingDefault { topic, field } => write!(
+                f,
+                "Topic {}: `{}.default` is missing or empty",
+                topic, field
+            ),
+            Self::MissingPackageCautionDefault { topic, package } => write!(
+                f,
+                "Topic {}: `per_package_caution.{}.default` is missing or empty",
+                topic, package
+            ),
+            Self::UnknownCautionPackages { topic, packages } => write!(
+                f,
+                "Per-package cautions in topic {} for packages it does not update: {:?}",
+                topic, packages
+            ),
+            Self::InvalidLocales { topic, locales } => {
+                write!(f, "Invalid locales in topic {}: {:?}", topic, locales)
+            }
+            Self::InvalidPackageName { topic, error } => write!(f, "Topic {}: {}", topic, error),
+            Self::DependencyCycle { topic } => write!(
+                f,
+                "Cumulative topic {} is part of or references a dependency cycle",
+                topic
+            ),
+            Self::InvalidVersion { topic, error } => write!(f, "Topic {}: {}", topic, error),
+            Self::InvalidUrl { topic, url } => write!(f, "Invalid URL in topic {}: {}", topic, url),
+            Self::InvalidExtends { reason, .. } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl ValidationReport {
+    /// Is the collection free of errors
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Is the collection free of both errors and warnings
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty() && self.warnings.is_empty()
+    }
+}
+
+/// Is the default value of a localized field missing or empty
+fn lacks_default(localized: &Localized<String>) -> bool {
+    localized.get_default().is_none_or(|s| s.trim().is_empty())
+}
+
+/// Default rule of [`ManifestCollection::find_name_mismatches`]
+///
+/// A topic matches its name if the first word of the topic (`kde` of `kde-survey-20231201`) appears
+/// in the default name (`KDE Updates (Winter 2023)`), ignoring case. Topics without a default name
+/// never match.
+pub fn topic_matches_name(topic: &str, name: &Localized<String>) -> bool {
+    let word = topic
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    name.get_default()
+        .is_some_and(|name| name.to_lowercase().contains(&word))
+}
+
+/// Is `url` a well-formed HTTP or HTTPS URL with a host
+///
+/// URLs are parsed with the [`url`] crate, following the WHATWG URL standard. Whitespace is
+/// rejected as well, which the standard would otherwise percent-encode or strip.
+pub fn is_valid_http_url(url: &str) -> bool {
+    !url.contains(char::is_whitespace)
+        && url::Url::parse(url)
+            .is_ok_and(|u| matches!(u.scheme(), "http" | "https") && u.host().is_some())
+}
+
+impl ManifestCollection {
+    /// Get a list of conventional topics with URLs not passing [`is_valid_http_url`], along with
+    /// the URLs
+    pub fn find_invalid_urls(&self) -> Vec<(String, String)> {
+        self.iter()
+            .filter_map(|(topic, manifest)| match manifest {
+                Manifest::Conventional(c) => c.get_url().map(|url| (topic, url)),
+                Manifest::Cumulative(_) => None,
+            })
+            .filter(|(_, url)| !is_valid_http_url(url))
+            .map(|(topic, url)| (topic.to_string(), url.to_string()))
+            .collect()
+    }
+
+    /// Get a list of conventional topics with per-package cautions for packages they do not
+    /// update, along with the packages
+    ///
+    /// Packages of the topics extended by a topic count as its own, see
+    /// [`ManifestCollection::resolve_extends`].
+    pub fn find_unknown_caution_packages(&self) -> Vec<(String, Vec<String>)> {
+        self.iter()
+            .filter_map(|(topic, manifest)| {
+                let Manifest::Conventional(c) = manifest else {
+                    return None;
+                };
+                let chain = self.extends_chain(topic, c).unwrap_or_else(|_| vec![c]);
+                let unknown: Vec<String> = c
+                    .get_per_package_caution()?
+                    .keys()
+                    .filter(|pkg| !chain.iter().any(|c| c.get_packages().contains(pkg)))
+                    .cloned()
+                    .collect();
+                (!unknown.is_empty()).then(|| (topic.to_string(), unknown))
+            })
+            .collect()
+    }
+
+    /// Get a list of topics not matching their default names, along with the names
+    ///
+    /// Topic names come from file stems of manifests, so this catches files renamed without
+    /// updating their titles, or the other way around. `rule` decides whether a topic matches its
+    /// name, see [`topic_matches_name`] for the default rule.
+    pub fn find_name_mismatches<F>(&self, rule: F) -> Vec<(String, String)>
+    where
+        F: Fn(&str, &Localized<String>) -> bool,
+    {
+        self.iter()
+            .filter(|(topic, manifest)| !rule(topic, manifest.get_name()))
+            .map(|(topic, manifest)| {
+                let name = manifest.get_name().get_default().cloned();
+                (topic.to_string(), name.unwrap_or_default())
+            })
+            .collect()
+    }
+
+    /// Run the checks enabled in `options` on the manifest collection
+    ///
+    /// This is what the `tumeta` CLI runs, see [`ValidateOptions`] for the checks and whether they
+    /// report errors or warnings. Errors come first in the order of checks, then sorted by topic.
+    pub fn validate(&self, options: &ValidateOptions) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        if options.missing_topics {
+            for (topic, missing) in self.find_missing_topics() {
+                report
+                    .errors
+                    .push(ValidationIssue::MissingTopics { topic, missing });
+            }
+        }
+
+        if options.self_references {
+            for topic in self.find_self_references() {
+                report.errors.push(ValidationIssue::SelfReference { topic });
+            }
+        }
+
+        if options.cycles {
+            // Self references are reported above
+            let mut graph = DependencyGraph::from_collection(self);
+            graph.remove_self_loops();
+            if let Err(cycle) = graph.topo_sort() {
+                for topic in cycle.get_topics() {
+                    report.errors.push(ValidationIssue::DependencyCycle {
+                        topic: topic.to_string(),
+                    });
+                }
+            }
+        }
+
+        if options.extends {
+            for (topic, reason) in self.find_invalid_extends() {
+                report
+                    .errors
+                    .push(ValidationIssue::InvalidExtends { topic, reason });
+            }
+        }
+
+        if options.package_cautions {
+            for (topic, packages) in self.find_unknown_caution_packages() {
+                report
+                    .errors
+                    .push(ValidationIssue::UnknownCautionPackages { topic, packages });
+            }
+        }
+
+        if options.invalid_locales {
+            for (topic, locales) in self.find_invalid_locales() {
+                report
+                    .warnings
+                    .push(ValidationIssue::InvalidLocales { topic, locales });
+            }
+        }
+
+        if options.urls {
+            for (topic, url) in self.find_invalid_urls() {
+                report
+                    .warnings
+                    .push(ValidationIssue::InvalidUrl { topic, url });
+            }
+        }
+
+        for (topic, manifest) in self.iter() {
+            let mut missing_default = Vec::new();
+            if options.missing_defaults {
+                if lacks_default(manifest.get_name()) {
+                    missing_default.push(LocalizedField::Name);
+                }
+                if let Manifest::Conventional(c) = manifest {
+                    if !c.get_caution().content.is_empty() && lacks_default(c.get_caution()) {
+                        missing_default.push(LocalizedField::Caution);
+                    }
+                }
+            }
+            for field in missing_default {
+                report.errors.push(ValidationIssue::MissingDefault {
+                    topic: topic.to_string(),
+                    field,
+                });
+            }
+            if let (true, Manifest::Conventional(c)) = (options.missing_defaults, manifest) {
+                for (package, caution) in c.get_per_package_caution().into_iter().flatten() {
+                    if lacks_default(caution) {
+                        report
+                            .errors
+                            .push(ValidationIssue::MissingPackageCautionDefault {
+                                topic: topic.to_string(),
+                                package: package.to_string(),
+                            });
+                    }
+                }
+            }
+
+            match manifest {
+                Manifest::Conventional(c) => {
+                    if options.package_names {
+                        for error in c.get_packages().validate_names() {
+                            report.errors.push(ValidationIssue::InvalidPackageName {
+                                topic: topic.to_string(),
+                                error,
+                            });
+                        }
+                    }
+                    if options.package_versions {
+                        for (name, version) in c.get_packages().updated() {
+                            if let Err(error) = PackageVersion::parse(version) {
+                                report.warnings.push(ValidationIssue::InvalidVersion {
+                                    topic: topic.to_string(),
+                                    error: error.with_package(name),
+                                });
+                            }
+                        }
+                    }
+                }
+                Manifest::Cumulative(c) => {
+                    let duplicates = c.find_duplicate_topics();
+                    if options.duplicate_topics && !duplicates.is_empty() {
+                        report.warnings.push(ValidationIssue::DuplicateTopics {
+                            topic: topic.to_string(),
+                            duplicates,
+                        });
+                    }
+                }
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_valid_http_url, topic_matches_name, ValidateOptions, ValidationIssue};
+    use crate::test_util::{conventional, cumulative, manifest, topics};
+    use crate::ManifestCollection;
+
+    #[test]
+    fn test_name_mismatches() {
+        let named = |name: &str| manifest(&format!("name.default = {:?}\ntopics = []", name));
+        let collection = ManifestCollection::from(topics([
+            (
+                "kde-survey-20231201".to_string(),
+                named("KDE Updates (Winter 2023)"),
+            ),
+            (
+                "cumulative-2023H3".to_string(),
+                named("Winter 2023 Cumulative Update"),
+            ),
+            ("gnome-45".to_string(), named("KDE Updates (Winter 2023)")),
+        ]));
+        assert_eq!(
+            collection.find_name_mismatches(topic_matches_name),
+            [(
+                "gnome-45".to_string(),
+                "KDE Updates (Winter 2023)".to_string()
+            )]
+        );
+        assert!(collection.find_name_mismatches(|_, _| true).is_empty());
+    }
+
+    #[test]
+    fn test_urls() {
+        for url in [
+            "https://github.com/AOSC-Dev/aosc-os-abbs/pull/5000",
+            "http://example.org",
+            "HTTPS://example.org:8443/path?q#frag",
+            "https://user@[::1]:80/",
+        ] {
+            assert!(is_valid_http_url(url), "{}", url);
+        }
+        for url in [
+            "",
+            "github.com/AOSC-Dev",
+            "ftp://example.org",
+            "https://",
+            "https://:80/",
+            "https://example.org:port/",
+            "https://example.org/with space",
+        ] {
+            assert!(!is_valid_http_url(url), "{}", url);
+        }
+
+        let with_url = |url: &str| conventional(&format!("url = {:?}", url));
+        let collection = ManifestCollection::from(topics([
+            (
+                "kde".to_string(),
+                with_url("https://kde.org/announcements/"),
+            ),
+            ("gnome".to_string(), with_url("gnome.org")),
+        ]));
+        assert_eq!(
+            collection.find_invalid_urls(),
+            [("gnome".to_string(), "gnome.org".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_missing_default() {
+        let localized_topic = |localized: &str| {
+            manifest(&format!(
+                "security = false\n{}\n[packages]\nkde = \"5.27\"",
+                localized
+            ))
+        };
+        let collection = ManifestCollection::from(topics([
+            (
+                "ok".to_string(),
+                localized_topic("name.default = \"OK\"\ncaution.default = \"\""),
+            ),
+            (
+                "no-caution".to_string(),
+                localized_topic("name.default = \"OK\"\ncaution = {}"),
+            ),
+            (
+                "no-name".to_string(),
+                localized_topic("name.zh_CN = \"名称\"\ncaution.default = \"\""),
+            ),
+            (
+                "empty-name".to_string(),
+                localized_topic("name.default = \" \"\nname.zh_CN = \"名称\"\ncaution.default = \"\""),
+            ),
+            (
+                "no-caution-default".to_string(),
+                localized_topic("name.default = \"OK\"\ncaution.zh_CN = \"注意\""),
+            ),
+            (
+                "empty-caution-default".to_string(),
+                localized_topic("name.default = \"OK\"\ncaution.default = \"\"\ncaution.zh_CN = \"注意\""),
+            ),
+            (
+                "no-package-caution-default".to_string(),
+                localized_topic("name.default = \"OK\"\ncaution.default = \"\"\nper_package_caution.kde.zh_CN = \"注意\""),
+            ),
+        ]));
+
+        let errors: Vec<String> = collection
+            .validate(&ValidateOptions::default())
+            .errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "Topic empty-caution-default: `caution.default` is missing or empty",
+                "Topic empty-name: `name.default` is missing or empty",
+                "Topic no-caution-default: `caution.default` is missing or empty",
+                "Topic no-name: `name.default` is missing or empty",
+                "Topic no-package-caution-default: `per_package_caution.kde.default` is missing or empty",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let conventional = conventional(
+            r#"
+            name.default = "KDE Updates (Winter 2023)"
+            [packages]
+            konsole = "23.04.1-1"
+            "Konsole!" = "23.04.1"
+            "#,
+        );
+        let cumulative = manifest(
+            r#"
+            name.default = "Winter 2023 Cumulative Update"
+            name.zh_CNN = "2023 年冬季累计更新"
+            topics = ["kde-survey-20231201", "core-12.1.0", "kde-survey-20231201"]
+            "#,
+        );
+        let collection = ManifestCollection::from(topics([
+            ("kde-survey-20231201".to_string(), conventional),
+            ("cumulative-2023H3".to_string(), cumulative),
+        ]));
+
+        let report = collection.validate(&ValidateOptions::default());
+        assert!(!report.is_ok());
+        assert_eq!(report.errors.len(), 2);
+        assert!(matches!(
+            &report.errors[0],
+            ValidationIssue::MissingTopics { topic, missing }
+                if topic == "cumulative-2023H3" && missing == &["core-12.1.0"]
+        ));
+        assert_eq!(report.errors[1].get_topic(), "kde-survey-20231201");
+        assert_eq!(
+            report.warnings,
+            [
+                ValidationIssue::InvalidLocales {
+                    topic: "cumulative-2023H3".to_string(),
+                    locales: vec!["zh_CNN".to_string()],
+                },
+                ValidationIssue::DuplicateTopics {
+                    topic: "cumulative-2023H3".to_string(),
+                    duplicates: vec!["kde-survey-20231201".to_string()],
+                }
+            ]
+        );
+        assert_eq!(
+            report.errors[1].to_string(),
+            "Topic kde-survey-20231201: Invalid package name `Konsole!`: must start with a lowercase letter or a digit"
+        );
+    }
+
+    #[test]
+    fn test_validate_options() {
+        let conventional = conventional(
+            r#"
+            url = "kde.org"
+            [packages]
+            konsole = "23.04.1-1"
+            dolphin = ":23.04.1"
+            pykde = false
+            "#,
+        );
+        let collection = ManifestCollection::from(topics([
+            ("a".to_string(), cumulative(&["b"])),
+            ("b".to_string(), cumulative(&["a", "kde"])),
+            ("self".to_string(), cumulative(&["self"])),
+            ("kde".to_string(), conventional),
+        ]));
+
+        let report = collection.validate(&ValidateOptions::default());
+        assert_eq!(
+            report.errors,
+            [
+                ValidationIssue::SelfReference {
+                    topic: "self".to_string()
+                },
+                ValidationIssue::DependencyCycle {
+                    topic: "a".to_string()
+                },
+                ValidationIssue::DependencyCycle {
+                    topic: "b".to_string()
+                },
+            ]
+        );
+        let warnings: Vec<String> = report.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            [
+                "Invalid URL in topic kde: kde.org",
+                "Topic kde: Invalid version `:23.04.1` for package `dolphin`: epoch must be a number",
+            ]
+        );
+
+        let options = ValidateOptions {
+            self_references: false,
+            cycles: false,
+            package_versions: false,
+            urls: false,
+            ..Default::default()
+        };
+        assert!(collection.validate(&options).is_clean());
+    }
+
+    #[test]
+    fn test_validate_extends() {
+        let extending = |extends: &str| conventional(&format!("extends = {:?}", extends));
+        let collection = ManifestCollection::from(topics([
+            ("all".to_string(), cumulative(&[])),
+            ("kde".to_string(), extending("all")),
+            ("qt".to_string(), extending("base")),
+            ("x".to_string(), extending("y")),
+            ("y".to_string(), extending("x")),
+        ]));
+
+        let report = collection.validate(&ValidateOptions::default());
+        let errors: Vec<(&str, String)> = report
+            .errors
+            .iter()
+            .map(|e| (e.get_topic(), e.to_string()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("kde", "Topic kde extends cumulative topic all".to_string()),
+                ("qt", "Topic qt extends missing topic base".to_string()),
+                (
+                    "x",
+                    "Topics extend each other in a cycle: x -> y -> x".to_string()
+                ),
+                (
+                    "y",
+                    "Topics extend each other in a cycle: y -> x -> y".to_string()
+                ),
+            ]
+        );
+
+        let options = ValidateOptions {
+            extends: false,
+            ..Default::default()
+        };
+        assert!(collection.validate(&options).is_ok());
+    }
+
+    #[test]
+    fn test_validate_package_cautions() {
+        let collection = ManifestCollection::from(topics([
+            (
+                "qt".to_string(),
+                conventional("[packages]\nqt-5 = \"5.15.11\""),
+            ),
+            (
+                "kde".to_string(),
+                conventional(
+                    r#"extends = "qt"
+                    per_package_caution.konsole.default = "Restart Konsole"
+                    per_package_caution.qt-5.default = "Restart KDE"
+                    per_package_caution.dolphin.default = "Restart Dolphin"
+                    [packages]
+                    konsole = "23.04.1-1""#,
+                ),
+            ),
+        ]));
+
+        let report = collection.validate(&ValidateOptions::default());
+        assert_eq!(
+            report.errors,
+            [ValidationIssue::UnknownCautionPackages {
+                topic: "kde".to_string(),
+                packages: vec!["dolphin".to_string()],
+            }]
+        );
+        assert_eq!(
+            report.errors[0].to_string(),
+            "Per-package cautions in topic kde for packages it does not update: [\"dolphin\"]"
+        );
+
+        let options = ValidateOptions {
+            package_cautions: false,
+            ..Default::default()
+        };
+        assert!(collection.validate(&options).is_ok());
+    }
+}
@@ -0,0 +1,248 @@
+//! `tumeta add`, creating a new topic manifest from arguments or prompts
+
+use clap::{ArgAction, Args, ValueEnum, ValueHint};
+use eyre::{bail, eyre, Result};
+use log::info;
+
+use topic_manifest::{Locale, Manifest, TopicId};
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use crate::error::Error;
+
+#[derive(Args)]
+pub struct AddArgs {
+    /// Directory of TOML manifests to add the topic to
+    #[arg(short, long, value_hint = ValueHint::DirPath)]
+    src: PathBuf,
+
+    /// Name of the topic, also the file name, prompting for all fields if not given
+    #[arg(short, long, requires = "name")]
+    topic: Option<TopicId>,
+
+    /// Type of the topic
+    #[arg(long = "type", value_enum, default_value_t = TopicType::Conventional)]
+    kind: TopicType,
+
+    /// Default name of the topic shown to users
+    #[arg(long, value_name = "TEXT", requires = "topic")]
+    name: Option<String>,
+
+    /// Name of the topic in a locale, may be given multiple times
+    #[arg(long, value_name = "LANG=TEXT", value_parser = parse_pair, action = ArgAction::Append, requires = "topic")]
+    locale: Vec<(String, String)>,
+
+    /// Mark the conventional topic as a security update
+    #[arg(long, action = ArgAction::SetTrue, requires = "topic")]
+    security: bool,
+
+    /// Default caution of the conventional topic
+    #[arg(long, value_name = "TEXT", requires = "topic")]
+    caution: Option<String>,
+
+    /// Package updated by the conventional topic, `false` as version if removed, may be given multiple times
+    #[arg(long, value_name = "NAME=VERSION", value_parser = parse_pair, action = ArgAction::Append, requires = "topic")]
+    package: Vec<(String, String)>,
+
+    /// Topic included in the cumulative topic, may be given multiple times
+    #[arg(long = "include", value_name = "TOPIC", action = ArgAction::Append, requires = "topic")]
+    includes: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TopicType {
+    Conventional,
+    Cumulative,
+}
+
+/// Fields of the new topic, see [`AddArgs`]
+struct NewTopic {
+    topic: TopicId,
+    kind: TopicType,
+    name: String,
+    locales: Vec<(String, String)>,
+    security: bool,
+    caution: String,
+    packages: Vec<(String, String)>,
+    includes: Vec<String>,
+}
+
+/// Parse `KEY=VALUE`
+fn parse_pair(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got {:?}", s)),
+    }
+}
+
+impl NewTopic {
+    fn from_args(args: AddArgs) -> Result<Self> {
+        let (Some(topic), Some(name)) = (args.topic, args.name) else {
+            bail!("--topic and --name are required unless prompting for fields");
+        };
+        let is_conventional = args.kind == TopicType::Conventional;
+        if is_conventional && !args.includes.is_empty() {
+            bail!("--include only works with --type cumulative");
+        }
+        if !is_conventional && (args.security || args.caution.is_some() || !args.package.is_empty())
+        {
+            bail!("--security, --caution and --package only work with --type conventional");
+        }
+        Ok(Self {
+            topic,
+            kind: args.kind,
+            name,
+            locales: args.locale,
+            security: args.security,
+            caution: args.caution.unwrap_or_default(),
+            packages: args.package,
+            includes: args.includes,
+        })
+    }
+
+    /// Prompt for all fields on stderr, reading answers from `input`
+    fn from_prompts<R: BufRead>(mut input: R) -> Result<Self> {
+        let topic = ask(&mut input, "Topic name, like kde-survey-20231201")?.parse()?;
+        let kind = ask(
+            &mut input,
+            "Type, conventional or cumulative [conventional]",
+        )?;
+        let kind = match kind.as_str() {
+            "" => TopicType::Conventional,
+            kind => TopicType::from_str(kind, true).map_err(|e| eyre!(e))?,
+        };
+        let name = ask(&mut input, "Name shown to users")?;
+        let locales = ask_many(&mut input, "Name in a locale as LANG=TEXT, empty to finish")?
+            .iter()
+            .map(|answer| parse_pair(answer).map_err(|e| eyre!(e)))
+            .collect::<Result<_>>()?;
+
+        let mut new = Self {
+            topic,
+            kind,
+            name,
+            locales,
+            security: false,
+            caution: String::new(),
+            packages: Vec::new(),
+            includes: Vec::new(),
+        };
+        match kind {
+            TopicType::Conventional => {
+                let security = ask(&mut input, "Security update? [y/N]")?;
+                new.security = matches!(security.to_lowercase().as_str(), "y" | "yes");
+                new.caution = ask(&mut input, "Caution, empty for none")?;
+                new.packages = ask_many(
+                    &mut input,
+                    "Package as NAME=VERSION, `false` as version if removed, empty to finish",
+                )?
+                .iter()
+                .map(|answer| parse_pair(answer).map_err(|e| eyre!(e)))
+                .collect::<Result<_>>()?;
+            }
+            TopicType::Cumulative => {
+                new.includes = ask_many(&mut input, "Included topic, empty to finish")?;
+            }
+        }
+        Ok(new)
+    }
+
+    /// Build the manifest, checking all fields by parsing it
+    fn to_manifest(&self) -> Result<Manifest> {
+        if self.name.trim().is_empty() {
+            bail!("Name of the topic must not be empty");
+        }
+        let mut name = toml::Table::new();
+        name.insert("default".into(), self.name.clone().into());
+        for (lang, text) in &self.locales {
+            if !Locale::new(lang).is_valid() {
+                bail!("Invalid locale {}", lang);
+            }
+            name.insert(lang.clone(), text.clone().into());
+        }
+
+        let mut table = toml::Table::new();
+        table.insert("name".into(), name.into());
+        match self.kind {
+            TopicType::Conventional => {
+                let caution =
+                    toml::Table::from_iter([("default".into(), self.caution.clone().into())]);
+                let packages: toml::Table = self
+                    .packages
+                    .iter()
+                    .map(|(name, version)| {
+                        let version = match version.as_str() {
+                            "false" => toml::Value::Boolean(false),
+                            version => toml::Value::String(version.to_string()),
+                        };
+                        (name.clone(), version)
+                    })
+                    .collect();
+                table.insert("security".into(), self.security.into());
+                table.insert("caution".into(), caution.into());
+                table.insert("packages".into(), packages.into());
+            }
+            TopicType::Cumulative => {
+                table.insert("topics".into(), self.includes.clone().into());
+            }
+        }
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+}
+
+/// Ask a question on stderr, returning the trimmed answer
+fn ask<R: BufRead>(input: &mut R, question: &str) -> Result<String> {
+    eprint!("{}: ", question);
+    io::stderr().flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        bail!("Input ended before answering {:?}", question);
+    }
+    Ok(line.trim().to_string())
+}
+
+/// Ask a question repeatedly until an empty answer
+fn ask_many<R: BufRead>(input: &mut R, question: &str) -> Result<Vec<String>> {
+    let mut answers = Vec::new();
+    loop {
+        match ask(input, question)? {
+            answer if answer.is_empty() => return Ok(answers),
+            answer => answers.push(answer),
+        }
+    }
+}
+
+pub fn run(args: AddArgs) -> Result<()> {
+    if !args.src.is_dir() {
+        bail!(
+            "Source directory {} does not exist",
+            args.src.to_string_lossy()
+        );
+    }
+    let src = args.src.clone();
+    let new = if args.topic.is_some() {
+        NewTopic::from_args(args)?
+    } else {
+        NewTopic::from_prompts(io::stdin().lock())?
+    };
+    let content = new.to_manifest()?.to_toml_string();
+
+    let path = src.join(format!("{}.toml", new.topic));
+    if path.exists() {
+        bail!("Topic manifest {} already exists", path.to_string_lossy());
+    }
+    info!("Writing topic {} to {}", new.topic, path.to_string_lossy());
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(Error::io(&path))?;
+    file.write_all(content.as_bytes())
+        .map_err(Error::io(&path))?;
+    info!("Done");
+    Ok(())
+}
@@ -0,0 +1,551 @@
+//! `tumeta compile`, converting TOML manifests to a JSON collection
+
+use clap::{ArgAction, Args, ValueEnum, ValueHint};
+use eyre::{bail, Result};
+use log::{error, info, warn};
+use notify_debouncer_mini::notify::RecursiveMode;
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
+
+use topic_manifest::{
+    LoadOptions, LoadWarning, Manifest, ManifestCollection, OutputFormat, TopicId, TopicSources,
+};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::{SourceArgs, STDIO_PATH};
+use crate::dot;
+use crate::error::Error;
+use crate::report::Report;
+
+#[derive(Args)]
+pub struct CompileArgs {
+    /// Path to a source file in TOML format, or a directory of them, `-` for stdin
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::AnyPath,
+        required_unless_present_any = ["emit_schema"]
+    )]
+    src: Option<PathBuf>,
+
+    /// Path to destination file, `-` for stdout
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::FilePath,
+        required_unless_present_any = ["emit_schema", "check", "diff_against"],
+        requires = "src"
+    )]
+    dst: Option<PathBuf>,
+
+    /// Format of the destination file
+    #[arg(long, value_enum, default_value_t = Format::Json, conflicts_with = "streaming")]
+    format: Format,
+
+    /// Topic name of the manifest read from stdin
+    #[arg(long, value_name = "TOPIC")]
+    topic_name: Option<TopicId>,
+
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Ignore errors
+    #[arg(short, long, action = ArgAction::SetTrue, default_value_t = false)]
+    ignore_error: bool,
+
+    /// Keep whitespace in localized strings as is
+    #[arg(long, action = ArgAction::SetTrue)]
+    no_normalize: bool,
+
+    /// Warn about topic names longer than this many characters
+    #[arg(long, value_name = "LENGTH", conflicts_with = "no_normalize")]
+    max_name_length: Option<usize>,
+
+    /// Warn about topics whose file names do not match their default names
+    #[arg(long, action = ArgAction::SetTrue)]
+    lint_names: bool,
+
+    /// Only keep topics and package versions applicable to this architecture
+    #[arg(long, value_name = "ARCH")]
+    arch: Option<String>,
+
+    /// Write manifests to the destination one by one in source path order, without sorting, checks,
+    /// normalization or resolving extends, to save memory
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["ignore_error", "emit_graph", "lint_names", "max_name_length", "arch"])]
+    streaming: bool,
+
+    /// Only parse and check the source files, without writing anything
+    #[arg(long, action = ArgAction::SetTrue, requires = "src", conflicts_with_all = ["dst", "ignore_error", "streaming", "emit_graph"])]
+    check: bool,
+
+    /// Merge topics into the existing destination file, replacing those with the same names
+    #[arg(long, action = ArgAction::SetTrue, requires = "dst", conflicts_with_all = ["streaming", "check", "diff_against"])]
+    merge: bool,
+
+    /// Remove topics without source files from the destination file when merging, not done if
+    /// any source file was skipped due to errors
+    #[arg(long, action = ArgAction::SetTrue, requires = "merge", conflicts_with = "topic_name")]
+    prune: bool,
+
+    /// Log changes against the existing destination file before writing it
+    #[arg(long, action = ArgAction::SetTrue, requires = "dst", conflicts_with_all = ["streaming", "check", "diff_against"])]
+    diff: bool,
+
+    /// Only log changes against the existing destination file, without writing it
+    #[arg(long, action = ArgAction::SetTrue, requires = "dst", conflicts_with_all = ["streaming", "check", "diff_against"])]
+    diff_only: bool,
+
+    /// Path to write changes against the existing destination file as JSON, implies --diff
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, requires = "dst", conflicts_with_all = ["streaming", "check", "diff_against"])]
+    diff_out: Option<PathBuf>,
+
+    /// Compare topics against a previous destination file instead of writing, failing if any changed
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, requires = "src", conflicts_with_all = ["dst", "streaming", "check", "emit_graph"])]
+    diff_against: Option<PathBuf>,
+
+    /// Log the number of topics and packages, and the size of the destination as JSON, per topic and in total
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "streaming")]
+    stats: bool,
+
+    /// Keep running and write the destination again whenever source files change, until Ctrl-C
+    #[arg(long, action = ArgAction::SetTrue, requires = "dst", conflicts_with_all = ["streaming", "check", "diff_only", "diff_against", "topic_name", "report_json"])]
+    watch: bool,
+
+    /// Path to write problems found as JSON, even if the run fails
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    report_json: Option<PathBuf>,
+
+    /// Path to write the topic dependency graph in Graphviz DOT format
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    emit_graph: Option<PathBuf>,
+
+    /// Path to write the JSON Schema of the destination file
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    emit_schema: Option<PathBuf>,
+}
+
+/// Formats of the destination file
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    JsonCompact,
+    Yaml,
+    Msgpack,
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Json => OutputFormat::Json,
+            Format::JsonCompact => OutputFormat::JsonCompact,
+            Format::Yaml => OutputFormat::Yaml,
+            Format::Msgpack => OutputFormat::MsgPack,
+        }
+    }
+}
+
+pub fn run(mut args: CompileArgs) -> Result<()> {
+    let report_path = args.report_json.take();
+    super::with_report(report_path.as_deref(), |report| compile(args, report))
+}
+
+fn compile(args: CompileArgs, report: &mut Report) -> Result<()> {
+    // Write JSON schema
+    if let Some(schema_path) = &args.emit_schema {
+        info!("Writing JSON schema to {}", schema_path.to_string_lossy());
+        let schema_file = fs::File::create(schema_path).map_err(Error::io(schema_path))?;
+        let mut writer = BufWriter::new(schema_file);
+        serde_json::to_writer_pretty(&mut writer, &ManifestCollection::json_schema())?;
+        writer.flush().map_err(Error::io(schema_path))?;
+    }
+    let Some(src) = &args.src else {
+        return Ok(());
+    };
+    let dst = match &args.dst {
+        Some(dst) => dst.clone(),
+        None if args.check || args.diff_against.is_some() => PathBuf::new(),
+        None => bail!("--dst is required unless --check or --diff-against is given"),
+    };
+
+    let options = args.source.load_options(args.ignore_error);
+
+    // Check src and dst paths
+    let from_stdin = src.as_os_str() == STDIO_PATH;
+    let to_stdout = dst.as_os_str() == STDIO_PATH;
+    if from_stdin {
+        if args.streaming {
+            bail!("Reading from stdin does not work with --streaming, which walks a directory");
+        }
+        if args.topic_name.is_none() {
+            bail!("--topic-name is required when reading from stdin");
+        }
+    } else if args.topic_name.is_some() {
+        bail!("--topic-name only works when reading from stdin with --src -");
+    } else {
+        super::check_src(src, &options)?;
+    }
+    if to_stdout && args.merge {
+        bail!("--merge needs a destination file to merge into");
+    }
+    if to_stdout && (args.diff || args.diff_only || args.diff_out.is_some()) {
+        bail!("--diff needs a destination file to compare against");
+    }
+    let json_dst = matches!(args.format, Format::Json | Format::JsonCompact);
+    if !json_dst && (args.merge || args.diff || args.diff_only || args.diff_out.is_some()) {
+        bail!("--merge and --diff only work with JSON destination files");
+    }
+    if !to_stdout && !args.check && args.diff_against.is_none() {
+        super::check_dst(&dst)?;
+    }
+
+    if args.streaming {
+        info!(
+            "Streaming TOML manifests in {} to {}",
+            src.to_string_lossy(),
+            dst.to_string_lossy()
+        );
+        let mut count = 0;
+        super::write_dst(&dst, |writer| {
+            count = ManifestCollection::write_streaming(src, &options, writer)
+                .map_err(super::report_load_error)?;
+            Ok(())
+        })?;
+        info!("Done, wrote {} entries", count);
+        return Ok(());
+    }
+
+    if args.watch {
+        return watch(&args, src, &dst, &options);
+    }
+    regenerate(&args, src, &dst, &options, report)
+}
+
+/// Debounce delay of changes to source files with `--watch`
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+enum WatchEvent {
+    Changed(DebounceEventResult),
+    Interrupted,
+}
+
+/// Write `dst` again whenever source files in `src` change, until interrupted by Ctrl-C
+///
+/// Errors of each run are logged without stopping the watch.
+fn watch(args: &CompileArgs, src: &Path, dst: &Path, options: &LoadOptions) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let interrupt = tx.clone();
+    ctrlc::set_handler(move || {
+        let _ = interrupt.send(WatchEvent::Interrupted);
+    })?;
+    let mut debouncer = new_debouncer(WATCH_DEBOUNCE, move |result| {
+        let _ = tx.send(WatchEvent::Changed(result));
+    })?;
+    debouncer.watcher().watch(src, RecursiveMode::Recursive)?;
+
+    let run = || {
+        if let Err(error) = regenerate(args, src, dst, options, &mut Report::default()) {
+            error!("{:#}", error);
+        }
+    };
+    let is_source = |path: &Path| {
+        path.extension().is_some_and(|ext| {
+            options
+                .extensions
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        })
+    };
+    run();
+    info!(
+        "Watching {} for changes, press Ctrl-C to stop",
+        src.to_string_lossy()
+    );
+    for event in rx {
+        match event {
+            WatchEvent::Changed(Ok(events)) => {
+                let changed: Vec<&Path> = events
+                    .iter()
+                    .map(|event| event.path.as_path())
+                    .filter(|path| is_source(path))
+                    .collect();
+                if changed.is_empty() {
+                    continue;
+                }
+                for path in changed {
+                    info!("Changed: {}", path.to_string_lossy());
+                }
+                run();
+            }
+            WatchEvent::Changed(Err(error)) => {
+                warn!("Failed to watch {}: {}", src.to_string_lossy(), error)
+            }
+            WatchEvent::Interrupted => break,
+        }
+    }
+    info!("Stopped watching {}", src.to_string_lossy());
+    Ok(())
+}
+
+/// Load manifests in `src`, check them and write them to `dst`, once or on each change with
+/// `--watch`
+fn regenerate(
+    args: &CompileArgs,
+    src: &Path,
+    dst: &Path,
+    options: &LoadOptions,
+    report: &mut Report,
+) -> Result<()> {
+    let (mut manifest, load_warnings, sources) = if let Some(topic) = &args.topic_name {
+        info!("Reading TOML manifest of topic {} from stdin", topic);
+        let content = io::read_to_string(io::stdin()).map_err(Error::io(src))?;
+        let parsed = Manifest::from_toml_str(&content, Path::new("<stdin>"))
+            .map_err(super::report_load_error)?;
+        let manifest = ManifestCollection::from(BTreeMap::from([(topic.clone(), parsed)]));
+        (manifest, Vec::new(), TopicSources::new())
+    } else {
+        super::load(src, options)?
+    };
+    report.push_load_warnings(&load_warnings);
+    report.set_sources(sources);
+    if args.check {
+        info!("Parsed {} topics", manifest.len());
+        if manifest.is_empty() {
+            warn!("No topic manifests found in {}", src.to_string_lossy());
+        }
+    }
+
+    // Merge into the existing destination, so the merged result is checked below
+    if args.merge && dst.exists() {
+        // Skipped files may define any topics, so nothing is known to have no source file
+        let skipped = load_warnings
+            .iter()
+            .filter(|warning| matches!(warning, LoadWarning::Skipped(_)))
+            .count();
+        if args.prune && skipped > 0 {
+            warn!(
+                "Not pruning the destination file, {} source files were skipped",
+                skipped
+            );
+        }
+        let sources = (args.prune && skipped == 0).then(|| {
+            manifest
+                .iter()
+                .map(|(topic, _)| topic.to_string())
+                .collect::<BTreeSet<_>>()
+        });
+        manifest = merge_into(manifest, dst, sources.as_ref())?;
+    }
+
+    // Normalize whitespace in localized strings
+    if !args.no_normalize {
+        super::normalize(&mut manifest, args.max_name_length, report);
+    }
+
+    // Check consistency of the file
+    let validation = super::validate(&manifest, report, args.ignore_error);
+    if !validation.is_ok() && !args.ignore_error {
+        return Err(Error::Inconsistent {
+            errors: validation.errors.len(),
+        }
+        .into());
+    }
+    if args.lint_names {
+        super::lint_names(&manifest, report);
+    }
+
+    // Consumers get the inherited packages of topics extending others
+    match manifest.resolve_extends() {
+        Ok(resolved) => manifest = resolved,
+        // Already reported as validation errors
+        Err(_) if args.ignore_error => warn!("Not resolving extends of topics due to errors"),
+        Err(e) => bail!(e),
+    }
+
+    // Filter topics by architecture
+    if let Some(arch) = &args.arch {
+        manifest.retain_arch(arch);
+        manifest.resolve_arch_packages(arch);
+        info!("Kept {} topics applicable to {}", manifest.len(), arch);
+    }
+
+    if args.stats {
+        log_stats(&manifest);
+    }
+
+    if args.check {
+        info!("Done, no errors found");
+        return Ok(());
+    }
+
+    // Log changes against the existing destination
+    if args.diff || args.diff_only || args.diff_out.is_some() {
+        log_changes(&manifest, dst, args.diff_out.as_deref())?;
+        if args.diff_only {
+            info!("Done");
+            return Ok(());
+        }
+    }
+
+    // Compare against the previous output instead of writing
+    if let Some(old_path) = &args.diff_against {
+        return diff_against(&manifest, old_path);
+    }
+
+    // Write dependency graph
+    if let Some(graph_path) = &args.emit_graph {
+        info!(
+            "Writing dependency graph to {}",
+            graph_path.to_string_lossy()
+        );
+        let graph_file = fs::File::create(graph_path).map_err(Error::io(graph_path))?;
+        let mut writer = BufWriter::new(graph_file);
+        dot::write_dot(&manifest, &mut writer)?;
+        writer.flush().map_err(Error::io(graph_path))?;
+    }
+
+    // Write to dst file
+    info!(
+        "Writing {} entries to {}",
+        manifest.len(),
+        dst.to_string_lossy()
+    );
+    super::write_dst(dst, |writer| {
+        Ok(manifest.to_writer(args.format.into(), writer)?)
+    })?;
+    info!("Done");
+
+    Ok(())
+}
+
+/// Log sizes of topics and the whole collection as JSON, see [`ManifestCollection::stats`]
+fn log_stats(manifest: &ManifestCollection) {
+    let stats = manifest.stats();
+    for (topic, topic_stats) in &stats.per_topic {
+        info!(
+            "{}: {} packages, {} bytes as compact JSON",
+            topic, topic_stats.packages, topic_stats.json_compact_bytes
+        );
+    }
+    info!(
+        "Stats: {} topics, {} packages, {} bytes as JSON, {} bytes as compact JSON",
+        stats.topics, stats.packages, stats.json_bytes, stats.json_compact_bytes
+    );
+}
+
+/// Merge `manifest` into the collection previously written to `dst`
+///
+/// With `sources`, names of topics having source files, other topics in `dst` are removed.
+fn merge_into(
+    manifest: ManifestCollection,
+    dst: &Path,
+    sources: Option<&BTreeSet<String>>,
+) -> Result<ManifestCollection> {
+    let mut merged = super::read_collection(dst)?;
+    if let Some(sources) = sources {
+        let count = merged.len();
+        merged.retain(|topic, _| sources.contains(topic));
+        info!(
+            "Pruned {} topics without source files from {}",
+            count - merged.len(),
+            dst.to_string_lossy()
+        );
+    }
+    let diff = merged.merge(manifest);
+    info!(
+        "Merged into {}: {} topics added, {} changed",
+        dst.to_string_lossy(),
+        diff.added.len(),
+        diff.changed.len()
+    );
+    Ok(merged)
+}
+
+/// Log changes against the collection previously written to `dst`, if any
+///
+/// Changes are also written to `diff_out` as JSON, see [`topic_manifest::CollectionDiff`].
+fn log_changes(manifest: &ManifestCollection, dst: &Path, diff_out: Option<&Path>) -> Result<()> {
+    let old = if dst.exists() {
+        super::read_collection(dst)?
+    } else {
+        ManifestCollection::default()
+    };
+    let diff = manifest.diff(&old);
+    info!(
+        "Changes against {}: {} topics added, {} removed, {} changed",
+        dst.to_string_lossy(),
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+    for topic in &diff.added {
+        info!("+ {}", topic);
+    }
+    for topic in &diff.removed {
+        info!("- {}", topic);
+    }
+    for topic in &diff.changed {
+        info!("~ {}", topic);
+        let Some(packages) = diff.packages.get(topic) else {
+            continue;
+        };
+        for (name, version) in &packages.added {
+            info!("    + {} {}", name, version);
+        }
+        for (name, (old, new)) in &packages.updated {
+            let old = old.as_deref().unwrap_or("(removed)");
+            info!("    ~ {} {} -> {}", name, old, new);
+        }
+        for name in &packages.removed {
+            info!("    - {}", name);
+        }
+        for name in &packages.deleted {
+            info!("    {} is no longer listed", name);
+        }
+    }
+
+    if let Some(path) = diff_out {
+        info!("Writing changes to {}", path.to_string_lossy());
+        let mut writer = super::open_dst(path).map_err(Error::io(path))?;
+        serde_json::to_writer_pretty(&mut writer, &diff)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Print topics added, removed or changed since the previous output in `old_path`
+///
+/// Fails if there is any difference, so the output can be checked for staleness.
+fn diff_against(manifest: &ManifestCollection, old_path: &Path) -> Result<()> {
+    info!("Comparing against {}", old_path.to_string_lossy());
+    let old = super::read_collection(old_path)?;
+    let diff = manifest.diff(&old);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    for (mark, topics) in [
+        ("+", &diff.added),
+        ("-", &diff.removed),
+        ("~", &diff.changed),
+    ] {
+        for topic in topics {
+            writeln!(out, "{} {}", mark, topic)?;
+        }
+    }
+    out.flush()?;
+
+    if !diff.is_empty() {
+        bail!(
+            "{} is outdated: {} topics added, {} removed, {} changed",
+            old_path.to_string_lossy(),
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len()
+        );
+    }
+    info!("Done, no changes against {}", old_path.to_string_lossy());
+    Ok(())
+}
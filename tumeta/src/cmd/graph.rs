@@ -0,0 +1,44 @@
+//! `tumeta graph`, writing the topic dependency graph
+
+use clap::{ArgAction, Args, ValueHint};
+use eyre::Result;
+use log::info;
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::{SourceArgs, STDIO_PATH};
+use crate::dot;
+
+#[derive(Args)]
+pub struct GraphArgs {
+    /// Path to a source file in TOML format, or a directory of them
+    #[arg(short, long, value_hint = ValueHint::AnyPath)]
+    src: PathBuf,
+
+    /// Path to write the graph in Graphviz DOT format, `-` for stdout
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    dst: PathBuf,
+
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Skip source files failing to parse
+    #[arg(short, long, action = ArgAction::SetTrue)]
+    ignore_error: bool,
+}
+
+pub fn run(args: GraphArgs) -> Result<()> {
+    let options = args.source.load_options(args.ignore_error);
+    super::check_src(&args.src, &options)?;
+    if args.dst.as_os_str() != STDIO_PATH {
+        super::check_dst(&args.dst)?;
+    }
+    let manifest = super::load(&args.src, &options)?.0;
+
+    info!("Writing dependency graph to {}", args.dst.to_string_lossy());
+    let mut writer = super::open_dst(&args.dst)?;
+    dot::write_dot(&manifest, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
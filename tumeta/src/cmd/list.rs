@@ -0,0 +1,133 @@
+//! `tumeta list`, printing a table of topics in TOML manifests
+
+use clap::{Args, ValueEnum, ValueHint};
+use eyre::Result;
+use serde_json::json;
+
+use topic_manifest::{Manifest, ManifestCollection};
+
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use super::SourceArgs;
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Path to a source file in TOML format, or a directory of them
+    #[arg(short, long, value_hint = ValueHint::AnyPath)]
+    src: PathBuf,
+
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Format of the list
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+}
+
+/// Formats of the list
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+}
+
+/// A row of the list
+struct Row<'a> {
+    topic: &'a str,
+    kind: &'static str,
+    /// `None` for cumulative topics
+    security: Option<bool>,
+    /// Packages of conventional topics, or topics of cumulative topics
+    count: usize,
+    date: Option<String>,
+}
+
+impl<'a> Row<'a> {
+    fn new(topic: &'a str, manifest: &Manifest) -> Self {
+        let (kind, security, count) = match manifest {
+            Manifest::Conventional(c) => (
+                "conventional",
+                Some(c.is_security_update()),
+                c.get_packages().len(),
+            ),
+            Manifest::Cumulative(c) => ("cumulative", None, c.get_topics().len()),
+        };
+        Self {
+            topic,
+            kind,
+            security,
+            count,
+            date: manifest.get_date().map(|date| date.to_string()),
+        }
+    }
+
+    fn cells(&self) -> [String; 5] {
+        [
+            self.topic.to_string(),
+            self.kind.to_string(),
+            match self.security {
+                Some(true) => "yes".to_string(),
+                Some(false) => "no".to_string(),
+                None => "-".to_string(),
+            },
+            self.count.to_string(),
+            self.date.clone().unwrap_or_else(|| "-".to_string()),
+        ]
+    }
+}
+
+pub fn run(args: ListArgs) -> Result<()> {
+    let options = args.source.load_options(false);
+    super::check_src(&args.src, &options)?;
+    let manifest = super::load(&args.src, &options)?.0;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    match args.format {
+        Format::Table => write_table(&manifest, &mut out)?,
+        Format::Json => {
+            let rows: Vec<_> = manifest
+                .iter()
+                .map(|(topic, manifest)| {
+                    let row = Row::new(topic, manifest);
+                    json!({
+                        "topic": row.topic,
+                        "type": row.kind,
+                        "security": row.security,
+                        "packages": row.count,
+                        "date": row.date,
+                    })
+                })
+                .collect();
+            serde_json::to_writer_pretty(&mut out, &rows)?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Write topics as a table with aligned columns
+///
+/// For cumulative topics, the package column counts the topics they consist of.
+fn write_table<W: Write>(manifest: &ManifestCollection, mut out: W) -> io::Result<()> {
+    let header = ["TOPIC", "TYPE", "SECURITY", "PACKAGES", "DATE"].map(str::to_string);
+    let rows: Vec<[String; 5]> = std::iter::once(header)
+        .chain(manifest.iter().map(|(topic, m)| Row::new(topic, m).cells()))
+        .collect();
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in &rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! Subcommands of tumeta, each with its own arguments
+
+pub mod add;
+pub mod compile;
+pub mod graph;
+pub mod list;
+pub mod packages;
+pub mod show;
+pub mod validate;
+
+use clap::{ArgAction, Args};
+use eyre::{bail, eyre, Result};
+use log::{debug, error, info, log, warn, Level};
+
+use topic_manifest::{
+    topic_matches_name, LoadError, LoadOptions, LoadWarning, ManifestCollection, NormalizeOptions,
+    TopicSources, ValidateOptions, ValidationIssue, ValidationReport,
+};
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::Error;
+use crate::report::{Kind, Report, Severity};
+
+pub static STDIO_PATH: &str = "-";
+
+// Options of finding source files, shared by subcommands reading them
+#[derive(Args)]
+pub struct SourceArgs {
+    /// Extension of source files, may be given multiple times
+    #[arg(long = "ext", value_name = "EXT", default_value = "toml", action = ArgAction::Append)]
+    extensions: Vec<String>,
+
+    /// Skip source files matching this glob, relative to the source directory, may be given multiple times
+    #[arg(long, value_name = "GLOB", action = ArgAction::Append)]
+    exclude: Vec<String>,
+
+    /// Load source files matching this glob even if excluded, may be given multiple times
+    #[arg(long, value_name = "GLOB", action = ArgAction::Append)]
+    include: Vec<String>,
+
+    /// Skip empty source files instead of failing
+    #[arg(long, action = ArgAction::SetTrue)]
+    skip_empty: bool,
+
+    /// Number of threads searching directories and parsing source files, 1 to do it one by one, one per CPU if not given
+    #[arg(long, value_name = "N", env = "TUMETA_JOBS", value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: Option<u32>,
+}
+
+impl SourceArgs {
+    pub fn load_options(&self, ignore_errors: bool) -> LoadOptions {
+        LoadOptions {
+            ignore_errors,
+            skip_empty: self.skip_empty,
+            exclude: self.exclude.clone(),
+            include: self.include.clone(),
+            jobs: self.jobs.map(|jobs| jobs as usize),
+            extensions: self
+                .extensions
+                .iter()
+                .map(|ext| ext.trim_start_matches('.').to_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Check that the source path exists, and is a manifest if it is a file
+pub fn check_src(src: &Path, options: &LoadOptions) -> Result<()> {
+    if !src.exists() {
+        bail!("Source path {} does not exist", src.to_string_lossy());
+    } else if src.is_file()
+        && !src.extension().is_some_and(|ext| {
+            options
+                .extensions
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        })
+    {
+        bail!(
+            "Source file {} is not a TOML manifest",
+            src.to_string_lossy()
+        );
+    }
+    Ok(())
+}
+
+/// Load manifests in `src` along with their files, logging load warnings and parsed topics
+pub fn load(
+    src: &Path,
+    options: &LoadOptions,
+) -> Result<(ManifestCollection, Vec<LoadWarning>, TopicSources)> {
+    info!("Searching for TOML manifests in {}", src.to_string_lossy());
+    debug!("Parsing jobs: {}", options.effective_jobs());
+    let (manifest, sources, load_warnings) =
+        ManifestCollection::load_from_dir_with_sources(src, options).map_err(report_load_error)?;
+    log_load_warnings(&load_warnings);
+    for (name, topic) in manifest.iter() {
+        debug!("Parsed {}: {}", name, topic.summary());
+    }
+    Ok((manifest, load_warnings, sources))
+}
+
+/// Run `f`, then write the findings it recorded to `report_path` as JSON, even if it fails
+pub fn with_report<F>(report_path: Option<&Path>, f: F) -> Result<()>
+where
+    F: FnOnce(&mut Report) -> Result<()>,
+{
+    let mut report = Report::default();
+    let result = f(&mut report);
+    if let Some(path) = report_path {
+        if let Err(error) = &result {
+            report.push_error(error);
+        }
+        info!("Writing report to {}", path.to_string_lossy());
+        report.write(path).map_err(Error::io(path))?;
+    }
+    result
+}
+
+/// Read a collection previously written as JSON
+pub fn read_collection(path: &Path) -> Result<ManifestCollection> {
+    let content = fs::read_to_string(path).map_err(Error::io(path))?;
+    ManifestCollection::from_json_str(&content)
+        .map_err(|e| eyre!("Failed to parse {}: {}", path.to_string_lossy(), e))
+}
+
+/// Log load warnings, those of files skipped due to errors as errors
+pub fn log_load_warnings(warnings: &[LoadWarning]) {
+    for warning in warnings {
+        match warning {
+            LoadWarning::Skipped(_) => error!("{}", warning),
+            _ => warn!("{}", warning),
+        }
+    }
+}
+
+/// Log parse errors along with the offending source lines, then turn `err` into a report
+pub fn report_load_error(err: LoadError) -> eyre::Report {
+    if matches!(err, LoadError::Parse { .. } | LoadError::Multiple { .. }) {
+        for error in err.get_errors() {
+            error!("{:#}", error);
+        }
+    }
+    Error::Load(err).into()
+}
+
+/// Normalize whitespace in localized strings, warning about names longer than `max_name_length`
+pub fn normalize(
+    manifest: &mut ManifestCollection,
+    max_name_length: Option<usize>,
+    report: &mut Report,
+) {
+    let options = NormalizeOptions { max_name_length };
+    for (topic, too_long) in manifest.normalize_all(&options) {
+        for name in too_long {
+            let locale = match &name.locale {
+                Some(locale) => locale.to_string(),
+                None => "default".to_string(),
+            };
+            let message = format!(
+                "Topic {}: name.{} is {} characters long",
+                topic, locale, name.length
+            );
+            warn!("{}", message);
+            report.push_topic(Severity::Warning, Kind::Lint, &topic, message);
+        }
+    }
+}
+
+/// Check consistency of the collection, logging all issues found
+///
+/// Issues are also recorded in `report`, errors as warnings if `ignore_errors` is set.
+pub fn validate(
+    manifest: &ManifestCollection,
+    report: &mut Report,
+    ignore_errors: bool,
+) -> ValidationReport {
+    let validation = manifest.validate(&ValidateOptions::default());
+    for issue in &validation.warnings {
+        log_issue(Level::Warn, issue);
+    }
+    for issue in &validation.errors {
+        log_issue(Level::Error, issue);
+    }
+    report.push_validation(&validation, ignore_errors);
+    validation
+}
+
+/// Log a consistency issue, with the topic and missing topics as structured fields
+fn log_issue(level: Level, issue: &ValidationIssue) {
+    match issue {
+        ValidationIssue::MissingTopics { topic, missing } => {
+            log!(level, topic = topic.as_str(), missing:? = missing; "{}", issue)
+        }
+        _ => log!(level, topic = issue.get_topic(); "{}", issue),
+    }
+}
+
+/// Warn about topics whose names do not match their default names
+pub fn lint_names(manifest: &ManifestCollection, report: &mut Report) {
+    for (topic, name) in manifest.find_name_mismatches(topic_matches_name) {
+        let message = format!("Topic {} does not match its name {:?}", topic, name);
+        warn!("{}", message);
+        report.push_topic(Severity::Warning, Kind::Lint, &topic, message);
+    }
+}
+
+/// Check that the destination file can be created
+pub fn check_dst(dst: &Path) -> Result<()> {
+    if dst.is_dir() {
+        bail!(
+            "Invalid destination path {}: destination could not be an existing directory",
+            dst.to_string_lossy()
+        );
+    }
+    let dst_parent = dst
+        .parent()
+        .ok_or(eyre!("Failed to get parent path for dst path"))?;
+    // A bare filename has an empty parent, which is the current directory
+    if !dst_parent.as_os_str().is_empty() && !dst_parent.exists() {
+        bail!(
+            "Parent path of the destination {} does not exist",
+            dst_parent.to_string_lossy()
+        );
+    }
+    Ok(())
+}
+
+/// Write the destination with `write`, `-` stands for stdout
+///
+/// Files are written to a temporary file next to `dst`, which then replaces it, so readers never
+/// see a partially written destination.
+pub fn write_dst<F>(dst: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    if dst.as_os_str() == STDIO_PATH {
+        let mut writer = BufWriter::new(io::stdout().lock());
+        write(&mut writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+    let Some(name) = dst.file_name() else {
+        bail!("Invalid destination path {}", dst.to_string_lossy());
+    };
+    let tmp = dst.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+    let file = fs::File::create(&tmp).map_err(Error::io(&tmp))?;
+    let mut writer = BufWriter::new(file);
+    let result = write(&mut writer).and_then(|()| Ok(writer.flush().map_err(Error::io(&tmp))?));
+    drop(writer);
+    if let Err(error) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(error);
+    }
+    fs::rename(&tmp, dst).map_err(Error::io(dst))?;
+    Ok(())
+}
+
+/// Open the destination for writing, `-` stands for stdout
+pub fn open_dst(dst: &Path) -> io::Result<Box<dyn Write>> {
+    if dst.as_os_str() == STDIO_PATH {
+        return Ok(Box::new(BufWriter::new(io::stdout().lock())));
+    }
+    let out_file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dst)?;
+    Ok(Box::new(BufWriter::new(out_file)))
+}
@@ -0,0 +1,29 @@
+//! `tumeta packages`, printing the packages updated by topics
+
+use clap::{Args, ValueHint};
+use eyre::Result;
+
+use topic_manifest::{LoadOptions, ManifestCollection};
+
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct PackagesArgs {
+    /// Path to a source file in TOML format, or a directory of them
+    #[arg(value_hint = ValueHint::AnyPath)]
+    src: PathBuf,
+}
+
+pub fn run(args: PackagesArgs) -> Result<()> {
+    let (manifest, load_warnings) =
+        ManifestCollection::load_from_dir(&args.src, &LoadOptions::default())
+            .map_err(super::report_load_error)?;
+    super::log_load_warnings(&load_warnings);
+    let mut out = BufWriter::new(io::stdout().lock());
+    for name in manifest.all_packages() {
+        writeln!(out, "{}", name)?;
+    }
+    out.flush()?;
+    Ok(())
+}
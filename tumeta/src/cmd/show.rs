@@ -0,0 +1,180 @@
+//! `tumeta show`, printing all fields of a single topic
+
+use clap::{Args, ValueHint};
+use eyre::{bail, Result};
+
+use topic_manifest::{Conventional, Cumulative, Localized, Manifest, Packages};
+
+use std::collections::BTreeMap;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use super::SourceArgs;
+
+#[derive(Args)]
+pub struct ShowArgs {
+    /// Path to a source file in TOML format, or a directory of them
+    #[arg(short, long, value_hint = ValueHint::AnyPath)]
+    src: PathBuf,
+
+    /// Name of the topic to show
+    #[arg(short, long)]
+    topic: String,
+
+    #[command(flatten)]
+    source: SourceArgs,
+}
+
+/// Labeled fields of a topic, printed with aligned values
+#[derive(Default)]
+struct Fields(Vec<(String, String)>);
+
+impl Fields {
+    fn push<L: Into<String>, V: Into<String>>(&mut self, label: L, value: V) {
+        self.0.push((label.into(), value.into()));
+    }
+
+    /// Push the default value as `label`, and each translation as `label (locale)`
+    fn push_localized(&mut self, label: &str, localized: &Localized<String>) {
+        if let Some(default) = localized.get_default() {
+            self.push(label, default);
+        }
+        for (locale, content) in &localized.content {
+            self.push(format!("{} ({})", label, locale), content);
+        }
+    }
+
+    fn push_arch(&mut self, arch: Option<&[String]>) {
+        if let Some(arch) = arch {
+            self.push("Arch", arch.join(", "));
+        }
+    }
+
+    fn push_date(&mut self, manifest: &Manifest) {
+        if let Some(date) = manifest.get_date() {
+            self.push("Date", date.to_string());
+        }
+    }
+
+    /// Write fields, indenting continuation lines of multi-line values
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let width = self
+            .0
+            .iter()
+            .map(|(label, _)| label.chars().count() + 1)
+            .max()
+            .unwrap_or_default();
+        for (label, value) in &self.0 {
+            let label = format!("{}:", label);
+            let value = value.replace('\n', &format!("\n{:width$}  ", "", width = width));
+            let line = format!("{:<width$}  {}", label, value, width = width);
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+pub fn run(args: ShowArgs) -> Result<()> {
+    let options = args.source.load_options(false);
+    super::check_src(&args.src, &options)?;
+    let manifest = super::load(&args.src, &options)?.0;
+    let Some(topic) = manifest.get(&args.topic) else {
+        bail!(
+            "Topic {} is not found in {}",
+            args.topic,
+            args.src.to_string_lossy()
+        );
+    };
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut fields = Fields::default();
+    fields.push("Topic", args.topic.as_str());
+    match topic {
+        Manifest::Conventional(c) => show_conventional(c, topic, fields, &mut out)?,
+        Manifest::Cumulative(c) => show_cumulative(c, topic, fields, &mut out)?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn show_conventional<W: Write>(
+    c: &Conventional,
+    topic: &Manifest,
+    mut fields: Fields,
+    mut out: W,
+) -> io::Result<()> {
+    fields.push("Type", "conventional");
+    fields.push_localized("Name", c.get_name());
+    fields.push(
+        "Security",
+        if c.is_security_update() { "yes" } else { "no" },
+    );
+    if let Some(severity) = c.get_severity() {
+        fields.push("Severity", severity.as_str());
+    }
+    fields.push_localized("Caution", c.get_caution());
+    for (package, caution) in c.get_per_package_caution().into_iter().flatten() {
+        fields.push_localized(&format!("Caution for {}", package), caution);
+    }
+    if let Some(url) = c.get_url() {
+        fields.push("URL", url);
+    }
+    fields.push_arch(c.get_arch());
+    fields.push_date(topic);
+    fields.write(&mut out)?;
+
+    writeln!(out, "\nPackages:")?;
+    write_packages(c.get_packages(), &mut out)?;
+    Ok(())
+}
+
+fn show_cumulative<W: Write>(
+    c: &Cumulative,
+    topic: &Manifest,
+    mut fields: Fields,
+    mut out: W,
+) -> io::Result<()> {
+    fields.push("Type", "cumulative");
+    fields.push_localized("Name", c.get_name());
+    fields.push_arch(c.get_arch());
+    fields.push_date(topic);
+    fields.write(&mut out)?;
+
+    writeln!(out, "\nTopics:")?;
+    for topic in c.get_topics() {
+        writeln!(out, "    {}", topic)?;
+    }
+    Ok(())
+}
+
+/// Write packages sorted by name with their versions, `(removed)` for packages removed by the topic
+fn write_packages<W: Write>(packages: &Packages, mut out: W) -> io::Result<()> {
+    let rows: BTreeMap<&str, String> = packages
+        .iter()
+        .map(|(name, version)| (name, version.unwrap_or("(removed)").to_string()))
+        .chain(packages.arch_specific().map(|(name, versions)| {
+            let default = packages.get(name).map(|version| ("default", version));
+            let versions: Vec<String> = default
+                .into_iter()
+                .chain(
+                    versions
+                        .iter()
+                        .map(|(arch, version)| (arch.as_str(), version)),
+                )
+                .map(|(arch, version)| {
+                    format!("{}: {}", arch, version.as_deref().unwrap_or("(removed)"))
+                })
+                .collect();
+            (name, versions.join(", "))
+        }))
+        .collect();
+    let width = rows
+        .keys()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or_default();
+    for (name, version) in rows {
+        writeln!(out, "    {:<width$}  {}", name, version, width = width)?;
+    }
+    Ok(())
+}
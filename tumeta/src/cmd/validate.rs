@@ -0,0 +1,65 @@
+//! `tumeta validate`, checking TOML manifests without writing anything
+
+use clap::{ArgAction, Args, ValueHint};
+use eyre::Result;
+use log::{info, warn};
+
+use std::path::PathBuf;
+
+use super::SourceArgs;
+use crate::error::Error;
+use crate::report::Report;
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// Path to a source file in TOML format, or a directory of them
+    #[arg(short, long, value_hint = ValueHint::AnyPath)]
+    src: PathBuf,
+
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Warn about topic names longer than this many characters
+    #[arg(long, value_name = "LENGTH")]
+    max_name_length: Option<usize>,
+
+    /// Warn about topics whose file names do not match their default names
+    #[arg(long, action = ArgAction::SetTrue)]
+    lint_names: bool,
+
+    /// Path to write problems found as JSON, even if validation fails
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    report_json: Option<PathBuf>,
+}
+
+pub fn run(args: ValidateArgs) -> Result<()> {
+    super::with_report(args.report_json.as_deref(), |report| {
+        validate(&args, report)
+    })
+}
+
+fn validate(args: &ValidateArgs, report: &mut Report) -> Result<()> {
+    let options = args.source.load_options(false);
+    super::check_src(&args.src, &options)?;
+    let (mut manifest, load_warnings, sources) = super::load(&args.src, &options)?;
+    report.push_load_warnings(&load_warnings);
+    report.set_sources(sources);
+    info!("Parsed {} topics", manifest.len());
+    if manifest.is_empty() {
+        warn!("No topic manifests found in {}", args.src.to_string_lossy());
+    }
+
+    super::normalize(&mut manifest, args.max_name_length, report);
+    let validation = super::validate(&manifest, report, false);
+    if !validation.is_ok() {
+        return Err(Error::Inconsistent {
+            errors: validation.errors.len(),
+        }
+        .into());
+    }
+    if args.lint_names {
+        super::lint_names(&manifest, report);
+    }
+    info!("Done, no errors found");
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Graphviz output of the topic dependency graph
+
+use topic_manifest::{DependencyGraph, Manifest, ManifestCollection};
+
+use std::io::{Result, Write};
+
+/// Quote a string as a DOT identifier
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Write the dependency graph of the collection in DOT format
+///
+/// Conventional topics are rendered as boxes, cumulative topics as diamonds, and security updates
+/// are highlighted in red. Topics referenced but missing from the collection are dashed ellipses.
+pub fn write_dot<W: Write>(manifest: &ManifestCollection, mut writer: W) -> Result<()> {
+    let graph = DependencyGraph::from_collection(manifest);
+
+    writeln!(writer, "digraph topics {{")?;
+    for node in graph.nodes() {
+        let attrs = match manifest.get(node) {
+            Some(Manifest::Conventional(c)) if c.is_security_update() => {
+                "shape=box, color=red, fontcolor=red"
+            }
+            Some(Manifest::Conventional(_)) => "shape=box",
+            Some(Manifest::Cumulative(_)) => "shape=diamond",
+            None => "shape=ellipse, style=dashed",
+        };
+        writeln!(writer, "    {} [{}];", quote(node), attrs)?;
+    }
+    for (from, to) in graph.edges() {
+        writeln!(writer, "    {} -> {};", quote(from), quote(to))?;
+    }
+    writeln!(writer, "}}")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use topic_manifest::{Manifest, ManifestCollection};
+
+    use std::collections::BTreeMap;
+
+    use super::write_dot;
+
+    #[test]
+    fn test_dot() {
+        let security: Manifest = toml::from_str(
+            r#"
+            name.default = "Security"
+            security = true
+            caution.default = ""
+            [packages]
+            "#,
+        )
+        .unwrap();
+        let cumulative: Manifest = toml::from_str(
+            r#"
+            name.default = "Cumulative"
+            topics = ["sec", "missing\"topic"]
+            "#,
+        )
+        .unwrap();
+        let collection = ManifestCollection::try_from(BTreeMap::from([
+            ("sec".to_string(), security),
+            ("cumulative".to_string(), cumulative),
+        ]))
+        .unwrap();
+
+        let mut out = Vec::new();
+        write_dot(&collection, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"digraph topics {
+    "cumulative" [shape=diamond];
+    "missing\"topic" [shape=ellipse, style=dashed];
+    "sec" [shape=box, color=red, fontcolor=red];
+    "cumulative" -> "missing\"topic";
+    "cumulative" -> "sec";
+}
+"#
+        );
+    }
+}
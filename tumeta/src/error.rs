@@ -0,0 +1,96 @@
+//! Errors failing a run, distinguished by the exit code of the process
+
+use topic_manifest::LoadError;
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Exit code of errors not covered by [`Error`], like a source directory not found
+///
+/// Invalid arguments exit with 2 instead, as clap does.
+pub const EXIT_OTHER: u8 = 1;
+/// Exit code of manifests failing to parse, `EX_DATAERR` of sysexits
+pub const EXIT_PARSE: u8 = 65;
+/// Exit code of inconsistent manifests
+pub const EXIT_INCONSISTENT: u8 = 3;
+/// Exit code of failing to read or write files, `EX_IOERR` of sysexits
+pub const EXIT_IO: u8 = 74;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Manifests failed to load, mostly due to parse errors
+    Load(LoadError),
+    /// Consistency checks found `errors` errors in the manifests
+    Inconsistent { errors: usize },
+    /// Failed to read or write a file
+    Io { path: PathBuf, error: io::Error },
+}
+
+impl Error {
+    /// Wrap an I/O error on `path`, for use with [`Result::map_err`]
+    pub fn io(path: &Path) -> impl FnOnce(io::Error) -> Self + '_ {
+        move |error| Self::Io {
+            path: path.to_path_buf(),
+            error,
+        }
+    }
+
+    /// Exit code of the process failing with this error
+    ///
+    /// Manifests failing to load only because they could not be read count as I/O errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Load(error) => {
+                let is_io =
+                    |e: &LoadError| matches!(e, LoadError::Io { .. } | LoadError::Write { .. });
+                if error.get_errors().iter().all(is_io) {
+                    EXIT_IO
+                } else {
+                    EXIT_PARSE
+                }
+            }
+            Self::Inconsistent { .. } => EXIT_INCONSISTENT,
+            Self::Io { .. } => EXIT_IO,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load(error) => write!(f, "{}", error),
+            Self::Inconsistent { errors } => write!(
+                f,
+                "Topic manifests are inconsistent: found {} errors",
+                errors
+            ),
+            Self::Io { path, error } => {
+                write!(f, "Failed to access {}: {}", path.to_string_lossy(), error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<LoadError> for Error {
+    fn from(error: LoadError) -> Self {
+        Self::Load(error)
+    }
+}
+
+/// Exit code of a run failing with `report`
+///
+/// Besides [`Error`], bare I/O errors like failing to write to stdout count as I/O errors.
+pub fn exit_code(report: &eyre::Report) -> ExitCode {
+    let code = if let Some(error) = report.downcast_ref::<Error>() {
+        error.exit_code()
+    } else if report.downcast_ref::<io::Error>().is_some() {
+        EXIT_IO
+    } else {
+        EXIT_OTHER
+    };
+    ExitCode::from(code)
+}
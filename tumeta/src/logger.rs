@@ -0,0 +1,76 @@
+//! Logger setup, either human-readable or JSON lines for log aggregation
+
+use clap::ValueEnum;
+use env_logger::fmt::Formatter;
+use log::kv::{self, Key, Value, VisitSource};
+use log::Record;
+use serde_json::{Map, Value as JsonValue};
+
+use std::io::{self, Write};
+
+/// Formats of log messages
+///
+/// JSON lines carry the timestamp, level, target and message of each record, along with its
+/// key-value pairs as structured fields.
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+/// Initialize the global logger, filtered by the directives in environment variable `env`
+pub fn init(format: LogFormat, env: &str) {
+    let mut builder = match format {
+        LogFormat::Pretty => pretty_env_logger::formatted_builder(),
+        LogFormat::Json => {
+            let mut builder = env_logger::Builder::new();
+            builder.format(write_json);
+            builder
+        }
+    };
+    builder.parse_env(env).init();
+}
+
+/// Collects key-value pairs of a record into a JSON object
+struct Fields<'a>(&'a mut Map<String, JsonValue>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.insert(key.to_string(), to_json(&value));
+        Ok(())
+    }
+}
+
+/// Convert a logged value to JSON
+///
+/// Besides strings, numbers and booleans, values whose formatted form is valid JSON (such as
+/// lists of strings captured with `:?`) are kept structured, and anything else becomes a string.
+fn to_json(value: &Value) -> JsonValue {
+    if let Some(s) = value.to_borrowed_str() {
+        JsonValue::from(s)
+    } else if let Some(b) = value.to_bool() {
+        JsonValue::from(b)
+    } else if let Some(n) = value.to_u64() {
+        JsonValue::from(n)
+    } else if let Some(n) = value.to_i64() {
+        JsonValue::from(n)
+    } else {
+        let s = value.to_string();
+        serde_json::from_str(&s).unwrap_or(JsonValue::String(s))
+    }
+}
+
+fn write_json(buf: &mut Formatter, record: &Record) -> io::Result<()> {
+    let mut object = Map::new();
+    object.insert("timestamp".into(), buf.timestamp().to_string().into());
+    object.insert("level".into(), record.level().as_str().into());
+    object.insert("target".into(), record.target().into());
+    object.insert("message".into(), record.args().to_string().into());
+    record
+        .key_values()
+        .visit(&mut Fields(&mut object))
+        .map_err(io::Error::other)?;
+    serde_json::to_writer(&mut *buf, &object)?;
+    writeln!(buf)
+}
@@ -1,139 +1,125 @@
-use clap::{Parser, ArgAction};
-use eyre::{bail, eyre, Report, Result};
-use log::{debug, error, info};
-use rayon::prelude::*;
+mod cmd;
+mod dot;
+mod error;
+mod logger;
+mod report;
 
-use topic_manifest::{Manifest, ManifestCollection};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_complete::Shell;
+use eyre::Result;
 
-use std::collections::BTreeMap;
 use std::env;
-use std::fs;
-use std::io::BufWriter;
-use std::path::PathBuf;
+use std::io;
+use std::process::ExitCode;
+
+use cmd::add::AddArgs;
+use cmd::compile::CompileArgs;
+use cmd::graph::GraphArgs;
+use cmd::list::ListArgs;
+use cmd::packages::PackagesArgs;
+use cmd::show::ShowArgs;
+use cmd::validate::ValidateArgs;
+use logger::LogFormat;
 
 static ENV_LOG: &str = "TUMETA_LOG";
 static ENV_LOG_DEFAULT: &str = "info";
 
+// Without a subcommand, the arguments of `tumeta compile` are accepted for compatibility
 #[derive(Parser)]
-#[command(author, version, about)]
+#[command(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Args {
-    /// Path to source file(s) in TOML format
-    #[arg(short, long)]
-    src: PathBuf,
-
-    /// Path to destination JSON file
-    #[arg(short, long)]
-    dst: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Ignore errors
-    #[arg(short, long, action = ArgAction::SetTrue, default_value_t = false)]
-    ignore_error: bool,
-}
+    #[command(flatten)]
+    compile: CompileArgs,
 
-fn main() -> Result<()> {
-    // Setup logger
-    if env::var(ENV_LOG).is_err() {
-        env::set_var(ENV_LOG, ENV_LOG_DEFAULT);
-    }
-    pretty_env_logger::init_custom_env(ENV_LOG);
+    /// Format of log messages, `json` for one JSON object per line, filtered by the TUMETA_LOG environment variable
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t,
+        global = true
+    )]
+    log_format: LogFormat,
 
-    // Parse arguments
-    let args = Args::parse();
-
-    // Check src and dst paths
-    if !args.src.exists() {
-        bail!("Source path {} does not exist", args.src.to_string_lossy());
-    }
-    if args.dst.is_dir() {
-        bail!(
-            "Invalid destination path {}: destination could not be an existing directory",
-            args.dst.to_string_lossy()
-        );
-    }
-    let dst_parent = args
-        .dst
-        .parent()
-        .ok_or(eyre!("Failed to get parent path for dst path"))?;
-    if !dst_parent.exists() {
-        bail!(
-            "Parent path of the destination {} does not exist",
-            dst_parent.to_string_lossy()
-        );
-    }
+    /// Print shell completions to stdout and exit
+    #[arg(long, value_name = "SHELL", exclusive = true)]
+    generate_completions: Option<Shell>,
+}
 
-    info!(
-        "Searching for TOML manifests in {}",
-        args.src.to_string_lossy()
-    );
-    let manifest: ManifestCollection = jwalk::WalkDir::new(args.src)
-        .follow_links(true)
-        .into_iter()
-        .par_bridge()
-        .filter_map(|res| {
-            let entry = res.ok()?;
-            if entry.file_type().is_file() {
-                let path = entry.path();
-                if path.extension()?.to_ascii_lowercase() == "toml" {
-                    Some(path)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })
-        .map(|p| {
-            debug!("Parsing {}", p.to_string_lossy());
+#[derive(Subcommand)]
+enum Command {
+    /// Convert TOML manifests to a JSON collection, the default without a subcommand
+    Compile(CompileArgs),
+    /// Only parse and check TOML manifests, without writing anything
+    Validate(ValidateArgs),
+    /// Write the topic dependency graph in Graphviz DOT format
+    Graph(GraphArgs),
+    /// Print a table of topics with their types, security flags, package counts and dates
+    List(ListArgs),
+    /// Print names of all packages updated or removed by the topics, one per line
+    Packages(PackagesArgs),
+    /// Print all fields of a single topic
+    Show(ShowArgs),
+    /// Create a new topic manifest, prompting for fields not given as arguments
+    Add(AddArgs),
+}
 
-            let parsed = toml::from_str(&fs::read_to_string(&p)?).map_err(|e| {
-                Report::new(e).wrap_err(format!("Failed to parse {}", p.to_string_lossy()))
-            })?;
-            let name = p
-                .file_stem()
-                .ok_or(eyre!(
-                    "Invalid topic manifest filename: {}",
-                    p.to_string_lossy()
-                ))?
-                .to_string_lossy()
-                .to_string();
-            Ok((name, parsed))
+/// Command line interface, where `--generate-completions` needs no other arguments
+fn command() -> clap::Command {
+    Args::command()
+        .mut_arg("src", |arg| {
+            arg.required_unless_present("generate_completions")
         })
-        .filter_map(|r: Result<(String, Manifest), Report>| {
-            if let Err(e) = r {
-                error!("{:#}", e);
-                if ! args.ignore_error {
-                    panic!("Failed to parse source file(s)");
-                }
-                None
-            } else {
-                r.ok()
-            }
+        .mut_arg("dst", |arg| {
+            arg.required_unless_present("generate_completions")
         })
-        .collect::<BTreeMap<String, Manifest>>()
-        .into();
+}
 
-    // Check consistency of the file
-    let inconsistency = manifest.find_missing_topics();
-    for (topic, missing) in &inconsistency {
-        error!("Missing dependency for cumulative topic {}: {:?}", topic, missing);
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(report) => {
+            eprintln!("Error: {:?}", report);
+            error::exit_code(&report)
+        }
     }
-    if (! inconsistency.is_empty()) && (! args.ignore_error) {
-        bail!("Topic manifests are inconsistent, abort");
+}
+
+fn run() -> Result<()> {
+    // Parse arguments
+    let args = Args::from_arg_matches(&command().get_matches()).unwrap_or_else(|e| e.exit());
+
+    // Setup logger
+    if env::var(ENV_LOG).is_err() {
+        env::set_var(ENV_LOG, ENV_LOG_DEFAULT);
     }
+    logger::init(args.log_format, ENV_LOG);
 
-    // Write to dst file
-    info!(
-        "Writing {} entries to {}",
-        manifest.len(),
-        args.dst.to_string_lossy()
-    );
-    let out_file = fs::OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(args.dst)?;
-    let writer = BufWriter::new(out_file);
-    serde_json::to_writer_pretty(writer, &manifest)?;
-    info!("Done");
+    // Print shell completions
+    if let Some(shell) = args.generate_completions {
+        let mut cmd = command();
+        let name = cmd.get_name().to_string();
+        clap_complete::generate(shell, &mut cmd, name, &mut io::stdout());
+        return Ok(());
+    }
 
-    Ok(())
+    match args.command {
+        Some(Command::Compile(compile)) => cmd::compile::run(compile),
+        Some(Command::Validate(validate)) => cmd::validate::run(validate),
+        Some(Command::Graph(graph)) => cmd::graph::run(graph),
+        Some(Command::List(list)) => cmd::list::run(list),
+        Some(Command::Packages(packages)) => cmd::packages::run(packages),
+        Some(Command::Show(show)) => cmd::show::run(show),
+        Some(Command::Add(add)) => cmd::add::run(add),
+        None => cmd::compile::run(args.compile),
+    }
 }
@@ -0,0 +1,164 @@
+//! Machine-readable report of problems found in a run, for annotating files in CI
+
+use serde::Serialize;
+
+use topic_manifest::{LoadError, LoadWarning, TopicSources, ValidationIssue, ValidationReport};
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    /// A file failed to load as a manifest
+    ParseError,
+    /// A cumulative topic references topics missing from the collection, itself, or a cycle
+    MissingTopic,
+    /// A topic is defined twice, or listed twice in a cumulative topic
+    Duplicate,
+    /// A topic is usable, but violates a policy
+    Lint,
+    /// A file failed to be read
+    Io,
+}
+
+/// A single problem, located in a file or a topic if known
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub file: Option<PathBuf>,
+    pub topic: Option<String>,
+    pub kind: Kind,
+    pub message: String,
+    /// Line (1-based) of parse errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Column (1-based) of parse errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+/// Findings of a run, in the order they are found
+///
+/// Errors ignored with `--ignore-error` are recorded as warnings, so a run fails exactly when its
+/// report has errors.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+    /// Files of topics, to locate findings of topics
+    #[serde(skip)]
+    sources: TopicSources,
+}
+
+impl Report {
+    pub fn set_sources(&mut self, sources: TopicSources) {
+        self.sources = sources;
+    }
+
+    /// Record a finding of `topic`, located in the file defining it
+    pub fn push_topic(&mut self, severity: Severity, kind: Kind, topic: &str, message: String) {
+        self.findings.push(Finding {
+            severity,
+            file: self.sources.get(topic).cloned(),
+            topic: Some(topic.to_string()),
+            kind,
+            message,
+            line: None,
+            column: None,
+        });
+    }
+
+    /// Record errors of files failing to load, one finding per file
+    pub fn push_load_error(&mut self, error: &LoadError, severity: Severity) {
+        for error in error.get_errors() {
+            let (kind, topic) = match error {
+                LoadError::Io { .. } | LoadError::Write { .. } => (Kind::Io, None),
+                LoadError::DuplicateTopic { topic, .. } => (Kind::Duplicate, Some(topic.clone())),
+                _ => (Kind::ParseError, None),
+            };
+            let location = error.get_location();
+            self.findings.push(Finding {
+                severity,
+                file: Some(error.get_path().to_path_buf()),
+                topic,
+                kind,
+                message: error.to_string(),
+                line: location.map(|(line, _)| line),
+                column: location.map(|(_, column)| column),
+            });
+        }
+    }
+
+    /// Record the error failing a run, if it is an error loading manifests
+    pub fn push_error(&mut self, error: &eyre::Report) {
+        if let Some(Error::Load(error)) = error.downcast_ref::<Error>() {
+            self.push_load_error(error, Severity::Error);
+        }
+    }
+
+    pub fn push_load_warnings(&mut self, warnings: &[LoadWarning]) {
+        for warning in warnings {
+            let (kind, topic) = match warning {
+                LoadWarning::Skipped(error) => {
+                    self.push_load_error(error, Severity::Warning);
+                    continue;
+                }
+                LoadWarning::DanglingSymlink { .. } => (Kind::Io, None),
+                LoadWarning::DuplicateTopic { topic, .. } => (Kind::Duplicate, Some(topic.clone())),
+            };
+            self.findings.push(Finding {
+                severity: Severity::Warning,
+                file: Some(warning.get_path().to_path_buf()),
+                topic,
+                kind,
+                message: warning.to_string(),
+                line: None,
+                column: None,
+            });
+        }
+    }
+
+    /// Record consistency issues, errors as warnings if they are ignored
+    pub fn push_validation(&mut self, report: &ValidationReport, ignore_errors: bool) {
+        let error = if ignore_errors {
+            Severity::Warning
+        } else {
+            Severity::Error
+        };
+        let issues = report.errors.iter().map(|issue| (error, issue)).chain(
+            report
+                .warnings
+                .iter()
+                .map(|issue| (Severity::Warning, issue)),
+        );
+        for (severity, issue) in issues {
+            let kind = match issue {
+                ValidationIssue::MissingTopics { .. }
+                | ValidationIssue::SelfReference { .. }
+                | ValidationIssue::DependencyCycle { .. }
+                | ValidationIssue::InvalidExtends { .. } => Kind::MissingTopic,
+                ValidationIssue::DuplicateTopics { .. } => Kind::Duplicate,
+                _ => Kind::Lint,
+            };
+            self.push_topic(severity, kind, issue.get_topic(), issue.to_string());
+        }
+    }
+
+    /// Write the report as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        writer.flush()
+    }
+}
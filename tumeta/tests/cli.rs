@@ -0,0 +1,1641 @@
+use assert_cmd::Command;
+use serde::Deserialize;
+use tempfile::TempDir;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CONVENTIONAL: &str = r#"
+name.default = "KDE Updates (Winter 2023)"
+security = true
+caution.default = ""
+
+[packages]
+konsole = "23.04.1-1"
+"#;
+
+fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    for (name, content) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
+
+fn tumeta() -> Command {
+    let mut cmd = Command::cargo_bin("tumeta").unwrap();
+    cmd.env("TUMETA_LOG", "info");
+    cmd
+}
+
+#[test]
+fn test_error_order_is_stable() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let files: Vec<(String, &str)> = (0..16)
+        .map(|i| (format!("dir{}/broken-{:02}.toml", i % 4, i), "name = 1"))
+        .collect();
+    let files: Vec<(&str, &str)> = files.iter().map(|(n, c)| (n.as_str(), *c)).collect();
+    write_files(src.path(), &files);
+    write_files(src.path(), &[("good.toml", CONVENTIONAL)]);
+    write_files(dst.path(), &[("topics.json", "")]);
+
+    let run = || {
+        let output = tumeta()
+            .arg("--src")
+            .arg(src.path())
+            .arg("--dst")
+            .arg(dst.path().join("topics.json"))
+            .arg("--ignore-error")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    };
+
+    let first = run();
+    assert!(first.contains("broken-00.toml"));
+    assert!(first.contains("broken-15.toml"));
+    for _ in 0..3 {
+        assert_eq!(first, run());
+    }
+}
+
+#[test]
+fn test_emit_schema() {
+    let dir = TempDir::new().unwrap();
+    let schema_path = dir.path().join("schema.json");
+    tumeta()
+        .arg("--emit-schema")
+        .arg(&schema_path)
+        .assert()
+        .success();
+    let schema: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(schema_path).unwrap()).unwrap();
+    assert_eq!(schema["title"], "ManifestCollection");
+}
+
+#[test]
+fn test_generate_completions() {
+    let output = tumeta()
+        .args(["--generate-completions", "bash"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(script.contains("_tumeta()"));
+    assert!(script.contains("--src"));
+
+    tumeta()
+        .args(["--generate-completions", "bash", "--src", "a", "--dst", "b"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_streaming() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    write_files(src.path(), &[("nested/kde.toml", CONVENTIONAL)]);
+    write_files(dst.path(), &[("topics.json", "")]);
+    let dst_path = dst.path().join("topics.json");
+    tumeta()
+        .arg("--streaming")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&dst_path)
+        .assert()
+        .success();
+    let output = fs::read_to_string(&dst_path).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["kde"]["type"], "conventional");
+
+    // The destination is only replaced once all manifests are written
+    write_files(src.path(), &[("zz/broken.toml", "name.default = ")]);
+    tumeta()
+        .arg("--streaming")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&dst_path)
+        .assert()
+        .failure();
+    assert_eq!(fs::read_to_string(&dst_path).unwrap(), output);
+    assert_eq!(fs::read_dir(dst.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_invalid_locale_warning() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let manifest = CONVENTIONAL.replace("security", "name.zh_XNN = \"KDE 更新\"\nsecurity");
+    write_files(src.path(), &[("kde.toml", &manifest)]);
+    write_files(dst.path(), &[("topics.json", "")]);
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(r#"Invalid locales in topic kde: ["zh_XNN"]"#));
+}
+
+#[test]
+fn test_single_file_src() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", CONVENTIONAL),
+            ("other.toml", CONVENTIONAL),
+            ("README.md", ""),
+        ],
+    );
+    write_files(dst.path(), &[("topics.json", "")]);
+    tumeta()
+        .arg("--src")
+        .arg(src.path().join("kde.toml"))
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .assert()
+        .success();
+    let output: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dst.path().join("topics.json")).unwrap()).unwrap();
+    let topics: Vec<&String> = output.as_object().unwrap().keys().collect();
+    assert_eq!(topics, ["kde"]);
+
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path().join("README.md"))
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("is not a TOML manifest"), "{}", stderr);
+}
+
+#[test]
+fn test_parse_error_location() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    write_files(src.path(), &[("kde.toml", "\nname.default = 1\n")]);
+    write_files(dst.path(), &[("topics.json", "")]);
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("at line 2, column 16"), "{}", stderr);
+    assert!(stderr.contains("kde.toml"));
+    assert!(stderr.contains("kde.toml:2:16\n  |\n2 | name.default = 1\n  |                ^\n"));
+}
+
+#[test]
+fn test_lint_names() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[
+            ("kde-survey.toml", CONVENTIONAL),
+            ("gnome-45.toml", CONVENTIONAL),
+        ],
+    );
+    write_files(dst.path(), &[("topics.json", "")]);
+    let output = tumeta()
+        .arg("--lint-names")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(r#"Topic gnome-45 does not match its name "KDE Updates (Winter 2023)""#)
+    );
+    assert!(!stderr.contains("Topic kde-survey does not match"));
+}
+
+#[test]
+fn test_normalize() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let manifest = CONVENTIONAL.replace(
+        r#"caution.default = """#,
+        "caution.default = \"\"\"\n    Reboot required.\n    \"\"\"",
+    );
+    write_files(src.path(), &[("kde.toml", &manifest)]);
+    write_files(dst.path(), &[("topics.json", "")]);
+    let dst_path = dst.path().join("topics.json");
+    let run = |extra: &[&str]| {
+        let output = tumeta()
+            .args(extra)
+            .arg("--src")
+            .arg(src.path())
+            .arg("--dst")
+            .arg(&dst_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&dst_path).unwrap()).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        (json["kde"]["caution"]["default"].clone(), stderr)
+    };
+
+    let (caution, stderr) = run(&["--max-name-length", "10"]);
+    assert_eq!(caution, "Reboot required.");
+    assert!(stderr.contains("Topic kde: name.default is 25 characters long"));
+    let (caution, _) = run(&["--no-normalize"]);
+    assert_eq!(caution, "    Reboot required.\n    ");
+}
+
+#[test]
+fn test_arch_filter() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let rpi = CONVENTIONAL.replace("security", "arch = [\"arm64\"]\nsecurity");
+    let kde = format!(
+        "{}\n[arch_packages.arm64]\nkonsole = \"23.04.1-2\"",
+        CONVENTIONAL
+    );
+    write_files(src.path(), &[("kde.toml", &kde), ("rpi.toml", &rpi)]);
+    write_files(dst.path(), &[("topics.json", "")]);
+    let dst_path = dst.path().join("topics.json");
+    let output = |arch: &str| -> serde_json::Value {
+        tumeta()
+            .args(["--arch", arch])
+            .arg("--src")
+            .arg(src.path())
+            .arg("--dst")
+            .arg(&dst_path)
+            .assert()
+            .success();
+        serde_json::from_str(&fs::read_to_string(&dst_path).unwrap()).unwrap()
+    };
+    let topics = |json: &serde_json::Value| {
+        json.as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    let amd64 = output("amd64");
+    assert_eq!(topics(&amd64), ["kde"]);
+    assert_eq!(amd64["kde"]["packages"]["konsole"], "23.04.1-1");
+    let arm64 = output("arm64");
+    assert_eq!(topics(&arm64), ["kde", "rpi"]);
+    assert_eq!(arm64["kde"]["packages"]["konsole"], "23.04.1-2");
+    assert!(arm64["kde"].get("arch_packages").is_none());
+}
+
+#[test]
+fn test_create_dst() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    write_files(src.path(), &[("kde.toml", CONVENTIONAL)]);
+
+    let dst_path = dst.path().join("topics.json");
+    tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&dst_path)
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&dst_path).unwrap().contains("\"kde\""));
+
+    // A bare filename is relative to the current directory
+    tumeta()
+        .current_dir(dst.path())
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "out.json"])
+        .assert()
+        .success();
+    assert!(dst.path().join("out.json").is_file());
+
+    tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(dst.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_extensions() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", CONVENTIONAL),
+            ("gnome.TOPIC", CONVENTIONAL),
+            ("notes.txt", "Not a manifest"),
+        ],
+    );
+    let dst_path = dst.path().join("topics.json");
+    let topics = |extensions: &[&str]| {
+        let mut cmd = tumeta();
+        for ext in extensions {
+            cmd.args(["--ext", ext]);
+        }
+        cmd.arg("--src")
+            .arg(src.path())
+            .arg("--dst")
+            .arg(&dst_path)
+            .assert()
+            .success();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&dst_path).unwrap()).unwrap();
+        json.as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(topics(&[]), ["kde"]);
+    assert_eq!(topics(&["topic"]), ["gnome"]);
+    assert_eq!(topics(&["toml", ".topic"]), ["gnome", "kde"]);
+}
+
+#[test]
+fn test_stdio() {
+    let output = tumeta()
+        .args(["--src", "-", "--dst", "-", "--topic-name", "kde"])
+        .write_stdin(CONVENTIONAL)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["kde"]["name"]["default"], "KDE Updates (Winter 2023)");
+    // Logs stay on stderr
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Done"));
+
+    let output = tumeta()
+        .args(["--src", "-", "--dst", "-", "--topic-name", "kde"])
+        .write_stdin("name = 1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("<stdin>"));
+
+    let src = TempDir::new().unwrap();
+    write_files(src.path(), &[("kde.toml", CONVENTIONAL)]);
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "-"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["kde"].is_object());
+
+    for args in [
+        &["--src", "-", "--dst", "-"][..],
+        &[
+            "--src",
+            "-",
+            "--dst",
+            "-",
+            "--topic-name",
+            "kde",
+            "--streaming",
+        ],
+        &["--src", ".", "--dst", "-", "--topic-name", "kde"],
+    ] {
+        tumeta()
+            .args(args)
+            .write_stdin(CONVENTIONAL)
+            .assert()
+            .failure();
+    }
+}
+
+#[test]
+fn test_empty_manifest() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[("kde.toml", CONVENTIONAL), ("draft.toml", "# comment\n")],
+    );
+    let dst_path = dst.path().join("topics.json");
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&dst_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("draft.toml is empty"), "{}", stderr);
+
+    tumeta()
+        .arg("--skip-empty")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&dst_path)
+        .assert()
+        .success();
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&dst_path).unwrap()).unwrap();
+    assert_eq!(json.as_object().unwrap().len(), 1);
+}
+
+#[test]
+fn test_format() {
+    let mut outputs = Vec::new();
+    for format in ["json", "json-compact"] {
+        let output = tumeta()
+            .args([
+                "--src",
+                "-",
+                "--dst",
+                "-",
+                "--topic-name",
+                "kde",
+                "--format",
+                format,
+            ])
+            .write_stdin(CONVENTIONAL)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        outputs.push(String::from_utf8(output.stdout).unwrap());
+    }
+    assert!(outputs[0].contains('\n'));
+    assert!(!outputs[1].contains('\n'));
+    let pretty: serde_json::Value = serde_json::from_str(&outputs[0]).unwrap();
+    let compact: serde_json::Value = serde_json::from_str(&outputs[1]).unwrap();
+    assert_eq!(pretty, compact);
+
+    let output = tumeta()
+        .args([
+            "--src",
+            "-",
+            "--dst",
+            "-",
+            "--topic-name",
+            "kde",
+            "--format",
+            "yaml",
+        ])
+        .write_stdin(CONVENTIONAL)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let yaml = String::from_utf8(output.stdout).unwrap();
+    assert!(yaml.starts_with("kde:\n"), "{}", yaml);
+    assert!(yaml.contains("type: conventional"), "{}", yaml);
+    let output = tumeta()
+        .args([
+            "--src",
+            "-",
+            "--dst",
+            "-",
+            "--topic-name",
+            "kde",
+            "--format",
+            "msgpack",
+        ])
+        .write_stdin(CONVENTIONAL)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!output.stdout.is_empty());
+    assert!(serde_json::from_slice::<serde_json::Value>(&output.stdout).is_err());
+
+    let dst = TempDir::new().unwrap();
+    let output = tumeta()
+        .args(["--src", ".", "--format", "yaml", "--merge", "--dst"])
+        .arg(dst.path().join("manifest.yaml"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("only work with JSON"), "{}", stderr);
+    tumeta()
+        .args(["--src", ".", "--dst", "-", "--format", "toml"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_extends() {
+    let src = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[
+            ("base.toml", CONVENTIONAL),
+            (
+                "kde.toml",
+                &format!("extends = \"base\"\n{}dolphin = \"23.04.1\"", CONVENTIONAL),
+            ),
+        ],
+    );
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "-"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["kde"].get("extends"), None);
+    assert_eq!(
+        json["kde"]["packages"],
+        serde_json::json!({ "dolphin": "23.04.1", "konsole": "23.04.1-1" })
+    );
+
+    fs::remove_file(src.path().join("base.toml")).unwrap();
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "-"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Topic kde extends missing topic base"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_check() {
+    let src = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", CONVENTIONAL),
+            (
+                "cumulative.toml",
+                "name.default = \"Cumulative\"\ntopics = [\"kde\"]",
+            ),
+        ],
+    );
+    let output = tumeta()
+        .arg("--check")
+        .arg("--src")
+        .arg(src.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Parsed 2 topics"));
+    assert_eq!(fs::read_dir(src.path()).unwrap().count(), 2);
+
+    write_files(
+        src.path(),
+        &[(
+            "cumulative.toml",
+            "name.default = \"Cumulative\"\ntopics = [\"kde\", \"gnome\"]",
+        )],
+    );
+    let output = tumeta()
+        .arg("--check")
+        .arg("--src")
+        .arg(src.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Parsed 2 topics"));
+    assert!(stderr.contains("Missing dependency for cumulative topic cumulative: [\"gnome\"]"));
+
+    let empty = TempDir::new().unwrap();
+    let output = tumeta()
+        .arg("--check")
+        .arg("--src")
+        .arg(empty.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Parsed 0 topics"));
+    assert!(stderr.contains("No topic manifests found"));
+
+    tumeta()
+        .arg("--check")
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "topics.json"])
+        .assert()
+        .failure();
+    tumeta().arg("--src").arg(src.path()).assert().failure();
+}
+
+#[test]
+fn test_multiple_parse_errors() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", CONVENTIONAL),
+            ("broken-a.toml", "name = 1"),
+            ("nested/broken-b.toml", "security = \"yes\""),
+        ],
+    );
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("broken-a.toml"));
+    assert!(stderr.contains("broken-b.toml"));
+    assert!(stderr.contains("2 of 3 manifests in"));
+    assert!(!stderr.contains("panicked"));
+    assert!(!dst.path().join("topics.json").exists());
+
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .arg("--ignore-error")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value =
+        serde_json::from_slice(&fs::read(dst.path().join("topics.json")).unwrap()).unwrap();
+    assert_eq!(json.as_object().unwrap().len(), 1);
+}
+
+#[test]
+fn test_packages_subcommand() {
+    let src = TempDir::new().unwrap();
+    let qt = "name.default = \"Qt\"\nsecurity = false\ncaution.default = \"\"\n[packages]\nqt5-base = \"5.15.11\"\npykde = false";
+    write_files(
+        src.path(),
+        &[("kde.toml", CONVENTIONAL), ("nested/qt.toml", qt)],
+    );
+    let output = tumeta().arg("packages").arg(src.path()).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "konsole\npykde\nqt5-base\n"
+    );
+
+    tumeta()
+        .arg("packages")
+        .arg(src.path().join("missing"))
+        .assert()
+        .failure();
+    tumeta()
+        .args(["--src", ".", "--dst", "-", "packages", "."])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_exclude_include() {
+    let src = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", CONVENTIONAL),
+            ("templates/conventional.toml", "name.default = \"\""),
+            ("templates/nested/example.toml", CONVENTIONAL),
+            ("taplo.toml", "[formatting]\nalign_entries = true"),
+        ],
+    );
+    let topics = |args: &[&str]| {
+        let output = tumeta()
+            .arg("--src")
+            .arg(src.path())
+            .args(["--dst", "-"])
+            .args(args)
+            .output()
+            .unwrap();
+        if !output.status.success() {
+            return None;
+        }
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        Some(
+            json.as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    assert_eq!(topics(&[]), None);
+    assert_eq!(
+        topics(&["--exclude", "templates/**", "--exclude", "taplo.toml"]).unwrap(),
+        ["kde"]
+    );
+    assert_eq!(
+        topics(&[
+            "--exclude",
+            "templates/**",
+            "--exclude",
+            "taplo.toml",
+            "--include",
+            "templates/nested/**"
+        ])
+        .unwrap(),
+        ["example", "kde"]
+    );
+}
+
+#[test]
+fn test_duplicate_topics() {
+    let src = TempDir::new().unwrap();
+    let testing = CONVENTIONAL.replace("23.04.1-1", "23.04.2-1");
+    write_files(
+        src.path(),
+        &[
+            ("stable/kde.toml", CONVENTIONAL),
+            ("testing/kde.toml", &testing),
+        ],
+    );
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "-"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Duplicate topic kde in"));
+    assert!(stderr.contains(&format!("testing{}kde.toml", std::path::MAIN_SEPARATOR)));
+    assert!(stderr.contains(&format!("stable{}kde.toml", std::path::MAIN_SEPARATOR)));
+
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "-", "--ignore-error"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Duplicate topic kde in"));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["kde"]["packages"]["konsole"], "23.04.2-1");
+}
+
+#[test]
+fn test_subcommands() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let cumulative = "name.default = \"Cumulative\"\ntopics = [\"kde\"]";
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", CONVENTIONAL),
+            ("nested/2023H3.toml", cumulative),
+        ],
+    );
+
+    // The flat arguments and `compile` produce the same output
+    let flat = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "-"])
+        .output()
+        .unwrap();
+    let compile = tumeta()
+        .args(["compile", "-s"])
+        .arg(src.path())
+        .args(["-d", "-"])
+        .output()
+        .unwrap();
+    assert!(compile.status.success());
+    assert_eq!(compile.stdout, flat.stdout);
+
+    tumeta()
+        .args(["validate", "-s"])
+        .arg(src.path())
+        .assert()
+        .success();
+
+    let graph = tumeta()
+        .args(["graph", "-s"])
+        .arg(src.path())
+        .args(["-d", "-"])
+        .output()
+        .unwrap();
+    assert!(graph.status.success());
+    let graph = String::from_utf8(graph.stdout).unwrap();
+    assert!(graph.starts_with("digraph topics {"));
+    assert!(graph.contains("\"2023H3\" -> \"kde\""));
+
+    tumeta()
+        .args(["list", "-s"])
+        .arg(src.path())
+        .assert()
+        .success();
+
+    // Problems fail every subcommand reading the sources
+    write_files(
+        src.path(),
+        &[(
+            "nested/2023H3.toml",
+            "name.default = \"Cumulative\"\ntopics = [\"qt\"]",
+        )],
+    );
+    tumeta()
+        .args(["validate", "-s"])
+        .arg(src.path())
+        .assert()
+        .failure();
+    write_files(src.path(), &[("broken.toml", "name = 1")]);
+    for args in [&["list", "-s"][..], &["graph", "-d", "-", "-s"]] {
+        tumeta().args(args).arg(src.path()).assert().failure();
+    }
+    tumeta()
+        .args(["graph", "--ignore-error", "-d"])
+        .arg(dst.path().join("topics.dot"))
+        .arg("-s")
+        .arg(src.path())
+        .assert()
+        .success();
+    assert!(dst.path().join("topics.dot").exists());
+    tumeta()
+        .args(["validate", "--dst", "-", "-s"])
+        .arg(src.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_diff_against() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let qt = "topic = \"qt-5.15\"\nname.default = \"Qt\"\nsecurity = false\ncaution.default = \"\"\n[packages]\nqt5-base = \"5.15.11\"";
+    write_files(src.path(), &[("kde.toml", CONVENTIONAL), ("qt.toml", qt)]);
+    let old = dst.path().join("topics.json");
+    tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&old)
+        .assert()
+        .success();
+    let diff = |src: &Path| {
+        tumeta()
+            .arg("--src")
+            .arg(src)
+            .arg("--diff-against")
+            .arg(&old)
+            .output()
+            .unwrap()
+    };
+
+    let output = diff(src.path());
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    fs::remove_file(src.path().join("qt.toml")).unwrap();
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", &CONVENTIONAL.replace("23.04.1-1", "23.04.2-1")),
+            ("mesa.toml", &CONVENTIONAL.replace("konsole", "mesa")),
+        ],
+    );
+    let output = diff(src.path());
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "+ mesa\n- qt-5.15\n~ kde\n"
+    );
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("1 topics added, 1 removed, 1 changed"));
+    // Nothing is written
+    let json: serde_json::Value = serde_json::from_slice(&fs::read(&old).unwrap()).unwrap();
+    assert_eq!(json["kde"]["packages"]["konsole"], "23.04.1-1");
+
+    tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--diff-against")
+        .arg(dst.path().join("missing.json"))
+        .assert()
+        .failure();
+    tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "-", "--diff-against"])
+        .arg(&old)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_diff() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let topics = dst.path().join("topics.json");
+    let changes = dst.path().join("changes.json");
+    write_files(src.path(), &[("kde.toml", CONVENTIONAL)]);
+
+    // A missing destination is compared as empty
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&topics)
+        .arg("--diff")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("1 topics added, 0 removed, 0 changed"));
+    assert!(topics.exists());
+
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", &CONVENTIONAL.replace("23.04.1-1", "23.04.2-1")),
+            ("mesa.toml", &CONVENTIONAL.replace("konsole", "mesa")),
+        ],
+    );
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&topics)
+        .arg("--diff-only")
+        .arg("--diff-out")
+        .arg(&changes)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("1 topics added, 0 removed, 1 changed"));
+    assert!(stderr.contains("+ mesa"));
+    assert!(stderr.contains("~ kde"));
+    assert!(stderr.contains("~ konsole 23.04.1-1 -> 23.04.2-1"));
+    let json: serde_json::Value = serde_json::from_slice(&fs::read(&changes).unwrap()).unwrap();
+    assert_eq!(json["added"], serde_json::json!(["mesa"]));
+    assert_eq!(json["changed"], serde_json::json!(["kde"]));
+    assert_eq!(
+        json["packages"]["kde"]["updated"]["konsole"],
+        serde_json::json!(["23.04.1-1", "23.04.2-1"])
+    );
+    // Nothing is written with --diff-only
+    let json: serde_json::Value = serde_json::from_slice(&fs::read(&topics).unwrap()).unwrap();
+    assert_eq!(json["kde"]["packages"]["konsole"], "23.04.1-1");
+    assert!(json.get("mesa").is_none());
+
+    tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .args(["--dst", "-", "--diff"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_log_format_json() {
+    let src = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[(
+            "cu.toml",
+            "name.default = \"Cu\"\ntopics = [\"kde\", \"qt\"]",
+        )],
+    );
+    let output = tumeta()
+        .env("TUMETA_LOG", "error")
+        .arg("validate")
+        .arg("--src")
+        .arg(src.path())
+        .args(["--log-format", "json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<serde_json::Value> = stderr
+        .lines()
+        .take_while(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    // Info messages are filtered out by TUMETA_LOG
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["level"], "ERROR");
+    assert_eq!(lines[0]["topic"], "cu");
+    assert_eq!(lines[0]["missing"], serde_json::json!(["kde", "qt"]));
+    assert!(lines[0]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Missing dependency for cumulative topic cu"));
+}
+
+#[test]
+fn test_merge() {
+    let stable = TempDir::new().unwrap();
+    let testing = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let dst_path = dst.path().join("topics.json");
+    let qt = "name.default = \"Qt\"\nsecurity = false\ncaution.default = \"\"\n[packages]\nqt5-base = \"5.15.11\"";
+    write_files(
+        stable.path(),
+        &[("kde.toml", CONVENTIONAL), ("qt.toml", qt)],
+    );
+    write_files(
+        testing.path(),
+        &[
+            ("kde.toml", &CONVENTIONAL.replace("23.04.1-1", "23.04.2-1")),
+            (
+                "all.toml",
+                "name.default = \"All\"\ntopics = [\"kde\", \"qt\"]",
+            ),
+        ],
+    );
+    let run = |src: &Path, args: &[&str]| {
+        tumeta()
+            .arg("--src")
+            .arg(src)
+            .arg("--dst")
+            .arg(&dst_path)
+            .args(args)
+            .assert()
+    };
+    let topics = || {
+        let json: serde_json::Value =
+            serde_json::from_slice(&fs::read(&dst_path).unwrap()).unwrap();
+        json.as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    // Nothing to merge into yet
+    run(stable.path(), &["--merge"]).success();
+    assert_eq!(topics(), ["kde", "qt"]);
+    // qt is only in the destination, so testing alone is inconsistent
+    run(testing.path(), &[]).failure();
+    run(testing.path(), &["--merge"]).success();
+    assert_eq!(topics(), ["all", "kde", "qt"]);
+    let json: serde_json::Value = serde_json::from_slice(&fs::read(&dst_path).unwrap()).unwrap();
+    assert_eq!(json["kde"]["packages"]["konsole"], "23.04.2-1");
+
+    // Nothing is pruned while files are skipped, then topics of removed files are
+    fs::remove_file(testing.path().join("all.toml")).unwrap();
+    write_files(testing.path(), &[("broken.toml", "name = 1")]);
+    let assert = run(testing.path(), &["--merge", "--prune", "--ignore-error"]).success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(
+        stderr.contains("Not pruning the destination file"),
+        "{}",
+        stderr
+    );
+    assert_eq!(topics(), ["all", "kde", "qt"]);
+    fs::remove_file(testing.path().join("broken.toml")).unwrap();
+    run(testing.path(), &["--merge", "--prune"]).success();
+    assert_eq!(topics(), ["kde"]);
+
+    run(testing.path(), &["--prune"]).failure();
+    tumeta()
+        .arg("--src")
+        .arg(stable.path())
+        .args(["--dst", "-", "--merge"])
+        .assert()
+        .failure();
+    fs::write(&dst_path, "not json").unwrap();
+    run(stable.path(), &["--merge"]).failure();
+}
+
+#[test]
+fn test_list() {
+    let src = TempDir::new().unwrap();
+    let dated = CONVENTIONAL.replace(
+        "caution.default = \"\"",
+        "caution.default = \"\"\ndate = 2023-12-01",
+    );
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", &dated),
+            ("nested/2023H3.toml", "name.default = \"Cumulative\"\ntopics = [\"kde\", \"qt\"]"),
+            ("qt.toml", "name.default = \"Qt\"\nsecurity = false\ncaution.default = \"\"\n[packages]\nqt5-base = \"5.15.11\"\npykde = false"),
+        ],
+    );
+    let output = tumeta()
+        .args(["list", "-s"])
+        .arg(src.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "TOPIC   TYPE          SECURITY  PACKAGES  DATE
+2023H3  cumulative    -         2         -
+kde     conventional  yes       1         2023-12-01
+qt      conventional  no        2         -
+"
+    );
+
+    let output = tumeta()
+        .args(["list", "--format", "json", "-s"])
+        .arg(src.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([
+            {"topic": "2023H3", "type": "cumulative", "security": null, "packages": 2, "date": null},
+            {"topic": "kde", "type": "conventional", "security": true, "packages": 1, "date": "2023-12-01"},
+            {"topic": "qt", "type": "conventional", "security": false, "packages": 2, "date": null},
+        ])
+    );
+}
+
+#[test]
+fn test_show() {
+    let src = TempDir::new().unwrap();
+    let kde = CONVENTIONAL
+        .replace(
+            "security = true",
+            "name.zh_CN = \"KDE 更新\"\nsecurity = true",
+        )
+        .replace("[packages]", "[packages]\npykde = false");
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", &kde),
+            (
+                "2023H3.toml",
+                "name.default = \"Cumulative\"\ntopics = [\"kde\", \"qt\"]",
+            ),
+        ],
+    );
+    let show = |topic: &str| {
+        tumeta()
+            .args(["show", "-s"])
+            .arg(src.path())
+            .args(["--topic", topic])
+            .output()
+            .unwrap()
+    };
+
+    let output = show("kde");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Topic:         kde
+Type:          conventional
+Name:          KDE Updates (Winter 2023)
+Name (zh-CN):  KDE 更新
+Security:      yes
+Caution:
+
+Packages:
+    konsole  23.04.1-1
+    pykde    (removed)
+"
+    );
+
+    let output = show("2023H3");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Topic:  2023H3
+Type:   cumulative
+Name:   Cumulative
+
+Topics:
+    kde
+    qt
+"
+    );
+
+    let output = show("qt");
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Topic qt is not found"));
+}
+
+/// Format of `--report-json`, which CI scripts depend on
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Report {
+    findings: Vec<Finding>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Finding {
+    severity: String,
+    file: Option<PathBuf>,
+    topic: Option<String>,
+    kind: String,
+    message: String,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+#[test]
+fn test_report_json() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let report_path = dst.path().join("report.json");
+    let read_report =
+        || -> Report { serde_json::from_slice(&fs::read(&report_path).unwrap()).unwrap() };
+    write_files(
+        src.path(),
+        &[
+            ("broken.toml", "name.default = \"Broken\"\nsecurity = maybe"),
+            (
+                "2023H3.toml",
+                "name.default = \"Cumulative\"\ntopics = [\"kde\", \"qt\"]",
+            ),
+            ("kde.toml", CONVENTIONAL),
+        ],
+    );
+
+    // Written even if the run fails
+    tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .arg("--report-json")
+        .arg(&report_path)
+        .assert()
+        .failure();
+    let report = read_report();
+    assert_eq!(report.findings.len(), 1);
+    let finding = &report.findings[0];
+    assert_eq!(finding.severity, "error");
+    assert_eq!(finding.kind, "parse-error");
+    assert_eq!(
+        finding.file.as_deref(),
+        Some(src.path().join("broken.toml").as_path())
+    );
+    assert_eq!(finding.topic, None);
+    assert_eq!((finding.line, finding.column), (Some(2), Some(12)));
+
+    // Ignored errors are warnings
+    tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .args(["--ignore-error", "--report-json"])
+        .arg(&report_path)
+        .assert()
+        .success();
+    let report = read_report();
+    assert_eq!(report.findings.len(), 2);
+    assert!(report.findings.iter().all(|f| f.severity == "warning"));
+    assert_eq!(report.findings[0].kind, "parse-error");
+    assert_eq!(report.findings[1].kind, "missing-topic");
+
+    fs::remove_file(src.path().join("broken.toml")).unwrap();
+    tumeta()
+        .args(["validate", "--src"])
+        .arg(src.path())
+        .arg("--report-json")
+        .arg(&report_path)
+        .assert()
+        .failure();
+    let report = read_report();
+    assert_eq!(report.findings.len(), 1);
+    let finding = &report.findings[0];
+    assert_eq!(finding.severity, "error");
+    assert_eq!(finding.kind, "missing-topic");
+    assert_eq!(finding.topic.as_deref(), Some("2023H3"));
+    assert_eq!(
+        finding.file.as_deref(),
+        Some(src.path().join("2023H3.toml").as_path())
+    );
+    assert!(finding.message.contains("[\"qt\"]"));
+    assert_eq!((finding.line, finding.column), (None, None));
+}
+
+#[test]
+fn test_exit_codes() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let compile = || {
+        let mut cmd = tumeta();
+        cmd.arg("--src")
+            .arg(src.path())
+            .arg("--dst")
+            .arg(dst.path().join("topics.json"));
+        cmd
+    };
+
+    write_files(
+        src.path(),
+        &[("kde.toml", "name.default = \"KDE\"\nsecurity = maybe")],
+    );
+    compile().assert().code(65);
+
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", CONVENTIONAL),
+            (
+                "2023H3.toml",
+                "name.default = \"Cumulative\"\ntopics = [\"kde\", \"qt\"]",
+            ),
+        ],
+    );
+    compile().assert().code(3);
+    tumeta()
+        .args(["validate", "--src"])
+        .arg(src.path())
+        .assert()
+        .code(3);
+
+    fs::remove_file(src.path().join("2023H3.toml")).unwrap();
+    tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--diff-against")
+        .arg(dst.path().join("missing.json"))
+        .assert()
+        .code(74);
+
+    // Other failures
+    tumeta()
+        .arg("--src")
+        .arg(dst.path().join("missing"))
+        .arg("--dst")
+        .arg(dst.path().join("topics.json"))
+        .assert()
+        .code(1);
+    // Usage errors of clap, distinct from all of the above
+    tumeta().arg("--no-such-option").assert().code(2);
+}
+
+#[test]
+fn test_add() {
+    let src = TempDir::new().unwrap();
+    tumeta()
+        .args(["add", "-s"])
+        .arg(src.path())
+        .args([
+            "-t",
+            "kde-20231201",
+            "--name",
+            "KDE Updates",
+            "--locale",
+            "zh_CN=KDE 更新",
+        ])
+        .args([
+            "--security",
+            "--package",
+            "konsole=23.04.1-1",
+            "--package",
+            "pykde=false",
+        ])
+        .assert()
+        .success();
+    let content = fs::read_to_string(src.path().join("kde-20231201.toml")).unwrap();
+    assert_eq!(
+        content,
+        r#"name.default = "KDE Updates"
+name.zh-CN = "KDE 更新"
+security = true
+caution.default = ""
+
+[packages]
+konsole = "23.04.1-1"
+pykde = false
+"#
+    );
+    let parsed: topic_manifest::Manifest = toml::from_str(&content).unwrap();
+    assert_eq!(parsed.to_toml_string(), content);
+
+    // Prompt for all fields without --topic
+    tumeta()
+        .args(["add", "-s"])
+        .arg(src.path())
+        .write_stdin("2023H3\ncumulative\nCumulative\n\nkde-20231201\n\n")
+        .assert()
+        .success();
+    let content = fs::read_to_string(src.path().join("2023H3.toml")).unwrap();
+    assert_eq!(
+        content,
+        "name.default = \"Cumulative\"\ntopics = [\"kde-20231201\"]\n"
+    );
+    tumeta()
+        .args(["validate", "-s"])
+        .arg(src.path())
+        .assert()
+        .success();
+
+    // Existing topics are not overwritten
+    tumeta()
+        .args(["add", "-s"])
+        .arg(src.path())
+        .args(["-t", "2023H3", "--name", "Other"])
+        .assert()
+        .failure();
+    tumeta()
+        .args(["add", "-s"])
+        .arg(src.path())
+        .write_stdin("incomplete\n")
+        .assert()
+        .failure();
+    tumeta()
+        .args(["add", "-s"])
+        .arg(src.path())
+        .args([
+            "-t",
+            "qt",
+            "--name",
+            "Qt",
+            "--type",
+            "cumulative",
+            "--security",
+        ])
+        .assert()
+        .failure();
+    let mut files: Vec<_> = fs::read_dir(src.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["2023H3.toml", "kde-20231201.toml"]);
+}
+
+#[test]
+fn test_jobs() {
+    let src = TempDir::new().unwrap();
+    let files: Vec<(String, &str)> = (0..8)
+        .map(|i| (format!("broken-{}.toml", i), "name = 1"))
+        .collect();
+    let files: Vec<(&str, &str)> = files.iter().map(|(n, c)| (n.as_str(), *c)).collect();
+    write_files(src.path(), &files);
+    write_files(src.path(), &[("good.toml", CONVENTIONAL)]);
+
+    let run = |jobs: Option<&str>, env: Option<&str>| {
+        let mut cmd = tumeta();
+        cmd.env("TUMETA_LOG", "debug")
+            .arg("validate")
+            .arg("-s")
+            .arg(src.path());
+        if let Some(jobs) = jobs {
+            cmd.arg("--jobs").arg(jobs);
+        }
+        match env {
+            Some(env) => cmd.env("TUMETA_JOBS", env),
+            None => cmd.env_remove("TUMETA_JOBS"),
+        };
+        let output = cmd.output().unwrap();
+        assert_eq!(output.status.code(), Some(65));
+        String::from_utf8(output.stderr).unwrap()
+    };
+    let errors = |stderr: &str| -> Vec<String> {
+        stderr
+            .lines()
+            .filter(|line| line.contains("broken-"))
+            .map(String::from)
+            .collect()
+    };
+
+    let sequential = run(Some("1"), None);
+    assert!(sequential.contains("Parsing jobs: 1"));
+    assert_eq!(errors(&sequential).len(), 8);
+    let parallel = run(Some("4"), None);
+    assert!(parallel.contains("Parsing jobs: 4"));
+    assert_eq!(errors(&parallel), errors(&sequential));
+    let from_env = run(None, Some("2"));
+    assert!(from_env.contains("Parsing jobs: 2"));
+    assert_eq!(errors(&from_env), errors(&sequential));
+    // Arguments take precedence over the environment
+    assert!(run(Some("1"), Some("2")).contains("Parsing jobs: 1"));
+
+    tumeta()
+        .arg("validate")
+        .arg("-s")
+        .arg(src.path())
+        .arg("--jobs")
+        .arg("0")
+        .assert()
+        .failure();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch() {
+    use std::process::{Command as StdCommand, Stdio};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let dst_file = dst.path().join("topics.json");
+    write_files(src.path(), &[("kde.toml", CONVENTIONAL)]);
+
+    let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("tumeta"))
+        .env("TUMETA_LOG", "info")
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&dst_file)
+        .arg("--watch")
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let wait_for = |f: &dyn Fn(&str) -> bool| {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(20) {
+            if f(&fs::read_to_string(&dst_file).unwrap_or_default()) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        false
+    };
+
+    assert!(wait_for(&|output| output.contains("23.04.1-1")));
+    // Errors while regenerating keep the watcher running and the previous output
+    write_files(src.path(), &[("broken.toml", "name = 1")]);
+    thread::sleep(Duration::from_secs(1));
+    fs::remove_file(src.path().join("broken.toml")).unwrap();
+    write_files(
+        src.path(),
+        &[("kde.toml", &CONVENTIONAL.replace("23.04.1-1", "23.08.0"))],
+    );
+    assert!(wait_for(&|output| output.contains("23.08.0")));
+    assert!(child.try_wait().unwrap().is_none());
+
+    let status = StdCommand::new("kill")
+        .arg("-INT")
+        .arg(child.id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+}
+
+#[test]
+fn test_stats() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    write_files(
+        src.path(),
+        &[
+            ("kde.toml", CONVENTIONAL),
+            (
+                "2023H3.toml",
+                "name.default = \"Cumulative\"\ntopics = [\"kde\"]",
+            ),
+        ],
+    );
+    let dst_file = dst.path().join("topics.json");
+    let output = tumeta()
+        .arg("--src")
+        .arg(src.path())
+        .arg("--dst")
+        .arg(&dst_file)
+        .arg("--stats")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let size = fs::read(&dst_file).unwrap().len();
+    assert!(stderr.contains(&format!(
+        "Stats: 2 topics, 1 packages, {} bytes as JSON",
+        size
+    )));
+    assert!(stderr.contains("kde: 1 packages, "));
+    assert!(stderr.contains("2023H3: 0 packages, "));
+}